@@ -4,9 +4,10 @@
 //! terminal sessions under various conditions to help identify bottlenecks
 //! and track performance regressions.
 
-use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
 use replay_rs::{clean_for_display, Player, Recorder};
 use std::fs::{self, File};
+use std::hint::black_box;
 use std::io::Write;
 use std::process::Command;
 use std::time::SystemTime;
@@ -71,7 +72,7 @@ fn bench_recording_commands(c: &mut Criterion) {
 
             let recorder = Recorder::new(&output_file, &timing_file).unwrap();
             let mut cmd = Command::new("seq");
-            cmd.args(&["1", "100"]);
+            cmd.args(["1", "100"]);
 
             let result = recorder.record_command(cmd, false);
             cleanup_bench_files(&[&output_file, &timing_file]);
@@ -124,7 +125,7 @@ fn bench_replay_performance(c: &mut Criterion) {
 
         let recorder = Recorder::new(&output_file, &timing_file).unwrap();
         let mut cmd = Command::new("seq");
-        cmd.args(&["1", "50"]);
+        cmd.args(["1", "50"]);
         recorder.record_command(cmd, false).unwrap();
 
         (output_file, timing_file)
@@ -273,7 +274,7 @@ fn bench_memory_patterns(c: &mut Criterion) {
 
             let recorder = Recorder::new(&output_file, &timing_file).unwrap();
             let mut cmd = Command::new("seq");
-            cmd.args(&["1", "1000"]); // Generate 1000 lines
+            cmd.args(["1", "1000"]); // Generate 1000 lines
 
             let result = recorder.record_command(cmd, false);
             cleanup_bench_files(&[&output_file, &timing_file]);
@@ -314,7 +315,7 @@ fn bench_concurrent_operations(c: &mut Criterion) {
 
                         let recorder = Recorder::new(&output_file, &timing_file).unwrap();
                         let mut cmd = Command::new("echo");
-                        cmd.arg(&format!("Concurrent test {}", i));
+                        cmd.arg(format!("Concurrent test {}", i));
 
                         let result = recorder.record_command(cmd, false);
                         cleanup_bench_files(&[&output_file, &timing_file]);
@@ -348,7 +349,7 @@ fn bench_session_sizes(c: &mut Criterion) {
 
                     let recorder = Recorder::new(&output_file, &timing_file).unwrap();
                     let mut cmd = Command::new("seq");
-                    cmd.args(&["1", &lines.to_string()]);
+                    cmd.args(["1", &lines.to_string()]);
 
                     let result = recorder.record_command(cmd, false);
                     cleanup_bench_files(&[&output_file, &timing_file]);
@@ -0,0 +1,723 @@
+//! asciicast support
+//!
+//! asciicast is the JSON based format used by asciinema. Three versions exist
+//! and all of them can be read into a [`Session`]:
+//!
+//! - **v1**: a single JSON document whose `stdout` array holds
+//!   `[delay, data]` pairs, each delay relative to the previous frame
+//! - **v2**: newline-delimited JSON, a header object on the first line followed
+//!   by one `[time, code, data]` array per event, where `time` is the number of
+//!   seconds since the start of the recording
+//! - **v3**: like v2, but event times are intervals since the previous event,
+//!   the terminal size lives in a `term` object, exit status events (`x`) are
+//!   added and lines starting with `#` are comments
+//!
+//! Recordings are written as v2, which every asciinema release can play. See
+//! <https://docs.asciinema.org/manual/asciicast/v2/>.
+
+use crate::gzip;
+use crate::session::{
+    create_file, open_file, seconds, Event, EventKind, EventStream, EventWriter, Metadata, Session,
+    SessionFiles, SessionFormat, DEFAULT_HEIGHT, DEFAULT_WIDTH,
+};
+use crate::utf8::Utf8Decoder;
+use anyhow::{anyhow, Result};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::io::{BufRead, Read, Write};
+use std::path::Path;
+use std::time::Duration;
+
+/// The asciicast format as a [`SessionFormat`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AsciicastFormat;
+
+impl SessionFormat for AsciicastFormat {
+    fn name(&self) -> &'static str {
+        "asciicast"
+    }
+
+    fn read(&self, files: &SessionFiles) -> Result<Session> {
+        read(open_file(&files.data, "asciicast")?)
+    }
+
+    fn write(&self, session: &Session, files: &SessionFiles) -> Result<()> {
+        write(session, create_file(&files.data, "output")?)
+    }
+
+    fn events(&self, files: &SessionFiles) -> Result<EventStream> {
+        Ok(match open(open_file(&files.data, "asciicast")?)? {
+            Opened::Whole(session) => {
+                EventStream::new(session.metadata, session.events.into_iter().map(Ok))
+            }
+            Opened::Lines(metadata, events) => EventStream::new(metadata, events),
+        })
+    }
+
+    fn writer(&self, metadata: &Metadata, files: &SessionFiles) -> Result<Box<dyn EventWriter>> {
+        Ok(Box::new(AsciicastWriter::new(
+            create_file(&files.data, "output")?,
+            metadata,
+        )?))
+    }
+}
+
+/// Read an asciicast v1, v2 or v3 recording into a [`Session`]
+///
+/// Events with codes the format version does not define are ignored as the
+/// specification requires.
+pub fn read<R: BufRead>(reader: R) -> Result<Session> {
+    match open(reader)? {
+        Opened::Whole(session) => Ok(session),
+        Opened::Lines(metadata, events) => Ok(Session {
+            metadata,
+            events: events.collect::<Result<_>>()?,
+        }),
+    }
+}
+
+/// A recording whose header has been read
+enum Opened<R: BufRead> {
+    /// v1 recordings are a single JSON document and are read whole
+    Whole(Session),
+    /// v2 and v3 events are read one line at a time
+    Lines(Metadata, Events<R>),
+}
+
+/// Read the header of a recording
+fn open<R: BufRead>(mut reader: R) -> Result<Opened<R>> {
+    let mut first_line = String::new();
+    let mut line_number = 0;
+    while first_line.trim().is_empty() {
+        first_line.clear();
+        line_number += 1;
+        let bytes_read = reader
+            .read_line(&mut first_line)
+            .map_err(|e| anyhow!("Failed to read asciicast file: {}", e))?;
+        if bytes_read == 0 {
+            return Err(anyhow!("Empty asciicast file"));
+        }
+    }
+
+    let header = match serde_json::from_str::<Value>(&first_line) {
+        Ok(header) => header,
+        Err(_) => {
+            // Only v1 spreads its single JSON document over several lines
+            let mut document = first_line;
+            reader
+                .read_to_string(&mut document)
+                .map_err(|e| anyhow!("Failed to read asciicast file: {}", e))?;
+            let document: Value = serde_json::from_str(&document)
+                .map_err(|e| anyhow!("Invalid asciicast file: {}", e))?;
+            return read_v1(&document).map(Opened::Whole);
+        }
+    };
+
+    match version(&header)? {
+        1 => read_v1(&header).map(Opened::Whole),
+        version @ (2 | 3) => Ok(Opened::Lines(
+            parse_header(&header)?,
+            Events {
+                lines: reader.lines(),
+                line_number,
+                version,
+                time: Duration::ZERO,
+                done: false,
+            },
+        )),
+        version => Err(anyhow!("Unsupported asciicast version: {}", version)),
+    }
+}
+
+/// Lazily parses the event lines of a v2 or v3 recording
+struct Events<R: BufRead> {
+    lines: std::io::Lines<R>,
+    line_number: usize,
+    version: u64,
+    time: Duration,
+    done: bool,
+}
+
+impl<R: BufRead> Events<R> {
+    fn read_event(&mut self) -> Result<Option<Event>> {
+        let relative = self.version == 3;
+        for line in self.lines.by_ref() {
+            let line = line.map_err(|e| anyhow!("Failed to read asciicast file: {}", e))?;
+            self.line_number += 1;
+
+            let line = line.trim();
+            if line.is_empty() || (relative && line.starts_with('#')) {
+                continue;
+            }
+
+            let event = parse_event(line, self.version).map_err(|e| {
+                anyhow!(
+                    "Invalid asciicast event on line {}: {}",
+                    self.line_number,
+                    e
+                )
+            })?;
+            if let Some((event_time, kind)) = event {
+                self.time = if relative {
+                    self.time + event_time
+                } else {
+                    event_time
+                };
+                return Ok(Some(Event {
+                    time: self.time,
+                    kind,
+                }));
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl<R: BufRead> Iterator for Events<R> {
+    type Item = Result<Event>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let event = self.read_event().transpose();
+        if !matches!(event, Some(Ok(_))) {
+            self.done = true;
+        }
+        event
+    }
+}
+
+/// Write a [`Session`] as an asciicast v2 recording
+pub fn write<W: Write>(session: &Session, writer: W) -> Result<()> {
+    let mut writer = AsciicastWriter::new(writer, &session.metadata)?;
+    for event in &session.events {
+        writer.write_event(event)?;
+    }
+    writer.finish()?;
+    Ok(())
+}
+
+/// Check whether a file looks like an asciicast recording of any version
+///
+/// Usually only the first line is inspected. A file starting with a
+/// multi-line JSON document is parsed as a whole to look for a v1 recording.
+pub fn is_asciicast(path: impl AsRef<Path>) -> bool {
+    let Ok(reader) = gzip::open(path) else {
+        return false;
+    };
+    looks_like_asciicast(reader)
+}
+
+/// Most bytes read to recognize an asciicast recording
+const DETECT_LIMIT: u64 = 64 * 1024;
+
+/// Check whether a stream starts like an asciicast recording of any version
+pub(crate) fn looks_like_asciicast<R: BufRead>(reader: R) -> bool {
+    // A header is a few hundred bytes at most; don't read a whole typescript
+    let mut reader = reader.take(DETECT_LIMIT);
+    let mut first_line = String::new();
+    if reader.read_line(&mut first_line).is_err() {
+        return false;
+    }
+    if !first_line.trim_start().starts_with('{') {
+        return false;
+    }
+    if let Ok(header) = serde_json::from_str::<Value>(&first_line) {
+        return matches!(version(&header), Ok(1..=3));
+    }
+
+    // Only v1 spreads its JSON over several lines, with the whole recording
+    // in it. A document longer than the limit is recognized by its version
+    // key, which asciinema writes first.
+    let mut document = first_line.into_bytes();
+    if reader.read_to_end(&mut document).is_err() {
+        return false;
+    }
+    if reader.limit() == 0 {
+        let v1 = regex::bytes::Regex::new(r#"^\s*\{\s*"version"\s*:\s*1\s*,"#).unwrap();
+        return v1.is_match(&document);
+    }
+    match serde_json::from_slice::<Value>(&document) {
+        Ok(document) => matches!(version(&document), Ok(1..=3)),
+        Err(_) => false,
+    }
+}
+
+/// Incremental asciicast v2 writer
+///
+/// Writes the header immediately and each event as it is added, so a
+/// recording can be streamed to disk. Output and input bytes are decoded as
+/// UTF-8 per stream, so multibyte characters split between events are carried
+/// over to the next event instead of being corrupted.
+#[derive(Debug)]
+pub struct AsciicastWriter<W: Write> {
+    writer: W,
+    output_decoder: Utf8Decoder,
+    input_decoder: Utf8Decoder,
+    last_time: Duration,
+}
+
+impl<W: Write> AsciicastWriter<W> {
+    /// Create a writer and emit the header line
+    pub fn new(mut writer: W, metadata: &Metadata) -> Result<Self> {
+        writeln!(writer, "{}", format_header(metadata))
+            .map_err(|e| anyhow!("Failed to write asciicast header: {}", e))?;
+
+        Ok(Self {
+            writer,
+            output_decoder: Utf8Decoder::new(),
+            input_decoder: Utf8Decoder::new(),
+            last_time: Duration::ZERO,
+        })
+    }
+
+    /// Append an event
+    pub fn write_event(&mut self, event: &Event) -> Result<()> {
+        self.last_time = event.time;
+        match &event.kind {
+            EventKind::Output(data) => {
+                let text = self.output_decoder.decode(data);
+                self.write_line(event.time, "o", &text)
+            }
+            EventKind::Input(data) => {
+                let text = self.input_decoder.decode(data);
+                self.write_line(event.time, "i", &text)
+            }
+            EventKind::Resize { cols, rows } => {
+                self.write_line(event.time, "r", &format!("{}x{}", cols, rows))
+            }
+            EventKind::Marker(label) => self.write_line(event.time, "m", label),
+            // v2 has no exit status event
+            EventKind::Exit(_) => Ok(()),
+        }
+    }
+
+    /// Flush incomplete characters and the underlying writer
+    ///
+    /// Returns the underlying writer.
+    pub fn finish(mut self) -> Result<W> {
+        let output = self.output_decoder.finish();
+        if !output.is_empty() {
+            self.write_line(self.last_time, "o", &output)?;
+        }
+        let input = self.input_decoder.finish();
+        if !input.is_empty() {
+            self.write_line(self.last_time, "i", &input)?;
+        }
+
+        self.writer
+            .flush()
+            .map_err(|e| anyhow!("Failed to write asciicast event: {}", e))?;
+        Ok(self.writer)
+    }
+
+    fn write_line(&mut self, time: Duration, code: &str, data: &str) -> Result<()> {
+        // Output that ended mid-character has nothing to show yet
+        if data.is_empty() && (code == "o" || code == "i") {
+            return Ok(());
+        }
+
+        writeln!(
+            self.writer,
+            "[{:.6}, {}, {}]",
+            time.as_secs_f64(),
+            Value::from(code),
+            Value::from(data)
+        )
+        .map_err(|e| anyhow!("Failed to write asciicast event: {}", e))
+    }
+}
+
+impl<W: Write> EventWriter for AsciicastWriter<W> {
+    fn write_event(&mut self, event: &Event) -> Result<()> {
+        AsciicastWriter::write_event(self, event)
+    }
+
+    fn finish(self: Box<Self>) -> Result<()> {
+        AsciicastWriter::finish(*self).map(|_| ())
+    }
+}
+
+fn version(header: &Value) -> Result<u64> {
+    header
+        .as_object()
+        .ok_or_else(|| anyhow!("Invalid asciicast header: expected a JSON object"))?
+        .get("version")
+        .and_then(Value::as_u64)
+        .ok_or_else(|| anyhow!("Invalid asciicast header: missing version"))
+}
+
+pub(crate) fn parse_header(header: &Value) -> Result<Metadata> {
+    let header = header
+        .as_object()
+        .ok_or_else(|| anyhow!("Invalid asciicast header: expected a JSON object"))?;
+
+    // v3 keeps the terminal description in a nested `term` object
+    let term = header.get("term").and_then(Value::as_object);
+
+    let dimension = |key: &str, term_key: &str| {
+        header
+            .get(key)
+            .or_else(|| term?.get(term_key))
+            .and_then(Value::as_u64)
+            .and_then(|value| u16::try_from(value).ok())
+    };
+    let string = |key: &str| header.get(key).and_then(Value::as_str).map(str::to_string);
+
+    let mut env: BTreeMap<String, String> = header
+        .get("env")
+        .and_then(Value::as_object)
+        .map(|env| {
+            env.iter()
+                .filter_map(|(key, value)| Some((key.clone(), value.as_str()?.to_string())))
+                .collect()
+        })
+        .unwrap_or_default();
+    if let Some(term_type) = term.and_then(|term| term.get("type")?.as_str()) {
+        env.entry("TERM".to_string())
+            .or_insert_with(|| term_type.to_string());
+    }
+
+    Ok(Metadata {
+        width: dimension("width", "cols"),
+        height: dimension("height", "rows"),
+        timestamp: header.get("timestamp").and_then(Value::as_u64),
+        title: string("title"),
+        command: string("command"),
+        env,
+        idle_time_limit: header.get("idle_time_limit").and_then(Value::as_f64),
+    })
+}
+
+/// Convert a v1 document, whose `stdout` frames carry relative delays
+fn read_v1(document: &Value) -> Result<Session> {
+    if version(document)? != 1 {
+        return Err(anyhow!("Invalid asciicast v1 file: expected version 1"));
+    }
+
+    let frames = document
+        .get("stdout")
+        .and_then(Value::as_array)
+        .ok_or_else(|| anyhow!("Invalid asciicast v1 file: missing stdout array"))?;
+
+    let mut session = Session {
+        metadata: parse_header(document)?,
+        events: Vec::with_capacity(frames.len()),
+    };
+    let mut time = Duration::ZERO;
+
+    for (index, frame) in frames.iter().enumerate() {
+        let (delay, data) = frame
+            .as_array()
+            .filter(|fields| fields.len() >= 2)
+            .and_then(|fields| Some((fields[0].as_f64()?, fields[1].as_str()?)))
+            .ok_or_else(|| {
+                anyhow!(
+                    "Invalid asciicast v1 frame {}: expected a [delay, data] array",
+                    index
+                )
+            })?;
+
+        time += seconds(delay)?;
+        session.events.push(Event::output(time, data));
+    }
+
+    Ok(session)
+}
+
+fn format_header(metadata: &Metadata) -> String {
+    // Written by hand to keep the conventional key order with version first
+    let mut header = format!(
+        "{{\"version\": 2, \"width\": {}, \"height\": {}",
+        metadata.width.unwrap_or(DEFAULT_WIDTH),
+        metadata.height.unwrap_or(DEFAULT_HEIGHT)
+    );
+    if let Some(timestamp) = metadata.timestamp {
+        header.push_str(&format!(", \"timestamp\": {}", timestamp));
+    }
+    if let Some(limit) = metadata.idle_time_limit {
+        header.push_str(&format!(", \"idle_time_limit\": {}", Value::from(limit)));
+    }
+    if let Some(command) = &metadata.command {
+        header.push_str(&format!(", \"command\": {}", Value::from(command.as_str())));
+    }
+    if let Some(title) = &metadata.title {
+        header.push_str(&format!(", \"title\": {}", Value::from(title.as_str())));
+    }
+    if !metadata.env.is_empty() {
+        let env: serde_json::Map<String, Value> = metadata
+            .env
+            .iter()
+            .map(|(key, value)| (key.clone(), Value::from(value.as_str())))
+            .collect();
+        header.push_str(&format!(", \"env\": {}", Value::Object(env)));
+    }
+    header.push('}');
+    header
+}
+
+/// Parse a v2/v3 event line into its raw time and payload
+fn parse_event(line: &str, version: u64) -> Result<Option<(Duration, EventKind)>> {
+    let value: Value = serde_json::from_str(line).map_err(|e| anyhow!("{}", e))?;
+    let fields = value
+        .as_array()
+        .filter(|fields| fields.len() >= 3)
+        .ok_or_else(|| anyhow!("expected a [time, code, data] array"))?;
+
+    let time = fields[0]
+        .as_f64()
+        .ok_or_else(|| anyhow!("event time must be a number"))?;
+    let code = fields[1]
+        .as_str()
+        .ok_or_else(|| anyhow!("event code must be a string"))?;
+    let data = &fields[2];
+    let text = || {
+        data.as_str()
+            .ok_or_else(|| anyhow!("event data must be a string"))
+    };
+
+    let kind = match code {
+        "o" => EventKind::Output(text()?.as_bytes().to_vec()),
+        "i" => EventKind::Input(text()?.as_bytes().to_vec()),
+        "m" => EventKind::Marker(text()?.to_string()),
+        "r" => parse_resize(text()?)?,
+        "x" if version >= 3 => {
+            // Exit status may be written as a number or a numeric string
+            let status = match data {
+                Value::Number(number) => number.as_i64(),
+                Value::String(status) => status.trim().parse().ok(),
+                _ => None,
+            }
+            .and_then(|status| i32::try_from(status).ok())
+            .ok_or_else(|| anyhow!("invalid exit status {}", data))?;
+            EventKind::Exit(status)
+        }
+        _ => return Ok(None),
+    };
+
+    Ok(Some((seconds(time)?, kind)))
+}
+
+/// Parse resize event data such as `100x40`
+pub(crate) fn parse_resize(data: &str) -> Result<EventKind> {
+    let (cols, rows) = data
+        .split_once('x')
+        .ok_or_else(|| anyhow!("invalid resize data '{}'", data))?;
+    let cols = cols
+        .trim()
+        .parse()
+        .map_err(|_| anyhow!("invalid resize data '{}'", data))?;
+    let rows = rows
+        .trim()
+        .parse()
+        .map_err(|_| anyhow!("invalid resize data '{}'", data))?;
+    Ok(EventKind::Resize { cols, rows })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = r#"{"version": 2, "width": 100, "height": 30, "timestamp": 1700000000, "title": "demo", "env": {"SHELL": "/bin/bash", "TERM": "xterm-256color"}}
+[0.5, "o", "$ ls\r\n"]
+[0.75, "i", "q"]
+[1.0, "m", "listing"]
+[1.25, "r", "120x40"]
+[1.5, "x", "ignored"]
+[2.0, "o", "\u001b[32mfile.txt\u001b[0m\r\n"]
+"#;
+
+    #[test]
+    fn test_read_sample() -> Result<()> {
+        let session = read(SAMPLE.as_bytes())?;
+
+        assert_eq!(session.metadata.width, Some(100));
+        assert_eq!(session.metadata.height, Some(30));
+        assert_eq!(session.metadata.timestamp, Some(1700000000));
+        assert_eq!(session.metadata.title.as_deref(), Some("demo"));
+        assert_eq!(
+            session.metadata.env.get("TERM").map(String::as_str),
+            Some("xterm-256color")
+        );
+
+        assert_eq!(session.events.len(), 5);
+        assert_eq!(
+            session.events[0],
+            Event::output(Duration::from_millis(500), "$ ls\r\n")
+        );
+        assert_eq!(session.events[1].kind, EventKind::Input(b"q".to_vec()));
+        assert_eq!(
+            session.events[2].kind,
+            EventKind::Marker("listing".to_string())
+        );
+        assert_eq!(
+            session.events[3].kind,
+            EventKind::Resize {
+                cols: 120,
+                rows: 40
+            }
+        );
+        assert_eq!(
+            session.events[4],
+            Event::output(Duration::from_secs(2), "\x1b[32mfile.txt\x1b[0m\r\n")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_read_round_trip() -> Result<()> {
+        let original = read(SAMPLE.as_bytes())?;
+
+        let mut buffer = Vec::new();
+        write(&original, &mut buffer)?;
+        let text = String::from_utf8(buffer.clone())?;
+        assert!(text.starts_with("{\"version\": 2, \"width\": 100, \"height\": 30"));
+        assert!(text.contains("[0.500000, \"o\", \"$ ls\\r\\n\"]"));
+
+        let read_back = read(buffer.as_slice())?;
+        assert_eq!(read_back, original);
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_carries_split_characters() -> Result<()> {
+        let bytes = "中文".as_bytes();
+        let mut session = Session::new();
+        session
+            .events
+            .push(Event::output(Duration::from_millis(100), &bytes[..4]));
+        session
+            .events
+            .push(Event::output(Duration::from_millis(200), &bytes[4..]));
+
+        let mut buffer = Vec::new();
+        write(&session, &mut buffer)?;
+        let read_back = read(buffer.as_slice())?;
+
+        let output: Vec<u8> = read_back
+            .output()
+            .flat_map(|(_, data)| data.to_vec())
+            .collect();
+        assert_eq!(output, bytes);
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_v1() -> Result<()> {
+        let document = r#"{
+  "version": 1,
+  "width": 90,
+  "height": 20,
+  "duration": 1.5,
+  "command": "/bin/zsh",
+  "title": "archived",
+  "env": {"TERM": "xterm", "SHELL": "/bin/zsh"},
+  "stdout": [
+    [0.5, "$ "],
+    [0.25, "echo hi\r\n"],
+    [0.75, "hi\r\n"]
+  ]
+}"#;
+        let session = read(document.as_bytes())?;
+
+        assert_eq!(session.metadata.width, Some(90));
+        assert_eq!(session.metadata.height, Some(20));
+        assert_eq!(session.metadata.title.as_deref(), Some("archived"));
+        assert_eq!(session.metadata.command.as_deref(), Some("/bin/zsh"));
+        assert_eq!(
+            session.events,
+            vec![
+                Event::output(Duration::from_millis(500), "$ "),
+                Event::output(Duration::from_millis(750), "echo hi\r\n"),
+                Event::output(Duration::from_millis(1500), "hi\r\n"),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_v3() -> Result<()> {
+        let recording = r#"{"version": 3, "term": {"cols": 132, "rows": 43, "type": "xterm-256color"}, "timestamp": 1700000000, "title": "new"}
+# comments are allowed in v3
+[0.5, "o", "$ "]
+[0.25, "i", "l"]
+[0.25, "r", "100x30"]
+[1.0, "m", "chapter"]
+[0.5, "o", "done\r\n"]
+[0.1, "x", "2"]
+"#;
+        let session = read(recording.as_bytes())?;
+
+        assert_eq!(session.metadata.width, Some(132));
+        assert_eq!(session.metadata.height, Some(43));
+        assert_eq!(
+            session.metadata.env.get("TERM").map(String::as_str),
+            Some("xterm-256color")
+        );
+
+        let times: Vec<u64> = session
+            .events
+            .iter()
+            .map(|event| event.time.as_millis() as u64)
+            .collect();
+        assert_eq!(times, vec![500, 750, 1000, 2000, 2500, 2600]);
+        assert_eq!(
+            session.events[2].kind,
+            EventKind::Resize {
+                cols: 100,
+                rows: 30
+            }
+        );
+        assert_eq!(session.events[5].kind, EventKind::Exit(2));
+        Ok(())
+    }
+
+    #[test]
+    fn test_v1_and_v3_convert_to_v2() -> Result<()> {
+        let v1 = r#"{"version": 1, "width": 80, "height": 24, "stdout": [[0.5, "a"], [0.5, "b"]]}"#;
+        let v3 = "{\"version\": 3, \"term\": {\"cols\": 80, \"rows\": 24}}\n[0.5, \"o\", \"a\"]\n[0.5, \"o\", \"b\"]\n";
+
+        let from_v1 = read(v1.as_bytes())?;
+        let from_v3 = read(v3.as_bytes())?;
+        assert_eq!(from_v1.events, from_v3.events);
+
+        let mut buffer = Vec::new();
+        write(&from_v3, &mut buffer)?;
+        let text = String::from_utf8(buffer)?;
+        assert!(text.starts_with("{\"version\": 2"));
+        assert!(text.contains("[1.000000, \"o\", \"b\"]"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_rejects_invalid_input() {
+        assert!(read("".as_bytes()).is_err());
+        assert!(read("not json\n".as_bytes()).is_err());
+        assert!(read("{\"version\": 1}\n".as_bytes()).is_err());
+        assert!(read("{\"version\": 4}\n".as_bytes()).is_err());
+
+        let error = read("{\"version\": 2, \"width\": 80, \"height\": 24}\n[\"bad\"]\n".as_bytes())
+            .unwrap_err();
+        assert!(error.to_string().contains("line 2"));
+    }
+
+    #[test]
+    fn test_detection_reads_a_bounded_prefix() {
+        assert!(looks_like_asciicast(SAMPLE.as_bytes()));
+
+        // A typescript starting with a brace is given up on at the limit
+        let endless = "{\n".as_bytes().chain(std::io::repeat(b'x'));
+        assert!(!looks_like_asciicast(std::io::BufReader::new(endless)));
+
+        // A v1 recording longer than the limit is still recognized
+        let frame = "[0.5, \"output\\r\\n\"],\n";
+        let v1 = format!(
+            "{{\n  \"version\": 1,\n  \"stdout\": [\n{}[0.5, \"end\"]]\n}}",
+            frame.repeat(8 * 1024)
+        );
+        assert!(v1.len() as u64 > DETECT_LIMIT);
+        assert!(looks_like_asciicast(v1.as_bytes()));
+        assert!(!looks_like_asciicast(v1.replace("1,", "7,").as_bytes()));
+    }
+}
@@ -6,7 +6,7 @@
 
 use anyhow::Result;
 use clap::Parser;
-use replay_rs::Recorder;
+use replay_rs::{Format, Recorder};
 use std::process::Command;
 
 #[derive(Parser)]
@@ -34,6 +34,10 @@ struct Cli {
     #[arg(short, long)]
     plain_text: bool,
 
+    /// Session file format (defaults to asciicast for .cast output files)
+    #[arg(long, value_enum)]
+    format: Option<Format>,
+
     /// Verbose output
     #[arg(short, long)]
     verbose: bool,
@@ -42,6 +46,8 @@ struct Cli {
 fn main() -> Result<()> {
     let cli = Cli::parse();
 
+    let format = cli.format.unwrap_or_else(|| Format::from_path(&cli.output));
+
     // Determine timing file name
     let timing_file = cli
         .timing
@@ -54,7 +60,12 @@ fn main() -> Result<()> {
             cli.args.join(" ")
         );
         println!("📄 Output file: {}", cli.output);
-        println!("⏱️  Timing file: {}", timing_file);
+        match format {
+            Format::Scriptreplay | Format::Advanced => println!("⏱️  Timing file: {}", timing_file),
+            Format::Asciicast => println!("🗂️  File format: asciicast v2"),
+            Format::Ttyrec => println!("🗂️  File format: ttyrec"),
+            Format::Container => println!("🗂️  File format: container"),
+        }
         println!(
             "📝 Format: {}",
             if cli.plain_text {
@@ -67,7 +78,7 @@ fn main() -> Result<()> {
     }
 
     // Create the recorder
-    let recorder = Recorder::new(&cli.output, &timing_file)?;
+    let recorder = Recorder::with_format(format, &cli.output, &timing_file)?;
 
     // Build the command
     let mut cmd = Command::new(&cli.command);
@@ -82,15 +93,29 @@ fn main() -> Result<()> {
         println!("✅ Recording completed successfully!");
         println!("📂 Files created:");
         println!("   📄 Session: {}", cli.output);
-        println!("   ⏱️  Timing: {}", timing_file);
+        if matches!(format, Format::Scriptreplay | Format::Advanced) {
+            println!("   ⏱️  Timing: {}", timing_file);
+        }
         println!();
         println!("🎭 To replay, use:");
-        println!("   player {} --timing {}", cli.output, timing_file);
+        match format {
+            Format::Scriptreplay | Format::Advanced => {
+                println!("   player {} --timing {}", cli.output, timing_file)
+            }
+            Format::Asciicast | Format::Ttyrec | Format::Container => {
+                println!("   player {}", cli.output)
+            }
+        }
     } else {
-        println!(
-            "✅ Recording saved to {} (timing: {})",
-            cli.output, timing_file
-        );
+        match format {
+            Format::Scriptreplay | Format::Advanced => println!(
+                "✅ Recording saved to {} (timing: {})",
+                cli.output, timing_file
+            ),
+            Format::Asciicast | Format::Ttyrec | Format::Container => {
+                println!("✅ Recording saved to {}", cli.output)
+            }
+        }
     }
 
     Ok(())
@@ -3,10 +3,24 @@
 //! A unified CLI tool for both recording and replaying terminal sessions.
 //! Choose between record and play modes with a simple subcommand interface.
 
+use anyhow::anyhow;
 use anyhow::Result;
 use clap::{Parser, Subcommand};
-use replay_rs::{Player, Recorder};
+use replay_rs::cli::PlayArgs;
+use replay_rs::gif::{self, GifOptions};
+use replay_rs::html::{self, HtmlOptions};
+use replay_rs::markdown::{self, MarkdownOptions};
+use replay_rs::svg::{self, SvgOptions};
+use replay_rs::text::{self, TextMode, TextOptions};
+use replay_rs::{
+    parse_time, BuiltinTheme, Format, Player, Recorder, SearchOptions, SessionFiles, Theme,
+};
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufWriter, IsTerminal};
+use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::time::Duration;
 
 #[derive(Parser)]
 #[command(name = "replay")]
@@ -41,32 +55,154 @@ enum Commands {
         #[arg(short, long)]
         plain_text: bool,
 
+        /// Session file format (defaults to asciicast for .cast output files)
+        #[arg(long, value_enum)]
+        format: Option<Format>,
+
         /// Verbose output
         #[arg(short, long)]
         verbose: bool,
     },
     /// Replay a recorded session with timing data
-    Play {
-        /// Session file to replay
+    Play(PlayArgs),
+    /// Convert a recorded session, or a directory of them, to another file format
+    Convert {
+        /// Session file to convert, or a directory of sessions
+        #[arg(value_name = "INPUT")]
+        input: String,
+
+        /// File to write the converted session to, or a directory for batch conversion
+        #[arg(value_name = "OUTPUT")]
+        output: String,
+
+        /// Timing file of the input session (found next to it when omitted)
+        #[arg(short, long)]
+        timing: Option<String>,
+
+        /// Timing file to write for formats that use one
+        #[arg(long)]
+        output_timing: Option<String>,
+
+        /// Input file format (detected from the content when omitted)
+        #[arg(long, value_enum)]
+        from: Option<Format>,
+
+        /// Output file format (defaults to the format implied by OUTPUT)
+        #[arg(long, visible_alias = "format", value_enum)]
+        to: Option<Format>,
+
+        /// Verbose output
+        #[arg(short, long)]
+        verbose: bool,
+    },
+    /// Render a recorded session to an animation or document
+    Export {
+        /// Session file to export
         #[arg(value_name = "SESSION_FILE")]
         session_file: String,
 
-        /// Timing file for replay data
+        /// Timing file for replay data (found next to the session file when omitted)
         #[arg(short, long)]
         timing: Option<String>,
 
-        /// Playback speed multiplier (1.0 = normal, 2.0 = 2x speed, 0.5 = half speed)
+        /// Write an animated GIF to this file
+        #[arg(long, value_name = "FILE")]
+        gif: Option<String>,
+
+        /// Write an animated SVG to this file
+        #[arg(long, value_name = "FILE")]
+        svg: Option<String>,
+
+        /// Write a self-contained HTML player to this file
+        #[arg(long, value_name = "FILE")]
+        html: Option<String>,
+
+        /// Write the text left on the terminal to this file, or to stdout with -
+        #[arg(long, value_name = "FILE")]
+        text: Option<String>,
+
+        /// Write a line-by-line transcript instead of the scrollback and final screen (text only)
+        #[arg(long)]
+        transcript: bool,
+
+        /// Write command and output blocks as Markdown to this file, or to stdout with -
+        #[arg(long, value_name = "FILE")]
+        markdown: Option<String>,
+
+        /// Text that ends the shell prompt, for sessions without shell integration (Markdown only)
+        #[arg(long, value_name = "TEXT")]
+        prompt: Option<String>,
+
+        /// Turn the session's markers into headings (Markdown only)
+        #[arg(long)]
+        chapters: bool,
+
+        /// Draw a window frame with a title bar (SVG only)
+        #[arg(long)]
+        window: bool,
+
+        /// Color theme
+        #[arg(long, value_enum, default_value_t = BuiltinTheme::Dark)]
+        theme: BuiltinTheme,
+
+        /// Terminal width in columns (defaults to the recorded width)
+        #[arg(long)]
+        cols: Option<u16>,
+
+        /// Terminal height in rows (defaults to the recorded height)
+        #[arg(long)]
+        rows: Option<u16>,
+
+        /// Pixel scale of the built-in font
+        #[arg(long, default_value = "2")]
+        scale: u16,
+
+        /// Highest number of frames per second
+        #[arg(long, default_value = "30")]
+        fps: f64,
+
+        /// Longest pause kept between frames, in seconds
+        #[arg(short, long)]
+        idle_time_limit: Option<f64>,
+
+        /// Playback speed multiplier
         #[arg(short, long, default_value = "1.0")]
         speed: f64,
 
-        /// Fast dump mode (no timing delays, just show content)
-        #[arg(short, long)]
-        dump: bool,
+        /// Start the export at this recording time (SS, MM:SS or HH:MM:SS)
+        #[arg(long, value_name = "TIME", value_parser = parse_time)]
+        from: Option<Duration>,
+
+        /// End the export at this recording time (SS, MM:SS or HH:MM:SS)
+        #[arg(long, value_name = "TIME", value_parser = parse_time)]
+        to: Option<Duration>,
 
         /// Verbose output
         #[arg(short, long)]
         verbose: bool,
     },
+    /// Find text in a recorded session's output, with the time of each match
+    Search {
+        /// Session file to search
+        #[arg(value_name = "SESSION_FILE")]
+        session_file: String,
+
+        /// Regular expression to look for
+        #[arg(value_name = "PATTERN")]
+        pattern: String,
+
+        /// Timing file for replay data (found next to the session file when omitted)
+        #[arg(short, long)]
+        timing: Option<String>,
+
+        /// Match letters in either case
+        #[arg(short, long)]
+        ignore_case: bool,
+
+        /// Characters of surrounding text shown on each side of a match
+        #[arg(short = 'C', long, default_value = "40")]
+        context: usize,
+    },
 }
 
 fn main() -> Result<()> {
@@ -79,15 +215,25 @@ fn main() -> Result<()> {
             output,
             timing,
             plain_text,
+            format,
             verbose,
         } => {
+            let format = format.unwrap_or_else(|| Format::from_path(&output));
+
             // Determine timing file name
             let timing_file = timing.unwrap_or_else(|| format!("{}.timing", output));
 
             if verbose {
                 println!("📹 Recording command: {} {}", command, args.join(" "));
                 println!("📄 Output file: {}", output);
-                println!("⏱️  Timing file: {}", timing_file);
+                match format {
+                    Format::Scriptreplay | Format::Advanced => {
+                        println!("⏱️  Timing file: {}", timing_file)
+                    }
+                    Format::Asciicast => println!("🗂️  File format: asciicast v2"),
+                    Format::Ttyrec => println!("🗂️  File format: ttyrec"),
+                    Format::Container => println!("🗂️  File format: container"),
+                }
                 println!(
                     "📝 Format: {}",
                     if plain_text { "Plain text" } else { "Binary" }
@@ -96,7 +242,7 @@ fn main() -> Result<()> {
             }
 
             // Create the recorder
-            let recorder = Recorder::new(&output, &timing_file)?;
+            let recorder = Recorder::with_format(format, &output, &timing_file)?;
 
             // Build the command
             let mut cmd = Command::new(&command);
@@ -111,62 +257,370 @@ fn main() -> Result<()> {
                 println!("✅ Recording completed successfully!");
                 println!("📂 Files created:");
                 println!("   📄 Session: {}", output);
-                println!("   ⏱️  Timing: {}", timing_file);
+                if matches!(format, Format::Scriptreplay | Format::Advanced) {
+                    println!("   ⏱️  Timing: {}", timing_file);
+                }
                 println!();
                 println!("🎭 To replay, use:");
-                println!("   replay play {} --timing {}", output, timing_file);
+                match format {
+                    Format::Scriptreplay | Format::Advanced => {
+                        println!("   replay play {} --timing {}", output, timing_file)
+                    }
+                    Format::Asciicast | Format::Ttyrec | Format::Container => {
+                        println!("   replay play {}", output)
+                    }
+                }
             } else {
-                println!("✅ Recording saved to {} (timing: {})", output, timing_file);
+                match format {
+                    Format::Scriptreplay | Format::Advanced => {
+                        println!("✅ Recording saved to {} (timing: {})", output, timing_file)
+                    }
+                    Format::Asciicast | Format::Ttyrec | Format::Container => {
+                        println!("✅ Recording saved to {}", output)
+                    }
+                }
             }
         }
-        Commands::Play {
+        Commands::Play(play) => play.run()?,
+        Commands::Convert {
+            input,
+            output,
+            timing,
+            output_timing,
+            from,
+            to,
+            verbose,
+        } => {
+            if Path::new(&input).is_dir() {
+                let to = to.ok_or_else(|| {
+                    anyhow!("Converting a directory needs an output format; use --to <FORMAT>")
+                })?;
+                return convert_dir(&input, &output, from, to, verbose);
+            }
+            let to = to.unwrap_or_else(|| Format::from_path(&output));
+
+            // Determine the output timing file name
+            let output_timing = output_timing.unwrap_or_else(|| format!("{}.timing", output));
+
+            let player = open_player(&input, timing.as_deref(), from)?;
+            if verbose {
+                println!(
+                    "🔄 Converting {} ({}) to {}",
+                    input,
+                    player.format().session_format().name(),
+                    to.session_format().name()
+                );
+            }
+            player.convert(to, &SessionFiles::with_timing(&output, &output_timing))?;
+
+            match to {
+                Format::Scriptreplay | Format::Advanced => println!(
+                    "✅ Converted {} to {} (timing: {})",
+                    input, output, output_timing
+                ),
+                Format::Asciicast | Format::Ttyrec | Format::Container => {
+                    println!("✅ Converted {} to {}", input, output)
+                }
+            }
+        }
+        Commands::Export {
             session_file,
             timing,
+            gif,
+            svg,
+            html,
+            text,
+            transcript,
+            markdown,
+            prompt,
+            chapters,
+            window,
+            theme,
+            cols,
+            rows,
+            scale,
+            fps,
+            idle_time_limit,
             speed,
-            dump,
+            from,
+            to,
             verbose,
         } => {
-            // Determine timing file name
-            let timing_file = timing.unwrap_or_else(|| format!("{}.timing", session_file));
+            if gif.is_none()
+                && svg.is_none()
+                && html.is_none()
+                && text.is_none()
+                && markdown.is_none()
+            {
+                return Err(anyhow!(
+                    "No export format given; use --gif, --svg, --html, --text or --markdown <FILE>"
+                ));
+            }
+            let theme = Theme::builtin(theme);
+            let (start, end) = (from, to);
+
+            let session = open_player(&session_file, timing.as_deref(), None)?.session()?;
 
             if verbose {
                 println!("🎬 Session file: {}", session_file);
-                println!("⏱️  Timing file: {}", timing_file);
-                if !dump {
-                    println!("🚀 Speed: {}x", speed);
+            }
+
+            let create = |path: &str| {
+                File::create(path)
+                    .map(BufWriter::new)
+                    .map_err(|e| anyhow!("Failed to create output file: {}", e))
+            };
+
+            if let Some(gif_file) = gif {
+                if verbose {
+                    println!("🖼️  Exporting GIF: {}", gif_file);
+                }
+                let options = GifOptions {
+                    theme: theme.clone(),
+                    scale,
+                    cols,
+                    rows,
+                    fps,
+                    idle_time_limit,
+                    speed,
+                    start,
+                    end,
+                };
+                gif::export(&session, create(&gif_file)?, &options)?;
+                println!("✅ Exported {} to {}", session_file, gif_file);
+            }
+
+            if let Some(svg_file) = svg {
+                if verbose {
+                    println!("🖼️  Exporting SVG: {}", svg_file);
+                }
+                let options = SvgOptions {
+                    theme: theme.clone(),
+                    cols,
+                    rows,
+                    window,
+                    fps,
+                    idle_time_limit,
+                    speed,
+                    start,
+                    end,
+                };
+                svg::export(&session, create(&svg_file)?, &options)?;
+                println!("✅ Exported {} to {}", session_file, svg_file);
+            }
+
+            if let Some(html_file) = html {
+                if verbose {
+                    println!("🖼️  Exporting HTML: {}", html_file);
                 }
+                let options = HtmlOptions {
+                    theme,
+                    cols,
+                    rows,
+                    fps,
+                    idle_time_limit,
+                    speed,
+                    start,
+                    end,
+                };
+                html::export(&session, create(&html_file)?, &options)?;
+                println!("✅ Exported {} to {}", session_file, html_file);
+            }
+
+            if let Some(text_file) = text {
+                let options = TextOptions {
+                    mode: if transcript {
+                        TextMode::Transcript
+                    } else {
+                        TextMode::Screen
+                    },
+                    cols,
+                    rows,
+                    start,
+                    end,
+                    ..TextOptions::default()
+                };
+                if text_file == "-" {
+                    text::export(&session, std::io::stdout().lock(), &options)?;
+                } else {
+                    if verbose {
+                        println!("📝 Exporting text: {}", text_file);
+                    }
+                    text::export(&session, create(&text_file)?, &options)?;
+                    println!("✅ Exported {} to {}", session_file, text_file);
+                }
+            }
+
+            if let Some(markdown_file) = markdown {
+                let options = MarkdownOptions {
+                    cols,
+                    prompt,
+                    chapters,
+                    start,
+                    end,
+                };
+                if markdown_file == "-" {
+                    markdown::export(&session, std::io::stdout().lock(), &options)?;
+                } else {
+                    if verbose {
+                        println!("📝 Exporting Markdown: {}", markdown_file);
+                    }
+                    markdown::export(&session, create(&markdown_file)?, &options)?;
+                    println!("✅ Exported {} to {}", session_file, markdown_file);
+                }
+            }
+        }
+        Commands::Search {
+            session_file,
+            pattern,
+            timing,
+            ignore_case,
+            context,
+        } => {
+            let player = open_player(&session_file, timing.as_deref(), None)?;
+            let options = SearchOptions {
+                ignore_case,
+                context,
+            };
+            let matches = player.search(&pattern, &options)?;
+            if matches.is_empty() {
+                eprintln!("No matches for '{}' in {}", pattern, session_file);
+                // Like grep, finding nothing is a failure for scripts
+                std::process::exit(1);
+            }
+
+            let highlight = std::io::stdout().is_terminal();
+            for found in matches {
+                let text = if highlight {
+                    format!("\x1b[1;31m{}\x1b[0m", found.text)
+                } else {
+                    found.text
+                };
                 println!(
-                    "📺 Mode: {}",
-                    if dump { "Fast dump" } else { "Timed replay" }
+                    "{}  {}{}{}",
+                    timestamp(found.time),
+                    found.before,
+                    text,
+                    found.after
                 );
-                println!();
             }
+        }
+    }
+
+    Ok(())
+}
+
+/// Open a session, detecting its format unless the format or timing file is given
+fn open_player(path: &str, timing: Option<&str>, format: Option<Format>) -> Result<Player> {
+    match (format, timing) {
+        (Some(format), Some(timing)) => Ok(Player::with_format(
+            format,
+            &SessionFiles::with_timing(path, timing),
+        )),
+        (Some(format), None) => Ok(Player::with_format(format, &SessionFiles::new(path))),
+        (None, Some(timing)) => Player::new(timing, path),
+        (None, None) => Player::open(path),
+    }
+}
+
+/// Convert every session in a directory to `to`, skipping other files
+fn convert_dir(
+    input: &str,
+    output: &str,
+    from: Option<Format>,
+    to: Format,
+    verbose: bool,
+) -> Result<()> {
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(input)
+        .map_err(|e| anyhow!("Failed to read directory {}: {}", input, e))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_file())
+        .collect();
+    paths.sort();
+    std::fs::create_dir_all(output)
+        .map_err(|e| anyhow!("Failed to create directory {}: {}", output, e))?;
 
-            // Create the player
-            let player = Player::new(&timing_file, &session_file)?;
+    let mut written = HashSet::new();
+    let mut converted = 0;
+    let mut failed = 0;
+    for path in paths {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        // Timing files are read along with their typescripts
+        if name == "timing" || name.ends_with(".timing") || name.ends_with(".timing.gz") {
+            continue;
+        }
 
-            if dump {
-                // Fast dump mode
+        let input_file = path.display().to_string();
+        let player = match open_player(&input_file, None, from) {
+            Ok(player) => player,
+            Err(e) => {
                 if verbose {
-                    println!("⚡ Fast dumping session content...");
-                    println!();
+                    println!("⏭️  Skipping {}: {}", input_file, e);
                 }
-                player.dump()?;
-            } else {
-                // Timed replay mode
+                continue;
+            }
+        };
+
+        let stem = Path::new(name.strip_suffix(".gz").unwrap_or(&name))
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or(name.clone());
+        let output_file = Path::new(output)
+            .join(format!("{}.{}", stem, to.extension()))
+            .display()
+            .to_string();
+        // Sessions that differ only in their extension would overwrite each other
+        if !written.insert(output_file.clone()) {
+            eprintln!(
+                "❌ Not converting {}: {} was already written",
+                input_file, output_file
+            );
+            failed += 1;
+            continue;
+        }
+
+        match player.convert(to, &SessionFiles::new(&output_file)) {
+            Ok(()) => {
+                converted += 1;
                 if verbose {
-                    println!("🎭 Starting timed replay...");
-                    println!();
+                    println!("🔄 {} -> {}", input_file, output_file);
                 }
-                player.replay(speed)?;
             }
-
-            if verbose {
-                println!();
-                println!("🎊 Playback completed!");
+            Err(e) => {
+                failed += 1;
+                eprintln!("❌ Failed to convert {}: {}", input_file, e);
             }
         }
     }
 
+    if failed > 0 {
+        return Err(anyhow!(
+            "Failed to convert {} of {} sessions",
+            failed,
+            converted + failed
+        ));
+    }
+    println!(
+        "✅ Converted {} sessions from {} to {} ({})",
+        converted,
+        input,
+        output,
+        to.session_format().name()
+    );
     Ok(())
 }
+
+/// A recording time as `HH:MM:SS.mmm`, which `--from` and `--to` accept
+fn timestamp(time: Duration) -> String {
+    let seconds = time.as_secs();
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60,
+        time.subsec_millis()
+    )
+}
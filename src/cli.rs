@@ -0,0 +1,186 @@
+//! Command-line options shared by the binaries
+//!
+//! The `player` binary and the `replay play` subcommand take the same
+//! options, so both flatten [`PlayArgs`] into their argument parsers and
+//! play through [`PlayArgs::run`]. Only built with the `cli` feature, which
+//! is on by default.
+
+use crate::{parse_time, Format, Player, Repeat, ReplayOptions, Sanitize};
+use anyhow::Result;
+use std::io::IsTerminal;
+use std::time::Duration;
+
+/// Options for replaying a recorded session
+#[derive(Debug, Clone, clap::Args)]
+pub struct PlayArgs {
+    /// Session file to replay
+    #[arg(value_name = "SESSION_FILE")]
+    pub session_file: String,
+
+    /// Timing file for replay data (found next to the session file when omitted)
+    #[arg(short, long)]
+    pub timing: Option<String>,
+
+    /// Playback speed multiplier (1.0 = normal, 2.0 = 2x speed, 0.5 = half speed)
+    #[arg(short, long, default_value = "1.0")]
+    pub speed: f64,
+
+    /// Longest wait between outputs, in seconds (defaults to the session's idle time limit)
+    #[arg(short, long, value_name = "SECONDS", value_parser = parse_time)]
+    pub max_delay: Option<Duration>,
+
+    /// Fast dump mode (no timing delays, just show content)
+    #[arg(short, long)]
+    pub dump: bool,
+
+    /// How much of the output a dump cleans up
+    #[arg(
+        long,
+        value_enum,
+        value_name = "LEVEL",
+        default_value_t = Sanitize::Raw,
+        requires = "dump"
+    )]
+    pub sanitize: Sanitize,
+
+    /// Start playback at this recording time (SS, MM:SS or HH:MM:SS)
+    #[arg(long, value_name = "TIME", value_parser = parse_time)]
+    pub from: Option<Duration>,
+
+    /// Stop playback at this recording time (SS, MM:SS or HH:MM:SS)
+    #[arg(long, value_name = "TIME", value_parser = parse_time)]
+    pub to: Option<Duration>,
+
+    /// Start playback at the first marker with this label
+    #[arg(long, value_name = "LABEL", conflicts_with = "from")]
+    pub from_marker: Option<String>,
+
+    /// Show a progress line with elapsed and total time, speed and the next marker on stderr
+    #[arg(long)]
+    pub progress: bool,
+
+    /// Replay continuously, or N times (stop with q, Ctrl+C or a signal)
+    #[arg(
+        long = "loop",
+        value_name = "N",
+        num_args = 0..=1,
+        default_missing_value = "0",
+        conflicts_with = "dump"
+    )]
+    pub repeat: Option<u32>,
+
+    /// Pause at the end of each loop (SS, MM:SS or HH:MM:SS)
+    #[arg(
+        long,
+        value_name = "TIME",
+        value_parser = parse_time,
+        default_value = "0",
+        requires = "repeat"
+    )]
+    pub loop_pause: Duration,
+
+    /// Reset the terminal between loops
+    #[arg(long, requires = "repeat")]
+    pub loop_reset: bool,
+
+    /// Play without keyboard controls, even when stdin is a terminal
+    #[arg(long)]
+    pub no_controls: bool,
+
+    /// Regular expression the n key jumps to during interactive playback
+    #[arg(long, value_name = "PATTERN", conflicts_with = "dump")]
+    pub search: Option<String>,
+
+    /// Verbose output
+    #[arg(short, long)]
+    pub verbose: bool,
+}
+
+impl PlayArgs {
+    /// Whether playback takes keyboard controls
+    ///
+    /// Keyboard controls need a terminal to read keys from and draw on.
+    pub fn interactive(&self) -> bool {
+        !self.dump
+            && !self.no_controls
+            && std::io::stdin().is_terminal()
+            && std::io::stdout().is_terminal()
+    }
+
+    /// The replay options these arguments select
+    pub fn options(&self) -> ReplayOptions {
+        ReplayOptions {
+            speed: self.speed,
+            start: self.from,
+            start_marker: self.from_marker.clone(),
+            end: self.to,
+            max_delay: self.max_delay,
+            progress: self.progress,
+            repeat: Repeat::from_count(self.repeat),
+            repeat_pause: self.loop_pause,
+            reset_between: self.loop_reset,
+            interactive: self.interactive(),
+            search: self.search.clone(),
+            ..ReplayOptions::default()
+        }
+    }
+
+    /// Open the session and play or dump it to stdout
+    pub fn run(&self) -> Result<()> {
+        // Create the player, detecting the format unless a timing file is given
+        let player = match &self.timing {
+            Some(timing_file) => Player::new(timing_file, &self.session_file)?,
+            None => Player::open(&self.session_file)?,
+        };
+        let options = self.options();
+
+        if self.verbose {
+            println!("🎬 Session file: {}", self.session_file);
+            match player.format() {
+                Format::Scriptreplay | Format::Advanced => {
+                    println!("⏱️  Timing file: {}", player.files().timing_file())
+                }
+                Format::Asciicast => println!("🗂️  File format: asciicast"),
+                Format::Ttyrec => println!("🗂️  File format: ttyrec"),
+                Format::Container => println!("🗂️  File format: container"),
+            }
+            if !self.dump {
+                println!("🚀 Speed: {}x", self.speed);
+            }
+            println!(
+                "📺 Mode: {}",
+                if self.dump {
+                    "Fast dump"
+                } else if options.interactive {
+                    "Interactive replay"
+                } else {
+                    "Timed replay"
+                }
+            );
+            println!();
+        }
+
+        if self.dump {
+            // Fast dump mode
+            if self.verbose {
+                println!("⚡ Fast dumping session content...");
+                println!();
+            }
+            player.dump_to(&mut std::io::stdout().lock(), self.sanitize)?;
+        } else {
+            // Timed replay mode
+            if self.verbose {
+                println!("🎭 Starting timed replay...");
+                println!();
+            }
+            player.replay_with(&options)?;
+        }
+
+        if self.verbose {
+            println!();
+            println!("🎊 Playback completed!");
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,608 @@
+//! Single-file session container
+//!
+//! A container keeps everything about a recording in one file, so a
+//! typescript can't get separated from its timing data. It is a plain
+//! (ustar) tar archive and can be unpacked with `tar -xf`:
+//!
+//! ```text
+//! replay.json    container version, metadata, resize/marker/exit events
+//!                and the order all events were recorded in
+//! typescript     the output, exactly as written to the terminal
+//! timing         scriptreplay timing for `typescript`
+//! input          bytes typed by the user, if any were recorded
+//! input.timing   scriptreplay timing for `input`
+//! ```
+//!
+//! The unpacked `typescript` and `timing` pair plays with `scriptreplay` and
+//! with [`Player::new`](crate::Player::new) like any other recording.
+
+use crate::asciicast::{parse_header, parse_resize};
+use crate::gzip;
+use crate::session::{
+    create_file, open_file, parse_timing_line, seconds, Event, EventKind, EventWriter, Metadata,
+    Session, SessionFiles, SessionFormat,
+};
+use anyhow::{anyhow, Result};
+use serde_json::{json, Map, Value};
+use std::io::{Read, Write};
+use std::path::Path;
+use std::time::Duration;
+
+/// Container layout version written by this crate
+pub const VERSION: u64 = 1;
+
+/// Name of the first archive member, used to recognize containers
+const MANIFEST: &str = "replay.json";
+
+/// Size of a tar header and of the blocks member data is padded to
+const BLOCK: usize = 512;
+
+/// The single-file container as a [`SessionFormat`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ContainerFormat;
+
+impl SessionFormat for ContainerFormat {
+    fn name(&self) -> &'static str {
+        "container"
+    }
+
+    fn read(&self, files: &SessionFiles) -> Result<Session> {
+        read(open_file(&files.data, "container")?)
+    }
+
+    fn write(&self, session: &Session, files: &SessionFiles) -> Result<()> {
+        write(session, create_file(&files.data, "output")?)
+    }
+
+    fn writer(&self, metadata: &Metadata, files: &SessionFiles) -> Result<Box<dyn EventWriter>> {
+        Ok(Box::new(ContainerWriter {
+            writer: create_file(&files.data, "output")?,
+            session: Session {
+                metadata: metadata.clone(),
+                events: Vec::new(),
+            },
+        }))
+    }
+}
+
+/// Collects events and writes the container when finished, since member
+/// sizes have to be known before their data
+struct ContainerWriter<W: Write> {
+    writer: W,
+    session: Session,
+}
+
+impl<W: Write> EventWriter for ContainerWriter<W> {
+    fn write_event(&mut self, event: &Event) -> Result<()> {
+        self.session.events.push(event.clone());
+        Ok(())
+    }
+
+    fn finish(self: Box<Self>) -> Result<()> {
+        write(&self.session, self.writer)
+    }
+}
+
+/// Read a container into a [`Session`]
+pub fn read<R: Read>(mut reader: R) -> Result<Session> {
+    let mut manifest = None;
+    let mut typescript = None;
+    let mut timing = None;
+    let mut input = None;
+    let mut input_timing = None;
+
+    while let Some((name, data)) = read_member(&mut reader)? {
+        match name.as_str() {
+            MANIFEST => manifest = Some(data),
+            "typescript" => typescript = Some(data),
+            "timing" => timing = Some(data),
+            "input" => input = Some(data),
+            "input.timing" => input_timing = Some(data),
+            // Members added by later minor revisions are ignored
+            _ => {}
+        }
+    }
+
+    let manifest = manifest.ok_or_else(|| anyhow!("Invalid container: missing {}", MANIFEST))?;
+    let manifest: Value = serde_json::from_slice(&manifest)
+        .map_err(|e| anyhow!("Invalid container {}: {}", MANIFEST, e))?;
+    let version = manifest
+        .get("version")
+        .and_then(Value::as_u64)
+        .ok_or_else(|| anyhow!("Invalid container {}: missing version", MANIFEST))?;
+    if version > VERSION {
+        return Err(anyhow!(
+            "Unsupported container version {} (this build reads up to {})",
+            version,
+            VERSION
+        ));
+    }
+
+    let mut session = Session::new();
+    session.metadata = parse_header(&manifest)?;
+
+    let typescript = typescript.ok_or_else(|| anyhow!("Invalid container: missing typescript"))?;
+    let timing = timing.ok_or_else(|| anyhow!("Invalid container: missing timing"))?;
+    let output = timed_blocks(&timing, &typescript, EventKind::Output)?;
+    let input = match (input, input_timing) {
+        (Some(input), Some(input_timing)) => timed_blocks(&input_timing, &input, EventKind::Input)?,
+        _ => Vec::new(),
+    };
+    let events = manifest
+        .get("events")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .map(parse_event)
+        .collect::<Result<Vec<_>>>()?;
+
+    let order = manifest.get("order").and_then(Value::as_str);
+    session.events = recorded_order(order, output, input, events);
+    // Stable, so events at the same time keep the order they were recorded in
+    session.events.sort_by_key(|event| event.time);
+    Ok(session)
+}
+
+/// Interleave the output, input and manifest events as `order` lists them
+///
+/// `order` has one code per event: `o` for output, `i` for input and `e`
+/// for a manifest event. Containers without it, or with one that doesn't
+/// match the events, keep them grouped by kind.
+fn recorded_order(
+    order: Option<&str>,
+    output: Vec<Event>,
+    input: Vec<Event>,
+    events: Vec<Option<Event>>,
+) -> Vec<Event> {
+    let fits = |order: &&str| {
+        let count = |code| order.matches(code).count();
+        order.len() == output.len() + input.len() + events.len()
+            && count('o') == output.len()
+            && count('i') == input.len()
+            && count('e') == events.len()
+    };
+    let Some(order) = order.filter(fits) else {
+        return output
+            .into_iter()
+            .chain(input)
+            .chain(events.into_iter().flatten())
+            .collect();
+    };
+
+    let (mut output, mut input, mut events) =
+        (output.into_iter(), input.into_iter(), events.into_iter());
+    order
+        .chars()
+        .filter_map(|code| match code {
+            'o' => output.next(),
+            'i' => input.next(),
+            // Events with unknown codes were parsed as None
+            _ => events.next().flatten(),
+        })
+        .collect()
+}
+
+/// Write a [`Session`] as a container
+pub fn write<W: Write>(session: &Session, mut writer: W) -> Result<()> {
+    let mut typescript = Vec::new();
+    let mut timing = String::new();
+    let mut input = Vec::new();
+    let mut input_timing = String::new();
+    let mut events = Vec::new();
+    let mut order = String::new();
+    let (mut last_output, mut last_input) = (Duration::ZERO, Duration::ZERO);
+
+    for event in &session.events {
+        let time = event.time.as_secs_f64();
+        match &event.kind {
+            EventKind::Output(data) => {
+                add_block(&mut timing, &mut last_output, event.time, data.len());
+                typescript.extend(data);
+                order.push('o');
+            }
+            EventKind::Input(data) => {
+                add_block(&mut input_timing, &mut last_input, event.time, data.len());
+                input.extend(data);
+                order.push('i');
+            }
+            EventKind::Resize { cols, rows } => {
+                events.push(json!([time, "r", format!("{}x{}", cols, rows)]));
+                order.push('e');
+            }
+            EventKind::Marker(label) => {
+                events.push(json!([time, "m", label]));
+                order.push('e');
+            }
+            EventKind::Exit(status) => {
+                events.push(json!([time, "x", status]));
+                order.push('e');
+            }
+        }
+    }
+
+    let mut manifest = Map::new();
+    manifest.insert("version".to_string(), json!(VERSION));
+    let metadata = &session.metadata;
+    let mut field = |key: &str, value: Option<Value>| {
+        if let Some(value) = value {
+            manifest.insert(key.to_string(), value);
+        }
+    };
+    field("width", metadata.width.map(Value::from));
+    field("height", metadata.height.map(Value::from));
+    field("timestamp", metadata.timestamp.map(Value::from));
+    field("title", metadata.title.clone().map(Value::from));
+    field("command", metadata.command.clone().map(Value::from));
+    field("idle_time_limit", metadata.idle_time_limit.map(Value::from));
+    field(
+        "env",
+        (!metadata.env.is_empty()).then(|| json!(metadata.env)),
+    );
+    field("events", (!events.is_empty()).then(|| Value::from(events)));
+    field("order", Some(Value::from(order)));
+    let manifest = serde_json::to_vec_pretty(&Value::Object(manifest))
+        .map_err(|e| anyhow!("Failed to write container: {}", e))?;
+
+    let mtime = metadata.timestamp.unwrap_or(0);
+    write_member(&mut writer, MANIFEST, &manifest, mtime)?;
+    write_member(&mut writer, "typescript", &typescript, mtime)?;
+    write_member(&mut writer, "timing", timing.as_bytes(), mtime)?;
+    if !input.is_empty() {
+        write_member(&mut writer, "input", &input, mtime)?;
+        write_member(&mut writer, "input.timing", input_timing.as_bytes(), mtime)?;
+    }
+
+    // End of archive marker
+    writer
+        .write_all(&[0; BLOCK * 2])
+        .and_then(|_| writer.flush())
+        .map_err(|e| anyhow!("Failed to write container: {}", e))
+}
+
+/// Check whether a file is a session container
+pub fn is_container(path: impl AsRef<Path>) -> bool {
+    let Ok(mut reader) = gzip::open(path) else {
+        return false;
+    };
+    let mut header = [0u8; BLOCK];
+    if reader.read_exact(&mut header).is_err() {
+        return false;
+    }
+    looks_like_container(&header)
+}
+
+/// Check whether data starts with the manifest header of a session container
+pub(crate) fn looks_like_container(data: &[u8]) -> bool {
+    let Some(header) = data.first_chunk::<BLOCK>() else {
+        return false;
+    };
+    &header[257..262] == b"ustar" && header_name(header) == MANIFEST
+}
+
+/// Append a timing line for a block of `len` bytes written at `time`
+fn add_block(timing: &mut String, last: &mut Duration, time: Duration, len: usize) {
+    let delay = time.saturating_sub(*last);
+    *last = time;
+    timing.push_str(&format!("{:.6} {}\n", delay.as_secs_f64(), len));
+}
+
+/// Split logged bytes into events using their scriptreplay timing
+fn timed_blocks(timing: &[u8], data: &[u8], kind: fn(Vec<u8>) -> EventKind) -> Result<Vec<Event>> {
+    let timing = String::from_utf8_lossy(timing);
+    let mut events = Vec::new();
+    let mut time = Duration::ZERO;
+    let mut offset = 0;
+    for line in timing.lines() {
+        let Some((delay, size)) = parse_timing_line(line)? else {
+            continue;
+        };
+        time += delay;
+        let end = (offset + size).min(data.len());
+        events.push(Event {
+            time,
+            kind: kind(data[offset..end].to_vec()),
+        });
+        offset = end;
+    }
+    Ok(events)
+}
+
+/// Parse a `[time, code, data]` entry of the manifest's event list
+///
+/// Events with codes added by later minor revisions are skipped.
+fn parse_event(event: &Value) -> Result<Option<Event>> {
+    let fields = event
+        .as_array()
+        .filter(|fields| fields.len() >= 3)
+        .ok_or_else(|| anyhow!("Invalid container event: expected [time, code, data]"))?;
+    let time = fields[0]
+        .as_f64()
+        .ok_or_else(|| anyhow!("Invalid container event: time must be a number"))?;
+    let time = seconds(time)?;
+
+    let kind = match (fields[1].as_str(), &fields[2]) {
+        (Some("r"), Value::String(size)) => parse_resize(size)?,
+        (Some("m"), Value::String(label)) => EventKind::Marker(label.clone()),
+        (Some("x"), status) => EventKind::Exit(
+            status
+                .as_i64()
+                .and_then(|status| i32::try_from(status).ok())
+                .ok_or_else(|| anyhow!("Invalid container event: bad exit status"))?,
+        ),
+        (Some("r" | "m"), _) | (None, _) => {
+            return Err(anyhow!("Invalid container event: {}", event))
+        }
+        (Some(_), _) => return Ok(None),
+    };
+    Ok(Some(Event { time, kind }))
+}
+
+/// Write one regular file member with its header and padding
+fn write_member<W: Write>(writer: &mut W, name: &str, data: &[u8], mtime: u64) -> Result<()> {
+    let mut header = [0u8; BLOCK];
+    header[..name.len()].copy_from_slice(name.as_bytes());
+    octal(&mut header[100..108], 0o644);
+    octal(&mut header[108..116], 0);
+    octal(&mut header[116..124], 0);
+    octal(&mut header[124..136], data.len() as u64);
+    octal(&mut header[136..148], mtime);
+    header[156] = b'0';
+    header[257..263].copy_from_slice(b"ustar\0");
+    header[263..265].copy_from_slice(b"00");
+
+    // The checksum is computed with its own field filled with spaces
+    header[148..156].fill(b' ');
+    let checksum: u64 = header.iter().map(|&byte| byte as u64).sum();
+    octal(&mut header[148..155], checksum);
+
+    let padding = (BLOCK - data.len() % BLOCK) % BLOCK;
+    writer
+        .write_all(&header)
+        .and_then(|_| writer.write_all(data))
+        .and_then(|_| writer.write_all(&[0; BLOCK][..padding]))
+        .map_err(|e| anyhow!("Failed to write container: {}", e))
+}
+
+/// Read the next regular file member, skipping other entry types
+fn read_member<R: Read>(reader: &mut R) -> Result<Option<(String, Vec<u8>)>> {
+    loop {
+        let mut header = [0u8; BLOCK];
+        match reader.read_exact(&mut header) {
+            Ok(()) => {}
+            // Some writers omit the end of archive marker
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(anyhow!("Failed to read container: {}", e)),
+        }
+        if header.iter().all(|&byte| byte == 0) {
+            return Ok(None);
+        }
+
+        let expected = parse_octal(&header[148..156])
+            .ok_or_else(|| anyhow!("Invalid container: bad header checksum"))?;
+        let checksum: u64 = header
+            .iter()
+            .enumerate()
+            .map(|(i, &byte)| {
+                if (148..156).contains(&i) {
+                    32
+                } else {
+                    byte as u64
+                }
+            })
+            .sum();
+        if checksum != expected {
+            return Err(anyhow!("Invalid container: header checksum mismatch"));
+        }
+
+        let size = parse_octal(&header[124..136])
+            .ok_or_else(|| anyhow!("Invalid container: bad member size"))?;
+        let mut data = Vec::new();
+        reader
+            .by_ref()
+            .take(size)
+            .read_to_end(&mut data)
+            .map_err(|e| anyhow!("Failed to read container: {}", e))?;
+        if (data.len() as u64) < size {
+            return Err(anyhow!("Invalid container: archive is truncated"));
+        }
+        let padding = (BLOCK - data.len() % BLOCK) % BLOCK;
+        std::io::copy(
+            &mut reader.by_ref().take(padding as u64),
+            &mut std::io::sink(),
+        )
+        .map_err(|e| anyhow!("Failed to read container: {}", e))?;
+
+        if matches!(header[156], b'0' | 0) {
+            return Ok(Some((header_name(&header), data)));
+        }
+    }
+}
+
+/// The member name of a header, with the ustar prefix applied
+fn header_name(header: &[u8; BLOCK]) -> String {
+    let field = |bytes: &[u8]| {
+        let end = bytes
+            .iter()
+            .position(|&byte| byte == 0)
+            .unwrap_or(bytes.len());
+        String::from_utf8_lossy(&bytes[..end]).into_owned()
+    };
+    let name = field(&header[..100]);
+    let prefix = field(&header[345..500]);
+    if prefix.is_empty() {
+        name
+    } else {
+        format!("{}/{}", prefix, name)
+    }
+}
+
+/// Fill a header field with a zero-padded octal number and a NUL
+///
+/// Numbers with too many digits for the field, such as sizes of 8 GiB and
+/// more, use the GNU base-256 encoding: a big-endian binary number marked
+/// by the high bit of the first byte.
+fn octal(field: &mut [u8], value: u64) {
+    let digits = format!("{:0width$o}", value, width = field.len() - 1);
+    if digits.len() < field.len() {
+        field[..digits.len()].copy_from_slice(digits.as_bytes());
+        field[digits.len()] = 0;
+    } else {
+        let start = field.len() - 8;
+        field.fill(0);
+        field[start..].copy_from_slice(&value.to_be_bytes());
+        field[0] |= 0x80;
+    }
+}
+
+/// Parse a numeric header field written by [`octal`]
+fn parse_octal(field: &[u8]) -> Option<u64> {
+    if let Some((&first, rest)) = field.split_first().filter(|(&first, _)| first & 0x80 != 0) {
+        return std::iter::once(first & 0x7f)
+            .chain(rest.iter().copied())
+            .try_fold(0u64, |value, byte| {
+                value.checked_mul(256)?.checked_add(byte as u64)
+            });
+    }
+
+    let text = std::str::from_utf8(field).ok()?;
+    let text = text.trim_matches(|c: char| c == '\0' || c == ' ');
+    if text.is_empty() {
+        return Some(0);
+    }
+    u64::from_str_radix(text, 8).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::Metadata;
+
+    #[test]
+    fn test_round_trip() {
+        let mut session = Session::new();
+        session.metadata = Metadata {
+            width: Some(100),
+            height: Some(30),
+            timestamp: Some(1_700_000_000),
+            command: Some("vim".to_string()),
+            ..Metadata::default()
+        };
+        session
+            .metadata
+            .env
+            .insert("TERM".to_string(), "xterm".to_string());
+        session.events = vec![
+            Event::output(Duration::from_millis(10), "hello"),
+            Event {
+                time: Duration::from_millis(20),
+                kind: EventKind::Input(b"q".to_vec()),
+            },
+            Event {
+                time: Duration::from_millis(30),
+                kind: EventKind::Resize { cols: 90, rows: 20 },
+            },
+            Event {
+                time: Duration::from_millis(40),
+                kind: EventKind::Marker("done".to_string()),
+            },
+            Event::output(Duration::from_millis(50), vec![0xff, 0x00, b'\n']),
+            Event {
+                time: Duration::from_millis(60),
+                kind: EventKind::Exit(3),
+            },
+        ];
+
+        let mut archive = Vec::new();
+        write(&session, &mut archive).unwrap();
+        assert_eq!(archive.len() % BLOCK, 0);
+        assert_eq!(&archive[257..262], b"ustar");
+        assert_eq!(read(archive.as_slice()).unwrap(), session);
+    }
+
+    #[test]
+    fn test_rejects_newer_versions_and_bad_checksums() {
+        let mut archive = Vec::new();
+        write_member(&mut archive, MANIFEST, br#"{"version": 2}"#, 0).unwrap();
+        let error = read(archive.as_slice()).unwrap_err().to_string();
+        assert!(
+            error.contains("Unsupported container version 2"),
+            "{}",
+            error
+        );
+
+        archive[0] = b'x';
+        let error = read(archive.as_slice()).unwrap_err().to_string();
+        assert!(error.contains("checksum"), "{}", error);
+    }
+
+    #[test]
+    fn test_events_at_the_same_time_keep_their_order() {
+        let at = Duration::from_millis(100);
+        let marker = |label: &str| Event {
+            time: at,
+            kind: EventKind::Marker(label.to_string()),
+        };
+        let mut session = Session::new();
+        session.events = vec![
+            marker("before"),
+            Event {
+                time: at,
+                kind: EventKind::Input(b"q".to_vec()),
+            },
+            Event::output(at, "output"),
+            marker("after"),
+            Event::output(Duration::from_millis(200), "later"),
+        ];
+
+        let mut archive = Vec::new();
+        write(&session, &mut archive).unwrap();
+        assert_eq!(read(archive.as_slice()).unwrap(), session);
+    }
+
+    #[test]
+    fn test_large_numbers_use_base_256() {
+        let mut field = [0u8; 12];
+        octal(&mut field, 0o777);
+        assert_eq!(&field, b"00000000777\0");
+        assert_eq!(parse_octal(&field), Some(0o777));
+
+        // 8 GiB has twelve octal digits, one more than the size field holds
+        let size = 8 << 30;
+        octal(&mut field, size);
+        assert_eq!(field[..4], [0x80, 0, 0, 0]);
+        assert_eq!(parse_octal(&field), Some(size));
+
+        octal(&mut field, u64::MAX);
+        assert_eq!(parse_octal(&field), Some(u64::MAX));
+        assert_eq!(parse_octal(&[0x81, 0, 0, 0, 0, 0, 0, 0, 0]), None);
+    }
+
+    #[test]
+    fn test_skips_unknown_event_codes() {
+        let manifest = br#"{"version": 1, "events": [[0.5, "m", "start"], [0.75, "z", "later"]]}"#;
+        let mut archive = Vec::new();
+        write_member(&mut archive, MANIFEST, manifest, 0).unwrap();
+        write_member(&mut archive, "typescript", b"hi", 0).unwrap();
+        write_member(&mut archive, "timing", b"1.000000 2\n", 0).unwrap();
+
+        let session = read(archive.as_slice()).unwrap();
+        assert_eq!(
+            session.events,
+            vec![
+                Event {
+                    time: Duration::from_millis(500),
+                    kind: EventKind::Marker("start".to_string()),
+                },
+                Event::output(Duration::from_millis(1000), "hi"),
+            ]
+        );
+
+        // Known codes with bad data are still errors
+        let mut archive = Vec::new();
+        let manifest = br#"{"version": 1, "events": [[0.5, "r", 80]]}"#;
+        write_member(&mut archive, MANIFEST, manifest, 0).unwrap();
+        write_member(&mut archive, "typescript", b"", 0).unwrap();
+        write_member(&mut archive, "timing", b"", 0).unwrap();
+        assert!(read(archive.as_slice()).is_err());
+    }
+}
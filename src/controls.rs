@@ -0,0 +1,308 @@
+//! Keyboard-controlled playback
+//!
+//! The terminal is put in raw mode and the bottom row is reserved for a
+//! status line with the playback progress and speed, keeping it out of the
+//! recording's way with a scroll region. Keys are read between output
+//! events, so they take effect immediately even during long pauses.
+
+use crate::progress::{StatusRow, Timeline, STATUS_INTERVAL};
+use crate::session::{EventKind, EventStream};
+use crate::tty::{self, RawMode, Stream};
+use anyhow::{anyhow, Result};
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+/// Slowest and fastest playback speeds reachable with `-` and `+`
+const MIN_SPEED: f64 = 1.0 / 16.0;
+const MAX_SPEED: f64 = 64.0;
+
+/// What a key press asks the player to do
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Control {
+    /// Pause or resume (space)
+    TogglePause,
+    /// Show the next chunk of output while paused (`.`)
+    Step,
+    /// Double the speed (`+`)
+    Faster,
+    /// Halve the speed (`-`)
+    Slower,
+    /// Fast-forward to the next marker and pause there (`]`)
+    NextMarker,
+    /// Fast-forward to the next search match and pause there (`n`)
+    NextMatch,
+    /// Skip the rest of the current pause in the output (`s`)
+    SkipIdle,
+    /// Stop playing (`q` or Ctrl+C)
+    Quit,
+}
+
+impl Control {
+    /// The control bound to a key, if any
+    pub(crate) fn from_key(key: u8) -> Option<Self> {
+        match key {
+            b' ' => Some(Control::TogglePause),
+            b'.' => Some(Control::Step),
+            b'+' | b'=' => Some(Control::Faster),
+            b'-' | b'_' => Some(Control::Slower),
+            b']' => Some(Control::NextMarker),
+            b'n' | b'N' => Some(Control::NextMatch),
+            b's' | b'S' => Some(Control::SkipIdle),
+            b'q' | b'Q' | 0x03 => Some(Control::Quit),
+            _ => None,
+        }
+    }
+}
+
+/// Where playback is and how it goes on
+#[derive(Debug, Clone, PartialEq)]
+struct State {
+    /// Recording time shown so far
+    position: Duration,
+    speed: f64,
+    paused: bool,
+    /// Show the next event without waiting, then stay paused
+    step: bool,
+    /// Show events without waiting until the next marker
+    seeking_marker: bool,
+    /// Show events without waiting until this search match is on screen
+    seeking_match: Option<Duration>,
+    /// Show the next output without waiting for the rest of the pause
+    skip_idle: bool,
+    quit: bool,
+}
+
+impl State {
+    fn new(start: Duration, speed: f64) -> Self {
+        Self {
+            position: start,
+            speed: if speed > 0.0 { speed } else { 1.0 },
+            paused: false,
+            step: false,
+            seeking_marker: false,
+            seeking_match: None,
+            skip_idle: false,
+            quit: false,
+        }
+    }
+
+    fn apply(&mut self, control: Control, timeline: &Timeline) {
+        match control {
+            Control::TogglePause => self.paused = !self.paused,
+            Control::Step => {
+                if self.paused {
+                    self.step = true;
+                }
+            }
+            Control::Faster => self.speed = (self.speed * 2.0).min(MAX_SPEED),
+            Control::Slower => self.speed = (self.speed / 2.0).max(MIN_SPEED),
+            Control::NextMarker => self.seeking_marker = true,
+            Control::NextMatch => {
+                let from = self.seeking_match.unwrap_or(self.position);
+                self.seeking_match = timeline.next_match(from).or(self.seeking_match);
+            }
+            Control::SkipIdle => self.skip_idle = !self.paused,
+            Control::Quit => self.quit = true,
+        }
+    }
+
+    /// Whether the next event, due at `time`, is shown now
+    fn due(&self, time: Duration) -> bool {
+        self.step
+            || self.seeking_marker
+            || self.seeking_match.is_some_and(|target| time <= target)
+            || (!self.paused && (self.skip_idle || self.position >= time))
+    }
+
+    fn status(&self, timeline: &Timeline) -> String {
+        format!(
+            " {} {}  │  space pause  . step  s skip idle  +/- speed  ] next marker  {}q quit",
+            if self.paused { "⏸" } else { "▶" },
+            timeline.status(self.position, self.speed),
+            if timeline.matches.is_empty() {
+                ""
+            } else {
+                "n next match  "
+            }
+        )
+    }
+}
+
+/// Play events from recording time `start` to `out`, taking keyboard
+/// controls from stdin and waiting at most `max_delay` between outputs
+///
+/// Returns false when the viewer quit or playback was interrupted.
+pub(crate) fn play(
+    out: &mut dyn Write,
+    events: EventStream,
+    start: Duration,
+    speed: f64,
+    max_delay: Option<Duration>,
+    timeline: &Timeline,
+) -> Result<bool> {
+    let raw = RawMode::enable()?;
+    let screen = StatusRow::new(Stream::Stdout);
+    let mut state = State::new(start, speed);
+    let mut clock = Instant::now();
+    let mut last_draw = Instant::now();
+    screen.draw(&state.status(timeline));
+
+    for event in events {
+        let event = event?;
+        if let Some(max_delay) = max_delay {
+            // Long pauses are cut short by moving the clock forward
+            state.position = state.position.max(event.time.saturating_sub(max_delay));
+        }
+        while !state.due(event.time) {
+            let timeout = if state.paused {
+                STATUS_INTERVAL
+            } else {
+                (event.time - state.position)
+                    .div_f64(state.speed)
+                    .min(STATUS_INTERVAL)
+            };
+            let key = raw.read_key(timeout)?;
+            if !state.paused {
+                let played = clock.elapsed().mul_f64(state.speed);
+                state.position = (state.position + played).min(event.time);
+            }
+            clock = Instant::now();
+
+            if let Some(control) = key.and_then(Control::from_key) {
+                state.apply(control, timeline);
+            }
+            if state.quit || tty::interrupted() {
+                return Ok(false);
+            }
+            if key.is_some() || !state.paused {
+                screen.draw(&state.status(timeline));
+                last_draw = Instant::now();
+            }
+        }
+
+        state.position = state.position.max(event.time);
+        if state.seeking_marker && matches!(event.kind, EventKind::Marker(_)) {
+            state.seeking_marker = false;
+            state.paused = true;
+            state.position = event.time;
+            screen.draw(&state.status(timeline));
+            last_draw = Instant::now();
+        }
+        if let EventKind::Output(data) = &event.kind {
+            state.step = false;
+            state.skip_idle = false;
+            // The recorded bytes, unchanged, as in non-interactive playback
+            out.write_all(data)
+                .and_then(|_| out.flush())
+                .map_err(|e| anyhow!("Failed to write output: {}", e))?;
+            if state
+                .seeking_match
+                .is_some_and(|target| event.time >= target)
+            {
+                state.seeking_match = None;
+                state.paused = true;
+                state.position = event.time;
+                screen.draw(&state.status(timeline));
+                last_draw = Instant::now();
+            }
+            // Output can clear or scroll over the status line
+            if last_draw.elapsed() >= STATUS_INTERVAL {
+                screen.draw(&state.status(timeline));
+                last_draw = Instant::now();
+            }
+        }
+    }
+
+    screen.draw(&state.status(timeline));
+    drop(screen);
+    drop(raw);
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_bindings() {
+        assert_eq!(Control::from_key(b' '), Some(Control::TogglePause));
+        assert_eq!(Control::from_key(b'.'), Some(Control::Step));
+        assert_eq!(Control::from_key(b'+'), Some(Control::Faster));
+        assert_eq!(Control::from_key(b'-'), Some(Control::Slower));
+        assert_eq!(Control::from_key(b']'), Some(Control::NextMarker));
+        assert_eq!(Control::from_key(b'n'), Some(Control::NextMatch));
+        assert_eq!(Control::from_key(b's'), Some(Control::SkipIdle));
+        assert_eq!(Control::from_key(b'q'), Some(Control::Quit));
+        assert_eq!(Control::from_key(0x03), Some(Control::Quit));
+        assert_eq!(Control::from_key(b'x'), None);
+    }
+
+    #[test]
+    fn test_state() {
+        let second = Duration::from_secs(1);
+        let timeline = Timeline {
+            duration: Duration::from_secs(100),
+            markers: Vec::new(),
+            matches: Vec::new(),
+        };
+        let mut state = State::new(Duration::ZERO, 1.0);
+        assert!(!state.due(second));
+        state.position = second;
+        assert!(state.due(second));
+
+        // Stepping only works while paused and lasts for one event
+        state.apply(Control::Step, &timeline);
+        assert!(!state.step);
+        state.apply(Control::TogglePause, &timeline);
+        assert!(!state.due(second));
+        state.apply(Control::Step, &timeline);
+        assert!(state.due(Duration::from_secs(60)));
+        assert!(state.status(&timeline).starts_with(" ⏸ 00:01 / 01:40 "));
+
+        // Skipping idle time only works while playing
+        state.step = false;
+        state.apply(Control::SkipIdle, &timeline);
+        assert!(!state.due(Duration::from_secs(60)));
+        state.apply(Control::TogglePause, &timeline);
+        state.apply(Control::SkipIdle, &timeline);
+        assert!(state.due(Duration::from_secs(60)));
+        state.apply(Control::TogglePause, &timeline);
+
+        for _ in 0..10 {
+            state.apply(Control::Faster, &timeline);
+        }
+        assert_eq!(state.speed, MAX_SPEED);
+        for _ in 0..20 {
+            state.apply(Control::Slower, &timeline);
+        }
+        assert_eq!(state.speed, MIN_SPEED);
+
+        state.apply(Control::Quit, &timeline);
+        assert!(state.quit);
+    }
+
+    #[test]
+    fn test_next_match() {
+        let timeline = Timeline {
+            duration: Duration::from_secs(100),
+            markers: Vec::new(),
+            matches: vec![Duration::from_secs(10), Duration::from_secs(40)],
+        };
+        let mut state = State::new(Duration::from_secs(10), 1.0);
+        assert!(state.status(&timeline).contains("n next match"));
+
+        // Each press moves on to the match after the one being sought
+        state.apply(Control::NextMatch, &timeline);
+        assert_eq!(state.seeking_match, Some(Duration::from_secs(40)));
+        assert!(state.due(Duration::from_secs(40)));
+        assert!(!state.due(Duration::from_secs(41)));
+        state.apply(Control::NextMatch, &timeline);
+        assert_eq!(state.seeking_match, Some(Duration::from_secs(40)));
+
+        let timeline = Timeline::default();
+        let mut state = State::new(Duration::ZERO, 1.0);
+        state.apply(Control::NextMatch, &timeline);
+        assert_eq!(state.seeking_match, None);
+        assert!(!state.status(&timeline).contains("n next match"));
+    }
+}
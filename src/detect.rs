@@ -0,0 +1,194 @@
+//! Session format detection for [`Player::open`](crate::Player::open)
+//!
+//! Formats are recognized by content rather than by name: the tar header of
+//! a container, the JSON header of an asciicast, a timing file next to a
+//! typescript, or consistent ttyrec frame headers. gzip-compressed files are
+//! detected the same way from their decompressed content, and read as a
+//! stream when played.
+
+use crate::{asciicast, container, gzip, script, ttyrec, Format};
+use anyhow::{anyhow, Result};
+use std::io::Read;
+use std::path::Path;
+
+/// Enough of a file to recognize any of the magic numbers and headers
+const HEAD_LEN: u64 = 512;
+
+/// Compression formats that are recognized but not read, with their tools
+const UNSUPPORTED: [(&[u8], &str); 3] = [
+    (b"\xfd7zXZ\x00", "xz"),
+    (b"\x28\xb5\x2f\xfd", "zstd"),
+    (b"BZh", "bzip2"),
+];
+
+/// What was found at a session path
+#[derive(Debug)]
+pub(crate) struct Detected {
+    pub(crate) format: Format,
+    /// The timing file of a typescript
+    pub(crate) timing_file: Option<String>,
+}
+
+/// Work out the format of the session at `path`
+///
+/// A typescript's timing file is looked for next to it unless `timing_file`
+/// is given; single-file recordings ignore it.
+pub(crate) fn detect(path: &str, timing_file: Option<&str>) -> Result<Detected> {
+    if Path::new(path).is_dir() {
+        return Err(anyhow!("{} is a directory, not a session file", path));
+    }
+    if let Some(timing_file) = timing_file {
+        if !Path::new(path).exists() {
+            return Err(match Path::new(timing_file).exists() {
+                true => anyhow!("Typescript file not found: {}", path),
+                false => anyhow!("Timing file not found: {}", timing_file),
+            });
+        }
+    }
+    let mut head = Vec::new();
+    gzip::open(path)
+        .and_then(|reader| reader.take(HEAD_LEN).read_to_end(&mut head))
+        .map_err(|e| anyhow!("Failed to open session file {}: {}", path, e))?;
+    // An empty typescript with a timing file is a session with no output
+    if head.is_empty() && timing_file.is_none() {
+        return Err(anyhow!("Session file {} is empty", path));
+    }
+
+    if let Some((_, tool)) = UNSUPPORTED
+        .iter()
+        .find(|(magic, _)| head.starts_with(magic))
+    {
+        return Err(anyhow!(
+            "{} is compressed with {}, which can't be played directly; decompress it first (`{} -d {}`)",
+            path,
+            tool,
+            tool,
+            path
+        ));
+    }
+
+    let single = |format| {
+        Ok(Detected {
+            format,
+            timing_file: None,
+        })
+    };
+    if container::looks_like_container(&head) {
+        return single(Format::Container);
+    }
+    if asciicast::is_asciicast(path) {
+        return single(Format::Asciicast);
+    }
+
+    // A typescript with a timing file next to it is never a ttyrec recording
+    let compressed = gzip::is_compressed(path);
+    let name = match compressed {
+        true => path.strip_suffix(".gz").unwrap_or(path),
+        false => path,
+    };
+    let candidates = match timing_file {
+        Some(timing_file) => vec![timing_file.to_string()],
+        // The timing file of a compressed typescript may be compressed too
+        None if compressed => {
+            let mut candidates = vec![format!("{}.timing", path)];
+            for candidate in timing_candidates(name) {
+                candidates.push(format!("{}.gz", candidate));
+                candidates.push(candidate);
+            }
+            candidates
+        }
+        None => timing_candidates(path),
+    };
+    let found = find_file(&candidates);
+    // A timing file given by the caller has to exist unless the name says ttyrec
+    let ttyrec_name = Format::from_path(name) == Format::Ttyrec;
+    if ttyrec::is_ttyrec(path) && (ttyrec_name || (found.is_none() && timing_file.is_none())) {
+        return single(Format::Ttyrec);
+    }
+
+    let Some(timing_file) = found else {
+        return Err(match timing_file {
+            Some(timing_file) => anyhow!("Timing file not found: {}", timing_file),
+            None => unknown(path, &candidates),
+        });
+    };
+    let format = if script::is_advanced_timing(&timing_file) {
+        Format::Advanced
+    } else {
+        Format::Scriptreplay
+    };
+    Ok(Detected {
+        format,
+        timing_file: Some(timing_file),
+    })
+}
+
+/// Timing files that may belong to a typescript, in order of preference
+fn timing_candidates(path: &str) -> Vec<String> {
+    let path = Path::new(path);
+    let mut candidates = vec![format!("{}.timing", path.display())];
+    if path.extension().is_some() {
+        candidates.push(path.with_extension("timing").display().to_string());
+    }
+    // `script` names its typescript "typescript" by default
+    if path.file_name().is_some_and(|name| name == "typescript") {
+        candidates.push(path.with_file_name("timing").display().to_string());
+    }
+    candidates
+}
+
+fn find_file(candidates: &[String]) -> Option<String> {
+    candidates
+        .iter()
+        .find(|candidate| Path::new(candidate).is_file())
+        .cloned()
+}
+
+fn unknown(path: &str, candidates: &[String]) -> anyhow::Error {
+    anyhow!(
+        "Unrecognized session format in {}: it is not an asciicast, ttyrec or container \
+         recording, and no timing file was found for it as a typescript (looked for {}); \
+         pass its timing file explicitly",
+        path,
+        candidates.join(", ")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timing_candidates() {
+        assert_eq!(
+            timing_candidates("session.log"),
+            vec!["session.log.timing", "session.timing"]
+        );
+        assert_eq!(timing_candidates("out"), vec!["out.timing"]);
+        assert_eq!(
+            timing_candidates("dir/typescript"),
+            vec!["dir/typescript.timing", "dir/timing"]
+        );
+    }
+
+    #[test]
+    fn test_missing_explicit_timing_file() {
+        // Output that happens to parse as one ttyrec frame
+        let path =
+            std::env::temp_dir().join(format!("replay-rs-detect-{}.log", std::process::id()));
+        let path = path.to_str().unwrap();
+        let mut frame = Vec::new();
+        for field in [1_700_000_000u32, 0, 2] {
+            frame.extend(field.to_le_bytes());
+        }
+        frame.extend(b"hi");
+        std::fs::write(path, &frame).unwrap();
+
+        assert_eq!(detect(path, None).unwrap().format, Format::Ttyrec);
+        let missing = format!("{}.missing.timing", path);
+        let error = detect(path, Some(&missing)).unwrap_err().to_string();
+        assert_eq!(error, format!("Timing file not found: {}", missing));
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
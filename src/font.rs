@@ -0,0 +1,237 @@
+//! Embedded bitmap font used by the image exporters
+//!
+//! Printable ASCII comes from the public domain `font8x8` glyphs, based on the
+//! IBM PC BIOS font. Box drawing and block element characters are drawn
+//! procedurally so that lines join up across cells, and anything else is
+//! shown as an outlined box.
+
+/// Width of a cell in pixels
+pub(crate) const CELL_WIDTH: usize = 8;
+
+/// Height of a cell in pixels; glyphs get a blank row above and below
+pub(crate) const CELL_HEIGHT: usize = 10;
+
+/// A glyph as one byte per pixel row, least significant bit leftmost
+pub(crate) type Glyph = [u8; CELL_HEIGHT];
+
+/// `font8x8_basic` glyphs for U+0020 to U+007E
+const ASCII: [[u8; 8]; 95] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // ' '
+    [0x18, 0x3c, 0x3c, 0x18, 0x18, 0x00, 0x18, 0x00], // '!'
+    [0x36, 0x36, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // '"'
+    [0x36, 0x36, 0x7f, 0x36, 0x7f, 0x36, 0x36, 0x00], // '#'
+    [0x0c, 0x3e, 0x03, 0x1e, 0x30, 0x1f, 0x0c, 0x00], // '$'
+    [0x00, 0x63, 0x33, 0x18, 0x0c, 0x66, 0x63, 0x00], // '%'
+    [0x1c, 0x36, 0x1c, 0x6e, 0x3b, 0x33, 0x6e, 0x00], // '&'
+    [0x06, 0x06, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00], // '\''
+    [0x18, 0x0c, 0x06, 0x06, 0x06, 0x0c, 0x18, 0x00], // '('
+    [0x06, 0x0c, 0x18, 0x18, 0x18, 0x0c, 0x06, 0x00], // ')'
+    [0x00, 0x66, 0x3c, 0xff, 0x3c, 0x66, 0x00, 0x00], // '*'
+    [0x00, 0x0c, 0x0c, 0x3f, 0x0c, 0x0c, 0x00, 0x00], // '+'
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x0c, 0x0c, 0x06], // ','
+    [0x00, 0x00, 0x00, 0x3f, 0x00, 0x00, 0x00, 0x00], // '-'
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x0c, 0x0c, 0x00], // '.'
+    [0x60, 0x30, 0x18, 0x0c, 0x06, 0x03, 0x01, 0x00], // '/'
+    [0x3e, 0x63, 0x73, 0x7b, 0x6f, 0x67, 0x3e, 0x00], // '0'
+    [0x0c, 0x0e, 0x0c, 0x0c, 0x0c, 0x0c, 0x3f, 0x00], // '1'
+    [0x1e, 0x33, 0x30, 0x1c, 0x06, 0x33, 0x3f, 0x00], // '2'
+    [0x1e, 0x33, 0x30, 0x1c, 0x30, 0x33, 0x1e, 0x00], // '3'
+    [0x38, 0x3c, 0x36, 0x33, 0x7f, 0x30, 0x78, 0x00], // '4'
+    [0x3f, 0x03, 0x1f, 0x30, 0x30, 0x33, 0x1e, 0x00], // '5'
+    [0x1c, 0x06, 0x03, 0x1f, 0x33, 0x33, 0x1e, 0x00], // '6'
+    [0x3f, 0x33, 0x30, 0x18, 0x0c, 0x0c, 0x0c, 0x00], // '7'
+    [0x1e, 0x33, 0x33, 0x1e, 0x33, 0x33, 0x1e, 0x00], // '8'
+    [0x1e, 0x33, 0x33, 0x3e, 0x30, 0x18, 0x0e, 0x00], // '9'
+    [0x00, 0x0c, 0x0c, 0x00, 0x00, 0x0c, 0x0c, 0x00], // ':'
+    [0x00, 0x0c, 0x0c, 0x00, 0x00, 0x0c, 0x0c, 0x06], // ';'
+    [0x18, 0x0c, 0x06, 0x03, 0x06, 0x0c, 0x18, 0x00], // '<'
+    [0x00, 0x00, 0x3f, 0x00, 0x00, 0x3f, 0x00, 0x00], // '='
+    [0x06, 0x0c, 0x18, 0x30, 0x18, 0x0c, 0x06, 0x00], // '>'
+    [0x1e, 0x33, 0x30, 0x18, 0x0c, 0x00, 0x0c, 0x00], // '?'
+    [0x3e, 0x63, 0x7b, 0x7b, 0x7b, 0x03, 0x1e, 0x00], // '@'
+    [0x0c, 0x1e, 0x33, 0x33, 0x3f, 0x33, 0x33, 0x00], // 'A'
+    [0x3f, 0x66, 0x66, 0x3e, 0x66, 0x66, 0x3f, 0x00], // 'B'
+    [0x3c, 0x66, 0x03, 0x03, 0x03, 0x66, 0x3c, 0x00], // 'C'
+    [0x1f, 0x36, 0x66, 0x66, 0x66, 0x36, 0x1f, 0x00], // 'D'
+    [0x7f, 0x46, 0x16, 0x1e, 0x16, 0x46, 0x7f, 0x00], // 'E'
+    [0x7f, 0x46, 0x16, 0x1e, 0x16, 0x06, 0x0f, 0x00], // 'F'
+    [0x3c, 0x66, 0x03, 0x03, 0x73, 0x66, 0x7c, 0x00], // 'G'
+    [0x33, 0x33, 0x33, 0x3f, 0x33, 0x33, 0x33, 0x00], // 'H'
+    [0x1e, 0x0c, 0x0c, 0x0c, 0x0c, 0x0c, 0x1e, 0x00], // 'I'
+    [0x78, 0x30, 0x30, 0x30, 0x33, 0x33, 0x1e, 0x00], // 'J'
+    [0x67, 0x66, 0x36, 0x1e, 0x36, 0x66, 0x67, 0x00], // 'K'
+    [0x0f, 0x06, 0x06, 0x06, 0x46, 0x66, 0x7f, 0x00], // 'L'
+    [0x63, 0x77, 0x7f, 0x7f, 0x6b, 0x63, 0x63, 0x00], // 'M'
+    [0x63, 0x67, 0x6f, 0x7b, 0x73, 0x63, 0x63, 0x00], // 'N'
+    [0x1c, 0x36, 0x63, 0x63, 0x63, 0x36, 0x1c, 0x00], // 'O'
+    [0x3f, 0x66, 0x66, 0x3e, 0x06, 0x06, 0x0f, 0x00], // 'P'
+    [0x1e, 0x33, 0x33, 0x33, 0x3b, 0x1e, 0x38, 0x00], // 'Q'
+    [0x3f, 0x66, 0x66, 0x3e, 0x36, 0x66, 0x67, 0x00], // 'R'
+    [0x1e, 0x33, 0x07, 0x0e, 0x38, 0x33, 0x1e, 0x00], // 'S'
+    [0x3f, 0x2d, 0x0c, 0x0c, 0x0c, 0x0c, 0x1e, 0x00], // 'T'
+    [0x33, 0x33, 0x33, 0x33, 0x33, 0x33, 0x3f, 0x00], // 'U'
+    [0x33, 0x33, 0x33, 0x33, 0x33, 0x1e, 0x0c, 0x00], // 'V'
+    [0x63, 0x63, 0x63, 0x6b, 0x7f, 0x77, 0x63, 0x00], // 'W'
+    [0x63, 0x63, 0x36, 0x1c, 0x1c, 0x36, 0x63, 0x00], // 'X'
+    [0x33, 0x33, 0x33, 0x1e, 0x0c, 0x0c, 0x1e, 0x00], // 'Y'
+    [0x7f, 0x63, 0x31, 0x18, 0x4c, 0x66, 0x7f, 0x00], // 'Z'
+    [0x1e, 0x06, 0x06, 0x06, 0x06, 0x06, 0x1e, 0x00], // '['
+    [0x03, 0x06, 0x0c, 0x18, 0x30, 0x60, 0x40, 0x00], // '\\'
+    [0x1e, 0x18, 0x18, 0x18, 0x18, 0x18, 0x1e, 0x00], // ']'
+    [0x08, 0x1c, 0x36, 0x63, 0x00, 0x00, 0x00, 0x00], // '^'
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xff], // '_'
+    [0x0c, 0x0c, 0x18, 0x00, 0x00, 0x00, 0x00, 0x00], // '`'
+    [0x00, 0x00, 0x1e, 0x30, 0x3e, 0x33, 0x6e, 0x00], // 'a'
+    [0x07, 0x06, 0x06, 0x3e, 0x66, 0x66, 0x3b, 0x00], // 'b'
+    [0x00, 0x00, 0x1e, 0x33, 0x03, 0x33, 0x1e, 0x00], // 'c'
+    [0x38, 0x30, 0x30, 0x3e, 0x33, 0x33, 0x6e, 0x00], // 'd'
+    [0x00, 0x00, 0x1e, 0x33, 0x3f, 0x03, 0x1e, 0x00], // 'e'
+    [0x1c, 0x36, 0x06, 0x0f, 0x06, 0x06, 0x0f, 0x00], // 'f'
+    [0x00, 0x00, 0x6e, 0x33, 0x33, 0x3e, 0x30, 0x1f], // 'g'
+    [0x07, 0x06, 0x36, 0x6e, 0x66, 0x66, 0x67, 0x00], // 'h'
+    [0x0c, 0x00, 0x0e, 0x0c, 0x0c, 0x0c, 0x1e, 0x00], // 'i'
+    [0x30, 0x00, 0x30, 0x30, 0x30, 0x33, 0x33, 0x1e], // 'j'
+    [0x07, 0x06, 0x66, 0x36, 0x1e, 0x36, 0x67, 0x00], // 'k'
+    [0x0e, 0x0c, 0x0c, 0x0c, 0x0c, 0x0c, 0x1e, 0x00], // 'l'
+    [0x00, 0x00, 0x33, 0x7f, 0x7f, 0x6b, 0x63, 0x00], // 'm'
+    [0x00, 0x00, 0x1f, 0x33, 0x33, 0x33, 0x33, 0x00], // 'n'
+    [0x00, 0x00, 0x1e, 0x33, 0x33, 0x33, 0x1e, 0x00], // 'o'
+    [0x00, 0x00, 0x3b, 0x66, 0x66, 0x3e, 0x06, 0x0f], // 'p'
+    [0x00, 0x00, 0x6e, 0x33, 0x33, 0x3e, 0x30, 0x78], // 'q'
+    [0x00, 0x00, 0x3b, 0x6e, 0x66, 0x06, 0x0f, 0x00], // 'r'
+    [0x00, 0x00, 0x3e, 0x03, 0x1e, 0x30, 0x1f, 0x00], // 's'
+    [0x08, 0x0c, 0x3e, 0x0c, 0x0c, 0x2c, 0x18, 0x00], // 't'
+    [0x00, 0x00, 0x33, 0x33, 0x33, 0x33, 0x6e, 0x00], // 'u'
+    [0x00, 0x00, 0x33, 0x33, 0x33, 0x1e, 0x0c, 0x00], // 'v'
+    [0x00, 0x00, 0x63, 0x6b, 0x7f, 0x7f, 0x36, 0x00], // 'w'
+    [0x00, 0x00, 0x63, 0x36, 0x1c, 0x36, 0x63, 0x00], // 'x'
+    [0x00, 0x00, 0x33, 0x33, 0x33, 0x3e, 0x30, 0x1f], // 'y'
+    [0x00, 0x00, 0x3f, 0x19, 0x0c, 0x26, 0x3f, 0x00], // 'z'
+    [0x38, 0x0c, 0x0c, 0x07, 0x0c, 0x0c, 0x38, 0x00], // '{'
+    [0x18, 0x18, 0x18, 0x00, 0x18, 0x18, 0x18, 0x00], // '|'
+    [0x07, 0x0c, 0x0c, 0x38, 0x0c, 0x0c, 0x07, 0x00], // '}'
+    [0x6e, 0x3b, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // '~'
+];
+
+/// Outline shown for characters the font doesn't have
+const MISSING: [u8; 8] = [0x00, 0x7e, 0x42, 0x42, 0x42, 0x42, 0x7e, 0x00];
+
+/// Look up the glyph for a character
+pub(crate) fn glyph(ch: char) -> Glyph {
+    match ch {
+        ' ' | '\u{a0}' => [0; CELL_HEIGHT],
+        ' '..='~' => padded(&ASCII[ch as usize - 0x20]),
+        '\u{2500}'..='\u{257f}' => box_drawing(ch).unwrap_or_else(|| padded(&MISSING)),
+        '\u{2580}'..='\u{2593}' => block_element(ch),
+        _ => padded(&MISSING),
+    }
+}
+
+/// Center an 8x8 glyph in the taller cell
+fn padded(rows: &[u8; 8]) -> Glyph {
+    let mut glyph = [0; CELL_HEIGHT];
+    glyph[1..9].copy_from_slice(rows);
+    glyph
+}
+
+/// Draw a box drawing character from the arms it extends in
+///
+/// Heavy, double, rounded and dashed variants are drawn as light lines.
+fn box_drawing(ch: char) -> Option<Glyph> {
+    const UP: u8 = 1;
+    const DOWN: u8 = 2;
+    const LEFT: u8 = 4;
+    const RIGHT: u8 = 8;
+
+    let arms = match ch as u32 {
+        0x2500 | 0x2501 | 0x2504 | 0x2505 | 0x2508 | 0x2509 | 0x254c | 0x254d | 0x2550 => {
+            LEFT | RIGHT
+        }
+        0x2502 | 0x2503 | 0x2506 | 0x2507 | 0x250a | 0x250b | 0x254e | 0x254f | 0x2551 => UP | DOWN,
+        0x250c..=0x250f | 0x2552..=0x2554 | 0x256d => DOWN | RIGHT,
+        0x2510..=0x2513 | 0x2555..=0x2557 | 0x256e => DOWN | LEFT,
+        0x2514..=0x2517 | 0x2558..=0x255a | 0x2570 => UP | RIGHT,
+        0x2518..=0x251b | 0x255b..=0x255d | 0x256f => UP | LEFT,
+        0x251c..=0x2523 | 0x255e..=0x2560 => UP | DOWN | RIGHT,
+        0x2524..=0x252b | 0x2561..=0x2563 => UP | DOWN | LEFT,
+        0x252c..=0x2533 | 0x2564..=0x2566 => DOWN | LEFT | RIGHT,
+        0x2534..=0x253b | 0x2567..=0x2569 => UP | LEFT | RIGHT,
+        0x253c..=0x254b | 0x256a..=0x256c => UP | DOWN | LEFT | RIGHT,
+        0x2574 | 0x2578 => LEFT,
+        0x2575 | 0x2579 => UP,
+        0x2576 | 0x257a => RIGHT,
+        0x2577 | 0x257b => DOWN,
+        _ => return None,
+    };
+
+    // Lines are two pixels thick through the middle of the cell
+    let (center_top, center_bottom) = (CELL_HEIGHT / 2 - 1, CELL_HEIGHT / 2);
+    let mut glyph = [0; CELL_HEIGHT];
+    for (row, bits) in glyph.iter_mut().enumerate() {
+        let in_center = row == center_top || row == center_bottom;
+        if (arms & UP != 0 && row <= center_bottom) || (arms & DOWN != 0 && row >= center_top) {
+            *bits |= 0x18;
+        }
+        if in_center && arms & LEFT != 0 {
+            *bits |= 0x1f;
+        }
+        if in_center && arms & RIGHT != 0 {
+            *bits |= 0xf8;
+        }
+    }
+    Some(glyph)
+}
+
+/// Draw a block element (U+2580 to U+2593)
+fn block_element(ch: char) -> Glyph {
+    let mut glyph = [0; CELL_HEIGHT];
+    match ch as u32 {
+        // Upper half
+        0x2580 => glyph[..CELL_HEIGHT / 2].fill(0xff),
+        // Lower one eighth to full block
+        code @ 0x2581..=0x2588 => {
+            let rows = ((code - 0x2580) as usize * CELL_HEIGHT).div_ceil(8);
+            glyph[CELL_HEIGHT - rows..].fill(0xff);
+        }
+        // Left seven eighths to left one eighth
+        code @ 0x2589..=0x258f => {
+            let columns = 8 - (code - 0x2588);
+            glyph.fill(((1u16 << columns) - 1) as u8);
+        }
+        // Right half
+        0x2590 => glyph.fill(0xf0),
+        // Light, medium and dark shade
+        code => {
+            let (even, odd) = match code {
+                0x2591 => (0x11, 0x44),
+                0x2592 => (0x55, 0xaa),
+                _ => (0xbb, 0xee),
+            };
+            for (row, bits) in glyph.iter_mut().enumerate() {
+                *bits = if row % 2 == 0 { even } else { odd };
+            }
+        }
+    }
+    glyph
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glyphs() {
+        assert_eq!(glyph(' '), [0; CELL_HEIGHT]);
+        assert_eq!(glyph('A')[0], 0);
+        assert_eq!(glyph('A')[1], 0x0c);
+        assert_eq!(glyph('中'), padded(&MISSING));
+
+        // Vertical lines reach both edges so they join across rows
+        let vertical = glyph('│');
+        assert_eq!(vertical[0], 0x18);
+        assert_eq!(vertical[CELL_HEIGHT - 1], 0x18);
+        assert_eq!(glyph('─')[CELL_HEIGHT / 2], 0xff);
+        assert_eq!(glyph('█'), [0xff; CELL_HEIGHT]);
+        assert_eq!(glyph('▌'), [0x0f; CELL_HEIGHT]);
+    }
+}
@@ -0,0 +1,549 @@
+//! Animated GIF export
+//!
+//! Sessions are played through the built-in [`Terminal`] emulator and each
+//! screen state is drawn with the embedded bitmap font, then encoded as a
+//! GIF89a animation. Only the part of the screen that changed is stored for
+//! each frame, which keeps typical recordings small. Everything is done in
+//! process, without any external tools.
+
+use crate::font::{self, CELL_HEIGHT, CELL_WIDTH};
+use crate::render::{screen_states, Timeline, FINAL_HOLD};
+use crate::session::{Session, DEFAULT_HEIGHT, DEFAULT_WIDTH};
+use crate::terminal::{Cell, Terminal};
+use crate::theme::{Rgb, Theme};
+use anyhow::{anyhow, Result};
+use std::collections::HashMap;
+use std::io::Write;
+use std::time::Duration;
+
+/// Shortest frame delay browsers honor, in hundredths of a second
+const MIN_DELAY: u16 = 2;
+
+/// Highest frame rate a GIF can show reliably
+const MAX_FPS: f64 = 50.0;
+
+/// Largest LZW code table a GIF decoder keeps
+const MAX_CODES: u16 = 4096;
+
+/// Options for [`export`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct GifOptions {
+    /// Color theme
+    pub theme: Theme,
+    /// Pixel scale of the 8x10 font, at least 1
+    pub scale: u16,
+    /// Terminal width; defaults to the recorded width
+    pub cols: Option<u16>,
+    /// Terminal height; defaults to the recorded height
+    pub rows: Option<u16>,
+    /// Highest number of frames per second, at most 50
+    pub fps: f64,
+    /// Longest pause kept between frames, in seconds; defaults to the
+    /// recording's own idle time limit
+    pub idle_time_limit: Option<f64>,
+    /// Playback speed multiplier
+    pub speed: f64,
+    /// Recording time to start at; earlier output is fast-forwarded
+    pub start: Option<Duration>,
+    /// Recording time to stop at
+    pub end: Option<Duration>,
+}
+
+impl Default for GifOptions {
+    fn default() -> Self {
+        Self {
+            theme: Theme::default(),
+            scale: 2,
+            cols: None,
+            rows: None,
+            fps: 30.0,
+            idle_time_limit: None,
+            speed: 1.0,
+            start: None,
+            end: None,
+        }
+    }
+}
+
+/// Render a session as an animated GIF
+pub fn export<W: Write>(session: &Session, writer: W, options: &GifOptions) -> Result<()> {
+    let cols = options
+        .cols
+        .or(session.metadata.width)
+        .unwrap_or(DEFAULT_WIDTH)
+        .max(1);
+    let rows = options
+        .rows
+        .or(session.metadata.height)
+        .unwrap_or(DEFAULT_HEIGHT)
+        .max(1);
+    let canvas = Canvas::new(cols as usize, rows as usize, options)?;
+    let mut encoder = Encoder::new(writer, canvas)?;
+
+    let timeline = Timeline {
+        fps: Some(options.fps.clamp(1.0, MAX_FPS)),
+        idle_time_limit: options.idle_time_limit.or(session.metadata.idle_time_limit),
+        speed: options.speed,
+        start: options.start,
+        end: options.end,
+    };
+    let mut terminal = Terminal::new(cols, rows);
+    terminal.set_scrollback_limit(0);
+    terminal.set_convert_eol(true);
+    let end = screen_states(session, &mut terminal, &timeline, |time, terminal| {
+        encoder.frame(time, terminal)
+    })?;
+
+    encoder.finish(end)
+}
+
+/// Pixel layout and palette of the animation
+struct Canvas {
+    width: usize,
+    height: usize,
+    scale: usize,
+    padding: usize,
+    theme: Theme,
+    palette: Vec<Rgb>,
+    exact: HashMap<Rgb, u8>,
+    nearest: HashMap<Rgb, u8>,
+}
+
+impl Canvas {
+    fn new(cols: usize, rows: usize, options: &GifOptions) -> Result<Self> {
+        let scale = options.scale.max(1) as usize;
+        let padding = CELL_WIDTH * scale;
+        let width = cols * CELL_WIDTH * scale + 2 * padding;
+        let height = rows * CELL_HEIGHT * scale + 2 * padding;
+        if width > u16::MAX as usize || height > u16::MAX as usize {
+            return Err(anyhow!(
+                "GIF of {}x{} pixels is too large; use a smaller scale or size",
+                width,
+                height
+            ));
+        }
+
+        // Theme colors first, then as much of the xterm palette as fits
+        let theme = options.theme.clone();
+        let mut palette = vec![theme.background, theme.foreground];
+        palette.extend(theme.palette);
+        palette.extend((16..=231).map(|index| theme.indexed(index)));
+        palette.extend((234..=255).map(|index| theme.indexed(index)));
+        palette.truncate(256);
+
+        let mut exact = HashMap::new();
+        for (index, color) in palette.iter().enumerate() {
+            exact.entry(*color).or_insert(index as u8);
+        }
+
+        Ok(Self {
+            width,
+            height,
+            scale,
+            padding,
+            theme,
+            palette,
+            exact,
+            nearest: HashMap::new(),
+        })
+    }
+
+    /// Palette index for a color, using the closest entry for colors not in it
+    fn index(&mut self, color: Rgb) -> u8 {
+        if let Some(&index) = self.exact.get(&color) {
+            return index;
+        }
+        let palette = &self.palette;
+        *self.nearest.entry(color).or_insert_with(|| {
+            let distance = |other: &Rgb| {
+                let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2);
+                d(color.0, other.0) + d(color.1, other.1) + d(color.2, other.2)
+            };
+            palette
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, other)| distance(other))
+                .map(|(index, _)| index as u8)
+                .unwrap_or(0)
+        })
+    }
+
+    /// Draw the terminal screen into a buffer of palette indices
+    fn draw(&mut self, terminal: &Terminal, pixels: &mut [u8]) {
+        pixels.fill(0);
+        let cursor = terminal.cursor_visible().then(|| terminal.cursor());
+
+        for (row, line) in terminal.lines().iter().enumerate() {
+            for (col, cell) in line.cells.iter().enumerate() {
+                if cell.width == 0 {
+                    continue;
+                }
+                let is_cursor = cursor == Some((col as u16, row as u16));
+                self.draw_cell(col, row, cell, is_cursor, pixels);
+            }
+        }
+    }
+
+    fn draw_cell(&mut self, col: usize, row: usize, cell: &Cell, cursor: bool, pixels: &mut [u8]) {
+        let (mut foreground, mut background) = self.theme.cell_colors(&cell.style);
+        if cursor {
+            std::mem::swap(&mut foreground, &mut background);
+        }
+        let (foreground, background) = (self.index(foreground), self.index(background));
+
+        let mut glyph = font::glyph(cell.ch);
+        if cell.style.bold {
+            for bits in &mut glyph {
+                *bits |= *bits << 1;
+            }
+        }
+        if cell.style.underline {
+            glyph[CELL_HEIGHT - 1] = 0xff;
+        }
+        if cell.style.strikethrough {
+            glyph[CELL_HEIGHT / 2] = 0xff;
+        }
+
+        // Wide characters are drawn stretched over both cells
+        let columns = cell.width as usize;
+        let x0 = self.padding + col * CELL_WIDTH * self.scale;
+        let y0 = self.padding + row * CELL_HEIGHT * self.scale;
+        for y in 0..CELL_HEIGHT * self.scale {
+            let bits = glyph[y / self.scale];
+            let offset = (y0 + y) * self.width + x0;
+            let end = (offset + CELL_WIDTH * self.scale * columns).min((y0 + y + 1) * self.width);
+            for (x, pixel) in pixels[offset..end].iter_mut().enumerate() {
+                let bit = x / self.scale / columns;
+                *pixel = if bits & (1 << bit) != 0 {
+                    foreground
+                } else {
+                    background
+                };
+            }
+        }
+    }
+}
+
+/// A screen that is waiting for the next one to know its delay
+struct PendingFrame {
+    time: Duration,
+    pixels: Vec<u8>,
+}
+
+/// Writes frames as they are produced, storing only the changed area
+struct Encoder<W: Write> {
+    writer: W,
+    canvas: Canvas,
+    /// The screen after the frames written so far
+    shown: Vec<u8>,
+    pending: Option<PendingFrame>,
+    /// Total delay of the frames written so far, in centiseconds
+    written: u64,
+}
+
+impl<W: Write> Encoder<W> {
+    fn new(mut writer: W, canvas: Canvas) -> Result<Self> {
+        let mut header = Vec::new();
+        header.extend(b"GIF89a");
+        header.extend((canvas.width as u16).to_le_bytes());
+        header.extend((canvas.height as u16).to_le_bytes());
+        // Global color table of 256 entries, 8 bits per channel
+        header.extend([0xf7, 0, 0]);
+        for index in 0..256 {
+            let Rgb(r, g, b) = canvas.palette.get(index).copied().unwrap_or(Rgb(0, 0, 0));
+            header.extend([r, g, b]);
+        }
+        // Loop forever
+        header.extend([0x21, 0xff, 0x0b]);
+        header.extend(b"NETSCAPE2.0");
+        header.extend([0x03, 0x01, 0x00, 0x00, 0x00]);
+        writer
+            .write_all(&header)
+            .map_err(|e| anyhow!("Failed to write GIF: {}", e))?;
+
+        Ok(Self {
+            writer,
+            canvas,
+            shown: Vec::new(),
+            pending: None,
+            written: 0,
+        })
+    }
+
+    fn frame(&mut self, time: Duration, terminal: &Terminal) -> Result<()> {
+        let mut pixels = vec![0; self.canvas.width * self.canvas.height];
+        self.canvas.draw(terminal, &mut pixels);
+
+        match self.pending.as_mut() {
+            None => {}
+            // Identical screens extend the pending frame
+            Some(pending) if pending.pixels == pixels => return Ok(()),
+            // A screen that would show too briefly is replaced by the next one
+            Some(pending) if centis(time) < centis(pending.time) + MIN_DELAY as u64 => {
+                pending.pixels = pixels;
+                return Ok(());
+            }
+            Some(_) => {
+                if let Some(pending) = self.pending.take() {
+                    self.write_frame(pending.pixels, centis(time))?;
+                }
+            }
+        }
+
+        self.pending = Some(PendingFrame { time, pixels });
+        Ok(())
+    }
+
+    /// Bounding box of the pixels that differ from the shown screen
+    fn changed_area(&self, pixels: &[u8]) -> (usize, usize, usize, usize) {
+        if self.shown.len() != pixels.len() {
+            return (0, 0, self.canvas.width, self.canvas.height);
+        }
+
+        let width = self.canvas.width;
+        let (mut left, mut top, mut right, mut bottom) = (usize::MAX, usize::MAX, 0, 0);
+        for (y, (old, new)) in self
+            .shown
+            .chunks(width)
+            .zip(pixels.chunks(width))
+            .enumerate()
+        {
+            if old == new {
+                continue;
+            }
+            let first = old.iter().zip(new).position(|(a, b)| a != b).unwrap_or(0);
+            let last = old.iter().zip(new).rposition(|(a, b)| a != b).unwrap_or(0);
+            left = left.min(first);
+            right = right.max(last);
+            top = top.min(y);
+            bottom = y;
+        }
+
+        if top == usize::MAX {
+            // Nothing changed, but the frame still has to hold its delay
+            return (0, 0, 1, 1);
+        }
+        (left, top, right - left + 1, bottom - top + 1)
+    }
+
+    fn write_frame(&mut self, pixels: Vec<u8>, until: u64) -> Result<()> {
+        let delay = until.saturating_sub(self.written).max(MIN_DELAY as u64);
+        self.written += delay;
+        let delay = delay.min(u16::MAX as u64) as u16;
+
+        let (left, top, width, height) = self.changed_area(&pixels);
+        let mut area = Vec::with_capacity(width * height);
+        for row in pixels.chunks(self.canvas.width).skip(top).take(height) {
+            area.extend(&row[left..left + width]);
+        }
+        self.shown = pixels;
+
+        let mut bytes = Vec::with_capacity(area.len() / 2 + 32);
+        // Graphic control extension: keep the previous frame, no transparency
+        bytes.extend([0x21, 0xf9, 0x04, 0x04]);
+        bytes.extend(delay.to_le_bytes());
+        bytes.extend([0x00, 0x00]);
+        // Image descriptor without a local color table
+        bytes.push(0x2c);
+        for value in [left, top, width, height] {
+            bytes.extend((value as u16).to_le_bytes());
+        }
+        bytes.push(0x00);
+        bytes.push(8);
+        for block in lzw_encode(&area, 8).chunks(255) {
+            bytes.push(block.len() as u8);
+            bytes.extend(block);
+        }
+        bytes.push(0x00);
+
+        self.writer
+            .write_all(&bytes)
+            .map_err(|e| anyhow!("Failed to write GIF: {}", e))
+    }
+
+    fn finish(mut self, end: Duration) -> Result<()> {
+        if let Some(pending) = self.pending.take() {
+            let until = centis(end.max(pending.time) + FINAL_HOLD);
+            self.write_frame(pending.pixels, until)?;
+        }
+        self.writer
+            .write_all(&[0x3b])
+            .and_then(|_| self.writer.flush())
+            .map_err(|e| anyhow!("Failed to write GIF: {}", e))
+    }
+}
+
+/// A duration in hundredths of a second, the unit of GIF frame delays
+fn centis(time: Duration) -> u64 {
+    (time.as_millis() as u64 + 5) / 10
+}
+
+/// Compress pixel indices with the variable code size LZW used by GIF
+fn lzw_encode(data: &[u8], min_code_size: u8) -> Vec<u8> {
+    let clear = 1u16 << min_code_size;
+    let end = clear + 1;
+
+    let mut output = Vec::new();
+    let mut buffer = 0u32;
+    let mut bits = 0u32;
+    let mut write = |code: u16, size: u8, output: &mut Vec<u8>| {
+        buffer |= (code as u32) << bits;
+        bits += size as u32;
+        while bits >= 8 {
+            output.push(buffer as u8);
+            buffer >>= 8;
+            bits -= 8;
+        }
+    };
+
+    let mut table: HashMap<(u16, u8), u16> = HashMap::new();
+    let mut next_code = end + 1;
+    let mut code_size = min_code_size + 1;
+    write(clear, code_size, &mut output);
+
+    let Some((&first, rest)) = data.split_first() else {
+        write(end, code_size, &mut output);
+        if bits > 0 {
+            output.push(buffer as u8);
+        }
+        return output;
+    };
+
+    let mut prefix = first as u16;
+    for &byte in rest {
+        if let Some(&code) = table.get(&(prefix, byte)) {
+            prefix = code;
+            continue;
+        }
+
+        write(prefix, code_size, &mut output);
+        if next_code < MAX_CODES {
+            table.insert((prefix, byte), next_code);
+            next_code += 1;
+            if next_code > (1 << code_size) && code_size < 12 {
+                code_size += 1;
+            }
+        } else {
+            write(clear, code_size, &mut output);
+            table.clear();
+            next_code = end + 1;
+            code_size = min_code_size + 1;
+        }
+        prefix = byte as u16;
+    }
+
+    write(prefix, code_size, &mut output);
+    write(end, code_size, &mut output);
+    if bits > 0 {
+        output.push(buffer as u8);
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::Event;
+
+    /// Minimal GIF LZW decoder to check the encoder against
+    fn lzw_decode(data: &[u8], min_code_size: u8) -> Vec<u8> {
+        let clear = 1usize << min_code_size;
+        let end = clear + 1;
+        let reset = || -> Vec<Vec<u8>> {
+            let mut table: Vec<Vec<u8>> = (0..clear).map(|i| vec![i as u8]).collect();
+            table.push(Vec::new());
+            table.push(Vec::new());
+            table
+        };
+
+        let mut table = reset();
+        let mut code_size = min_code_size as u32 + 1;
+        let mut previous: Option<Vec<u8>> = None;
+        let mut output = Vec::new();
+        let (mut buffer, mut bits, mut position) = (0u32, 0u32, 0);
+        loop {
+            while bits < code_size {
+                buffer |= (data[position] as u32) << bits;
+                position += 1;
+                bits += 8;
+            }
+            let code = (buffer & ((1 << code_size) - 1)) as usize;
+            buffer >>= code_size;
+            bits -= code_size;
+
+            if code == clear {
+                table = reset();
+                code_size = min_code_size as u32 + 1;
+                previous = None;
+                continue;
+            }
+            if code == end {
+                return output;
+            }
+            let entry = match (&previous, table.get(code)) {
+                (_, Some(entry)) => entry.clone(),
+                (Some(previous), None) => {
+                    let mut entry = previous.clone();
+                    entry.push(previous[0]);
+                    entry
+                }
+                (None, None) => panic!("invalid code {}", code),
+            };
+            if let Some(mut previous) = previous.take() {
+                if table.len() < MAX_CODES as usize {
+                    previous.push(entry[0]);
+                    table.push(previous);
+                    if table.len() == 1 << code_size && code_size < 12 {
+                        code_size += 1;
+                    }
+                }
+            }
+            output.extend(&entry);
+            previous = Some(entry);
+        }
+    }
+
+    #[test]
+    fn test_lzw_round_trip() {
+        let mut data: Vec<u8> = (0..20_000u32)
+            .map(|i| (i * i % 7 + i / 300) as u8)
+            .collect();
+        data.extend(std::iter::repeat_n(3, 5000));
+        assert_eq!(lzw_decode(&lzw_encode(&data, 8), 8), data);
+        assert_eq!(lzw_decode(&lzw_encode(&[], 8), 8), Vec::<u8>::new());
+        assert_eq!(lzw_decode(&lzw_encode(&[42], 8), 8), vec![42]);
+    }
+
+    #[test]
+    fn test_export() {
+        let mut session = Session::new();
+        session.metadata.width = Some(10);
+        session.metadata.height = Some(2);
+        session.events = vec![
+            Event::output(Duration::from_millis(500), "\x1b[31mhi\x1b[0m"),
+            Event::output(Duration::from_millis(501), "!"),
+            Event::output(Duration::from_secs(60), "\r\n\x1b[38;2;1;2;3mdone"),
+        ];
+        let options = GifOptions {
+            scale: 1,
+            idle_time_limit: Some(1.0),
+            ..GifOptions::default()
+        };
+
+        let mut gif = Vec::new();
+        export(&session, &mut gif, &options).unwrap();
+
+        assert!(gif.starts_with(b"GIF89a"));
+        assert_eq!(&gif[6..10], &[96, 0, 36, 0]);
+        assert_eq!(gif.last(), Some(&0x3b));
+
+        // Blank screen, "hi!", then "done" after the shortened pause
+        let delays: Vec<u16> = gif
+            .windows(4)
+            .enumerate()
+            .filter(|(_, window)| window == &[0x21, 0xf9, 0x04, 0x04])
+            .map(|(i, _)| u16::from_le_bytes([gif[i + 4], gif[i + 5]]))
+            .collect();
+        assert_eq!(delays, vec![50, 100, 200]);
+    }
+}
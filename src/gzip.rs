@@ -0,0 +1,77 @@
+//! gzip-compressed sessions
+//!
+//! Recordings are often archived compressed (`demo.cast.gz`,
+//! `game.ttyrec.gz`). Session files are opened through [`open`], which
+//! decompresses them on the fly when they start with the gzip magic number,
+//! so every format reads compressed files as a stream just like plain ones.
+
+use flate2::bufread::MultiGzDecoder;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+
+const MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// Whether data starts with the gzip magic number
+pub(crate) fn is_gzip(data: &[u8]) -> bool {
+    data.starts_with(&MAGIC)
+}
+
+/// Whether the file at `path` is gzip-compressed
+pub(crate) fn is_compressed(path: impl AsRef<Path>) -> bool {
+    let mut reader = match File::open(path) {
+        Ok(file) => BufReader::new(file),
+        Err(_) => return false,
+    };
+    reader.fill_buf().is_ok_and(is_gzip)
+}
+
+/// Open a file for buffered reading, decompressing it as it is read if it is
+/// gzip-compressed
+///
+/// Files made of several gzip members, as `cat a.gz b.gz` produces, are read
+/// through to the end like `gzip -d` does.
+pub(crate) fn open(path: impl AsRef<Path>) -> io::Result<Box<dyn BufRead>> {
+    let mut reader = BufReader::new(File::open(path)?);
+    if is_gzip(reader.fill_buf()?) {
+        Ok(Box::new(BufReader::new(MultiGzDecoder::new(reader))))
+    } else {
+        Ok(Box::new(reader))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::{Read, Write};
+
+    fn compress(data: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn test_open_decompresses_members() {
+        let path = std::env::temp_dir().join(format!("replay-rs-gzip-{}", std::process::id()));
+        let mut data = compress(b"first member\n");
+        data.extend(compress(b"second member\n"));
+        std::fs::write(&path, &data).unwrap();
+        assert!(is_compressed(&path));
+
+        let mut text = String::new();
+        open(&path).unwrap().read_to_string(&mut text).unwrap();
+        assert_eq!(text, "first member\nsecond member\n");
+
+        // Plain files are read as they are
+        std::fs::write(&path, b"plain\n").unwrap();
+        assert!(!is_compressed(&path));
+        let mut text = String::new();
+        open(&path).unwrap().read_to_string(&mut text).unwrap();
+        assert_eq!(text, "plain\n");
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,460 @@
+//! Self-contained HTML player export
+//!
+//! The session is played through the built-in [`Terminal`] emulator at
+//! export time and every screen state is stored in the page, with each
+//! distinct line rendered to HTML once. A small script then only has to
+//! swap lines to play, pause, seek and change speed, so the page works
+//! offline and from a plain `file://` URL.
+
+use crate::render::{escape, line_runs, screen_states, Timeline};
+use crate::session::{Session, DEFAULT_HEIGHT, DEFAULT_WIDTH};
+use crate::terminal::{Line, Terminal};
+use crate::theme::Theme;
+use anyhow::{anyhow, Result};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::Write;
+use std::time::Duration;
+
+/// URL schemes that are kept as clickable OSC 8 hyperlinks
+const LINK_SCHEMES: &[&str] = &["http://", "https://", "ftp://", "mailto:", "file://"];
+
+/// Options for [`export`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct HtmlOptions {
+    /// Color theme
+    pub theme: Theme,
+    /// Terminal width; defaults to the recorded width
+    pub cols: Option<u16>,
+    /// Terminal height; defaults to the recorded height
+    pub rows: Option<u16>,
+    /// Highest number of screen updates per second
+    pub fps: f64,
+    /// Longest pause kept between screen updates, in seconds; defaults to
+    /// the recording's own idle time limit
+    pub idle_time_limit: Option<f64>,
+    /// Playback speed multiplier applied before the player's own speed
+    pub speed: f64,
+    /// Recording time to start at; earlier output is fast-forwarded
+    pub start: Option<Duration>,
+    /// Recording time to stop at
+    pub end: Option<Duration>,
+}
+
+impl Default for HtmlOptions {
+    fn default() -> Self {
+        Self {
+            theme: Theme::default(),
+            cols: None,
+            rows: None,
+            fps: 30.0,
+            idle_time_limit: None,
+            speed: 1.0,
+            start: None,
+            end: None,
+        }
+    }
+}
+
+/// Render a session as a single HTML page with an embedded player
+pub fn export<W: Write>(session: &Session, mut writer: W, options: &HtmlOptions) -> Result<()> {
+    let cols = options
+        .cols
+        .or(session.metadata.width)
+        .unwrap_or(DEFAULT_WIDTH)
+        .max(1);
+    let rows = options
+        .rows
+        .or(session.metadata.height)
+        .unwrap_or(DEFAULT_HEIGHT)
+        .max(1);
+
+    let timeline = Timeline {
+        fps: Some(options.fps),
+        idle_time_limit: options.idle_time_limit.or(session.metadata.idle_time_limit),
+        speed: options.speed,
+        start: options.start,
+        end: options.end,
+    };
+    let mut terminal = Terminal::new(cols, rows);
+    terminal.set_scrollback_limit(0);
+    terminal.set_convert_eol(true);
+
+    let theme = &options.theme;
+    let mut lines: Vec<Value> = Vec::new();
+    let mut line_ids: HashMap<Line, usize> = HashMap::new();
+    let mut frames: Vec<Value> = Vec::new();
+    let end = screen_states(session, &mut terminal, &timeline, |time, terminal| {
+        let ids: Vec<usize> = terminal
+            .lines()
+            .iter()
+            .map(|line| {
+                *line_ids.entry(line.clone()).or_insert_with(|| {
+                    lines.push(Value::from(line_html(line, theme)));
+                    lines.len() - 1
+                })
+            })
+            .collect();
+        let cursor = terminal
+            .cursor_visible()
+            .then(|| terminal.cursor())
+            .map(|(col, row)| json!([col, row]));
+        frames.push(json!([seconds(time), ids, cursor]));
+        Ok(())
+    })?;
+
+    let title = session
+        .metadata
+        .title
+        .clone()
+        .or_else(|| terminal.title().map(str::to_string))
+        .or_else(|| session.metadata.command.clone())
+        .unwrap_or_else(|| "Terminal session".to_string());
+    let data = json!({
+        "cols": cols,
+        "rows": rows,
+        "duration": seconds(end),
+        "lines": lines,
+        "frames": frames,
+    });
+
+    let page = fill(
+        TEMPLATE,
+        &[
+            ("title", escape(&title)),
+            ("foreground", theme.foreground.hex()),
+            ("background", theme.background.hex()),
+            ("cols", cols.to_string()),
+            ("rows", rows.to_string()),
+            // Keep the JSON from closing the script element early
+            ("data", data.to_string().replace("</", "<\\/")),
+        ],
+    );
+    writer
+        .write_all(page.as_bytes())
+        .and_then(|_| writer.flush())
+        .map_err(|e| anyhow!("Failed to write HTML: {}", e))
+}
+
+/// Replace `{name}` placeholders in one pass, so values are never rescanned
+fn fill(template: &str, values: &[(&str, String)]) -> String {
+    let mut page = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        page.push_str(&rest[..open]);
+        rest = &rest[open..];
+        let value = values.iter().find(|(name, _)| {
+            rest[1..].starts_with(name) && rest[1 + name.len()..].starts_with('}')
+        });
+        match value {
+            Some((name, value)) => {
+                page.push_str(value);
+                rest = &rest[name.len() + 2..];
+            }
+            None => {
+                page.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    page.push_str(rest);
+    page
+}
+
+/// Seconds with millisecond precision, as the player uses them
+fn seconds(time: Duration) -> f64 {
+    (time.as_secs_f64() * 1000.0).round() / 1000.0
+}
+
+/// Render one line as HTML spans with inline styles
+fn line_html(line: &Line, theme: &Theme) -> String {
+    let mut html = String::new();
+    for run in line_runs(line) {
+        let (foreground, background) = theme.cell_colors(run.style);
+        let mut css = Vec::new();
+        if foreground != theme.foreground {
+            css.push(format!("color:{}", foreground.hex()));
+        }
+        if background != theme.background {
+            css.push(format!("background:{}", background.hex()));
+        }
+        if run.style.bold {
+            css.push("font-weight:bold".to_string());
+        }
+        if run.style.italic {
+            css.push("font-style:italic".to_string());
+        }
+        let decorations: Vec<&str> = [
+            (run.style.underline, "underline"),
+            (run.style.strikethrough, "line-through"),
+        ]
+        .iter()
+        .filter(|(enabled, _)| *enabled)
+        .map(|(_, name)| *name)
+        .collect();
+        if !decorations.is_empty() {
+            css.push(format!("text-decoration:{}", decorations.join(" ")));
+        }
+
+        let text = escape(&run.text);
+        let span = if css.is_empty() {
+            text
+        } else {
+            format!("<span style=\"{}\">{}</span>", css.join(";"), text)
+        };
+        match &run.style.link {
+            Some(link) if LINK_SCHEMES.iter().any(|scheme| link.starts_with(scheme)) => {
+                html.push_str(&format!(
+                    "<a href=\"{}\" target=\"_blank\" rel=\"noopener noreferrer\">{}</a>",
+                    escape(link),
+                    span
+                ));
+            }
+            _ => html.push_str(&span),
+        }
+    }
+    html
+}
+
+const TEMPLATE: &str = r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>{title}</title>
+<style>
+body { margin: 0; padding: 24px; background: #e8e8e8; font-family: system-ui, sans-serif; }
+.replay { display: inline-block; background: {background}; color: {foreground}; border-radius: 6px; box-shadow: 0 2px 12px rgba(0, 0, 0, 0.3); outline: none; }
+.replay-screen { position: relative; width: {cols}ch; height: calc({rows} * 1.25em); margin: 12px; overflow: hidden; font: 14px/1.25 ui-monospace, SFMono-Regular, Menlo, Monaco, Consolas, "DejaVu Sans Mono", monospace; white-space: pre; }
+.replay-row { height: 1.25em; }
+.replay-row a { color: inherit; }
+.replay-cursor { position: absolute; width: 1ch; height: 1.25em; background: {foreground}; opacity: 0.6; }
+.replay-controls { display: flex; align-items: center; gap: 8px; padding: 6px 12px; border-top: 1px solid rgba(128, 128, 128, 0.3); font-size: 12px; }
+.replay-controls button, .replay-controls select { background: none; color: inherit; border: 1px solid rgba(128, 128, 128, 0.5); border-radius: 4px; font: inherit; }
+.replay-controls button { width: 32px; cursor: pointer; }
+.replay-controls option { color: #000; }
+.replay-seek { flex: 1; }
+.replay-clock { font-variant-numeric: tabular-nums; }
+</style>
+</head>
+<body>
+<div class="replay" tabindex="0">
+<div class="replay-screen"><div class="replay-cursor"></div></div>
+<div class="replay-controls">
+<button class="replay-play" type="button" title="Play (space)">&#9654;</button>
+<input class="replay-seek" type="range" min="0" step="0.001" value="0" aria-label="Seek">
+<span class="replay-clock"></span>
+<select class="replay-speed" aria-label="Speed">
+<option value="0.5">0.5x</option><option value="1" selected>1x</option><option value="2">2x</option><option value="4">4x</option>
+</select>
+</div>
+</div>
+<script type="application/json" id="replay-data">{data}</script>
+<script>
+(function () {
+  var data = JSON.parse(document.getElementById("replay-data").textContent);
+  var player = document.querySelector(".replay");
+  var screen = player.querySelector(".replay-screen");
+  var cursor = player.querySelector(".replay-cursor");
+  var button = player.querySelector(".replay-play");
+  var seek = player.querySelector(".replay-seek");
+  var clock = player.querySelector(".replay-clock");
+  var speedSelect = player.querySelector(".replay-speed");
+
+  var rows = [];
+  for (var i = 0; i < data.rows; i++) {
+    var row = document.createElement("div");
+    row.className = "replay-row";
+    screen.insertBefore(row, cursor);
+    rows.push(row);
+  }
+
+  var shown = [];
+  var current = -1;
+  var position = 0;
+  var playing = false;
+  var startedAt = 0;
+  var startPosition = 0;
+  var speed = 1;
+  seek.max = data.duration;
+
+  // Index of the last frame shown at or before the given time
+  function frameAt(time) {
+    var low = 0;
+    var high = data.frames.length - 1;
+    while (low < high) {
+      var middle = (low + high + 1) >> 1;
+      if (data.frames[middle][0] <= time) {
+        low = middle;
+      } else {
+        high = middle - 1;
+      }
+    }
+    return low;
+  }
+
+  function render(index) {
+    if (index === current) {
+      return;
+    }
+    current = index;
+    var frame = data.frames[index];
+    for (var i = 0; i < rows.length; i++) {
+      var id = i < frame[1].length ? frame[1][i] : -1;
+      if (shown[i] !== id) {
+        rows[i].innerHTML = id < 0 ? "" : data.lines[id];
+        shown[i] = id;
+      }
+    }
+    if (frame[2]) {
+      cursor.style.display = "block";
+      cursor.style.left = frame[2][0] + "ch";
+      cursor.style.top = frame[2][1] * 1.25 + "em";
+    } else {
+      cursor.style.display = "none";
+    }
+  }
+
+  function format(time) {
+    var total = Math.floor(time);
+    var seconds = total % 60;
+    return Math.floor(total / 60) + ":" + (seconds < 10 ? "0" : "") + seconds;
+  }
+
+  function update() {
+    seek.value = position;
+    clock.textContent = format(position) + " / " + format(data.duration);
+    render(frameAt(position));
+  }
+
+  function tick(now) {
+    if (!playing) {
+      return;
+    }
+    position = startPosition + ((now - startedAt) / 1000) * speed;
+    if (position >= data.duration) {
+      position = data.duration;
+      pause();
+    }
+    update();
+    if (playing) {
+      requestAnimationFrame(tick);
+    }
+  }
+
+  function restart() {
+    startedAt = performance.now();
+    startPosition = position;
+  }
+
+  function play() {
+    if (position >= data.duration) {
+      position = 0;
+    }
+    playing = true;
+    button.innerHTML = "&#10074;&#10074;";
+    button.title = "Pause (space)";
+    restart();
+    requestAnimationFrame(tick);
+  }
+
+  function pause() {
+    playing = false;
+    button.innerHTML = "&#9654;";
+    button.title = "Play (space)";
+  }
+
+  function seekTo(time) {
+    position = Math.min(Math.max(time, 0), data.duration);
+    restart();
+    update();
+  }
+
+  button.addEventListener("click", function () {
+    playing ? pause() : play();
+  });
+  seek.addEventListener("input", function () {
+    seekTo(parseFloat(seek.value));
+  });
+  speedSelect.addEventListener("change", function () {
+    speed = parseFloat(speedSelect.value);
+    restart();
+  });
+  player.addEventListener("keydown", function (event) {
+    if (event.target !== player) {
+      return;
+    }
+    if (event.key === " ") {
+      playing ? pause() : play();
+    } else if (event.key === "ArrowLeft") {
+      seekTo(position - 5);
+    } else if (event.key === "ArrowRight") {
+      seekTo(position + 5);
+    } else {
+      return;
+    }
+    event.preventDefault();
+  });
+
+  update();
+})();
+</script>
+</body>
+</html>
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::Event;
+
+    #[test]
+    fn test_line_html() {
+        let mut terminal = Terminal::new(40, 1);
+        terminal.feed(
+            b"\x1b[31;1mred\x1b[0m <&> \x1b]8;;https://example.com\x07site\x1b]8;;\x07 \
+              \x1b]8;;javascript:alert(1)\x07bad\x1b]8;;\x07",
+        );
+        let theme = Theme::default();
+        assert_eq!(
+            line_html(&terminal.lines()[0], &theme),
+            format!(
+                "<span style=\"color:{};font-weight:bold\">red</span> &lt;&amp;&gt; \
+                 <a href=\"https://example.com\" target=\"_blank\" rel=\"noopener noreferrer\">site</a> bad",
+                theme.palette[9].hex()
+            )
+        );
+    }
+
+    #[test]
+    fn test_export_embeds_frames() {
+        let mut session = Session::new();
+        session.metadata.width = Some(20);
+        session.metadata.height = Some(2);
+        session.events = vec![
+            Event::output(Duration::from_secs(1), "</script>{rows}"),
+            Event::output(Duration::from_secs(2), "\r\nnext"),
+        ];
+
+        let mut html = Vec::new();
+        export(&session, &mut html, &HtmlOptions::default()).unwrap();
+        let html = String::from_utf8(html).unwrap();
+
+        assert_eq!(html.matches("</script>").count(), 2);
+        let start = html.find("id=\"replay-data\">").unwrap() + 17;
+        let end = html[start..].find("</script>").unwrap() + start;
+        let data: Value = serde_json::from_str(&html[start..end]).unwrap();
+        assert_eq!(data["cols"], 20);
+        assert_eq!(data["duration"], 2.0);
+        assert_eq!(data["lines"][1], "&lt;/script&gt;{rows}");
+        assert_eq!(
+            data["frames"],
+            json!([
+                [0.0, [0, 0], [0, 0]],
+                [1.0, [1, 0], [15, 0]],
+                [2.0, [1, 2], [4, 1]]
+            ])
+        );
+        assert!(!html.contains("http://") && !html.contains("https://"));
+    }
+}
@@ -8,10 +8,29 @@
 //!
 //! - **Record terminal sessions**: Capture command output with precise timing data
 //! - **Replay with speed control**: Play back sessions at different speeds (like asciinema)
+//! - **Interactive playback**: Pause, step, skip idle time, change speed and jump between markers from the keyboard
+//! - **Event iterators**: Walk a session's output lazily with [`Player::events`], or on schedule
+//! - **Progress line**: Elapsed and total time, percent, speed and next marker during playback
+//! - **Loop playback**: Repeat a session for kiosks, stopping cleanly on a signal
+//! - **Terminal restore**: Switch off modes a replayed program left on, however playback ends
+//! - **Full-text search**: Find regex matches in the output with their timestamps and context
+//! - **Seeking**: Play from a time or marker to a time, with the screen fast-forwarded to the start
 //! - **ANSI sequence handling**: Clean up problematic control sequences while preserving colors
 //! - **Cross-platform**: Works on macOS, Linux, and other Unix-like systems
 //! - **Zero external dependencies**: Built-in implementation, no need for external tools
 //! - **Multiple formats**: Support for both raw binary and cleaned text output
+//! - **Binary-safe dump**: Write the recorded bytes unchanged, or pick a [`Sanitize`] level
+//! - **One session model**: Every format reads into a [`Session`] through the [`SessionFormat`] trait
+//! - **asciicast**: Record, play and convert asciinema `.cast` files (v1 and v3 import)
+//! - **util-linux advanced timing**: Read and write `script --log-timing` multi-stream logs
+//! - **ttyrec**: Record, play and convert binary `.ttyrec` captures
+//! - **Format detection**: [`Player::open`] recognizes each format by content, gzip included
+//! - **Single-file container**: Keep the whole session, metadata and input in one `.replay` archive
+//! - **GIF export**: Render sessions to animated GIFs with a built-in terminal emulator and font
+//! - **SVG export**: Render sessions to self-contained animated SVGs with selectable text
+//! - **HTML export**: Share sessions as a single offline HTML page with a built-in player
+//! - **Text export**: Write the text a session left on screen, or a clean transcript, for tickets and grep
+//! - **Markdown export**: Split a shell session into command and output blocks for runbooks
 //!
 //! ## Quick Start
 //!
@@ -20,6 +39,9 @@
 //! use std::process::Command;
 //!
 //! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! # let dir = std::env::temp_dir().join(format!("replay-rs-doctest-{}", std::process::id()));
+//! # std::fs::create_dir_all(&dir)?;
+//! # std::env::set_current_dir(&dir)?;
 //! // Record a command
 //! let mut recorder = Recorder::new("session.log", "session.log.timing")?;
 //! let mut cmd = Command::new("echo");
@@ -29,21 +51,127 @@
 //! // Replay the session
 //! let player = Player::new("session.log.timing", "session.log")?;
 //! player.replay(1.0)?; // 1.0 = normal speed, 2.0 = 2x speed, etc.
+//! # std::fs::remove_dir_all(&dir)?;
 //! # Ok(())
 //! # }
 //! ```
 
 use anyhow::{anyhow, Result};
+use std::fs::File;
 use std::io::{Read, Write};
 use std::process::{Command, Stdio};
-use std::thread;
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+pub mod asciicast;
+#[cfg(feature = "cli")]
+pub mod cli;
+pub mod container;
+mod controls;
+mod detect;
+mod font;
+pub mod gif;
+mod gzip;
+pub mod html;
+pub mod markdown;
+pub mod playback;
+mod progress;
+mod render;
+mod restore;
+mod sanitize;
+mod script;
+pub mod search;
+mod seek;
+mod session;
+pub mod svg;
+pub mod terminal;
+pub mod text;
+mod theme;
+mod tty;
+pub mod ttyrec;
+mod utf8;
+
+pub use asciicast::AsciicastFormat;
+pub use container::ContainerFormat;
+pub use playback::{OutputEvent, OutputEvents, ScheduledEvents};
+pub use progress::Timeline;
+pub use sanitize::{Sanitize, StreamSanitizer};
+pub use script::{AdvancedFormat, ScriptreplayFormat};
+pub use search::{SearchMatch, SearchOptions};
+pub use session::{
+    Event, EventKind, EventStream, EventWriter, Metadata, Session, SessionFiles, SessionFormat,
+    DEFAULT_HEIGHT, DEFAULT_WIDTH,
+};
+pub use theme::{BuiltinTheme, Rgb, Theme};
+pub use ttyrec::TtyrecFormat;
+pub use utf8::Utf8Decoder;
+
+use asciicast::AsciicastWriter;
+use restore::Restoring;
+use script::AdvancedWriter;
+use ttyrec::TtyrecWriter;
+
+/// Session file formats understood by the recorder and player
+///
+/// The variant names double as the values of the binaries' format options.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum Format {
+    /// A typescript plus a separate timing file, as used by `script`/`scriptreplay`
+    Scriptreplay,
+    /// A single asciinema asciicast (`.cast`) file, written as v2 and read as v1, v2 or v3
+    Asciicast,
+    /// util-linux advanced timing (`O`/`I`/`S`/`H` entries) with separate log files
+    Advanced,
+    /// A single binary ttyrec file of timestamped frames
+    Ttyrec,
+    /// A single tar archive holding a typescript, its timing, metadata and input
+    Container,
+}
+
+impl Format {
+    /// Guess the format from a file name, defaulting to scriptreplay
+    pub fn from_path(path: &str) -> Self {
+        if path.ends_with(".cast") {
+            Format::Asciicast
+        } else if path.ends_with(".ttyrec") || path.ends_with(".tty") {
+            Format::Ttyrec
+        } else if path.ends_with(".replay") {
+            Format::Container
+        } else {
+            Format::Scriptreplay
+        }
+    }
+
+    /// The usual file extension for this format, without the dot
+    ///
+    /// Timing files of the formats that use one are named `<file>.timing`.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Scriptreplay | Format::Advanced => "log",
+            Format::Asciicast => "cast",
+            Format::Ttyrec => "ttyrec",
+            Format::Container => "replay",
+        }
+    }
+
+    /// The reader and writer for this format
+    pub fn session_format(self) -> &'static dyn SessionFormat {
+        match self {
+            Format::Scriptreplay => &ScriptreplayFormat,
+            Format::Asciicast => &AsciicastFormat,
+            Format::Advanced => &AdvancedFormat,
+            Format::Ttyrec => &TtyrecFormat,
+            Format::Container => &ContainerFormat,
+        }
+    }
+}
 
 /// A recorder for capturing terminal sessions with timing data
 #[derive(Debug)]
 pub struct Recorder {
     output_file: String,
     timing_file: String,
+    format: Format,
 }
 
 /// A player for replaying recorded terminal sessions
@@ -51,6 +179,165 @@ pub struct Recorder {
 pub struct Player {
     timing_file: String,
     typescript_file: String,
+    format: Format,
+}
+
+/// Options for [`Player::replay_with`] and [`Player::replay_to`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplayOptions {
+    /// Playback speed multiplier (1.0 = normal, 2.0 = 2x speed, 0.5 = half speed)
+    pub speed: f64,
+    /// Recording time to start at; earlier output is fast-forwarded through
+    /// a terminal emulator so the screen is right when playback begins
+    pub start: Option<Duration>,
+    /// Start at the first marker with this label instead of at `start`
+    pub start_marker: Option<String>,
+    /// Recording time to stop at
+    pub end: Option<Duration>,
+    /// Longest wait between two outputs, like `scriptreplay -m`; defaults
+    /// to the session's idle time limit. The cap applies before `speed`.
+    pub max_delay: Option<Duration>,
+    /// Print a header before the output and a newline after it; playback
+    /// with progress or controls shows a status line instead
+    pub banner: bool,
+    /// Show elapsed and total time, percent, speed and the next marker on
+    /// the bottom row of the terminal on stderr, or as a line on stderr
+    pub progress: bool,
+    /// How many times the session is played
+    pub repeat: Repeat,
+    /// Pause after each round before the next one starts
+    pub repeat_pause: Duration,
+    /// Reset the terminal between rounds
+    pub reset_between: bool,
+    /// Switch off terminal modes the output left on when playback ends,
+    /// fails or is interrupted: the alternate screen, a hidden cursor, mouse
+    /// reporting, colors and the like
+    pub restore_terminal: bool,
+    /// Take keyboard controls from the terminal on stdin, as
+    /// [`Player::replay_interactive`] does
+    pub interactive: bool,
+    /// Regular expression the `n` key jumps to in interactive playback
+    pub search: Option<String>,
+}
+
+impl Default for ReplayOptions {
+    fn default() -> Self {
+        Self {
+            speed: 1.0,
+            start: None,
+            start_marker: None,
+            end: None,
+            max_delay: None,
+            banner: true,
+            progress: false,
+            repeat: Repeat::default(),
+            repeat_pause: Duration::ZERO,
+            reset_between: false,
+            restore_terminal: true,
+            interactive: false,
+            search: None,
+        }
+    }
+}
+
+/// How many times [`Player::replay_with`] plays a session
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Repeat {
+    /// A fixed number of rounds, at least one
+    Times(u32),
+    /// Until the viewer quits or a signal arrives
+    Forever,
+}
+
+impl Default for Repeat {
+    fn default() -> Self {
+        Repeat::Times(1)
+    }
+}
+
+impl Repeat {
+    /// The repeat given by an optional loop count
+    ///
+    /// No count plays the session once and a count of 0 repeats it forever.
+    pub fn from_count(count: Option<u32>) -> Self {
+        match count {
+            None => Repeat::Times(1),
+            Some(0) => Repeat::Forever,
+            Some(times) => Repeat::Times(times),
+        }
+    }
+}
+
+/// Where the recorder writes each chunk of output
+enum SessionWriter {
+    Scriptreplay {
+        output: File,
+        timing: File,
+    },
+    Asciicast(AsciicastWriter<File>),
+    Advanced(AdvancedWriter<File>),
+    Ttyrec(TtyrecWriter<File>),
+    /// Containers need the sizes of their members up front, so the session
+    /// is collected in memory and written when recording ends
+    Container {
+        file: File,
+        session: Session,
+    },
+}
+
+impl SessionWriter {
+    fn write(&mut self, time: Duration, delay: Duration, data: &[u8]) -> Result<()> {
+        match self {
+            SessionWriter::Scriptreplay { output, timing } => {
+                // Write timing info: delay and size
+                writeln!(timing, "{:.6} {}", delay.as_secs_f64(), data.len())
+                    .map_err(|e| anyhow!("Failed to write timing data: {}", e))?;
+
+                // Write output
+                output
+                    .write_all(data)
+                    .map_err(|e| anyhow!("Failed to write output: {}", e))
+            }
+            SessionWriter::Asciicast(writer) => writer.write_event(&Event::output(time, data)),
+            SessionWriter::Advanced(writer) => writer.write_event(&Event::output(time, data)),
+            SessionWriter::Ttyrec(writer) => writer.write_event(&Event::output(time, data)),
+            SessionWriter::Container { session, .. } => {
+                session.events.push(Event::output(time, data));
+                Ok(())
+            }
+        }
+    }
+
+    /// Record the exit status for formats that keep it
+    fn exit(&mut self, time: Duration, status: Option<i32>) -> Result<()> {
+        let Some(status) = status else {
+            return Ok(());
+        };
+        let event = Event {
+            time,
+            kind: EventKind::Exit(status),
+        };
+        match self {
+            SessionWriter::Advanced(writer) => writer.write_event(&event),
+            SessionWriter::Container { session, .. } => {
+                session.events.push(event);
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn finish(self) -> Result<()> {
+        match self {
+            SessionWriter::Scriptreplay { .. } => Ok(()),
+            SessionWriter::Asciicast(writer) => writer.finish().map(|_| ()),
+            SessionWriter::Advanced(writer) => writer.finish(),
+            SessionWriter::Ttyrec(writer) => writer.finish().map(|_| ()),
+            SessionWriter::Container { file, session } => {
+                container::write(&session, std::io::BufWriter::new(file))
+            }
+        }
+    }
 }
 
 impl Recorder {
@@ -64,9 +351,87 @@ impl Recorder {
         Ok(Self {
             output_file: output_file.to_string(),
             timing_file: timing_file.to_string(),
+            format: Format::Scriptreplay,
+        })
+    }
+
+    /// Create a new recorder that will write a single asciicast v2 file
+    ///
+    /// # Arguments
+    ///
+    /// * `output_file` - Path where the `.cast` recording will be written
+    pub fn new_asciicast(output_file: &str) -> Result<Self> {
+        Ok(Self {
+            output_file: output_file.to_string(),
+            timing_file: String::new(),
+            format: Format::Asciicast,
+        })
+    }
+
+    /// Create a new recorder that writes util-linux advanced timing data
+    ///
+    /// The output log starts with a `Script started` header and the timing
+    /// file records header info and the exit code, like `script --log-timing`.
+    ///
+    /// # Arguments
+    ///
+    /// * `output_file` - Path where session output will be written
+    /// * `timing_file` - Path where timing data will be written
+    pub fn new_advanced(output_file: &str, timing_file: &str) -> Result<Self> {
+        Ok(Self {
+            output_file: output_file.to_string(),
+            timing_file: timing_file.to_string(),
+            format: Format::Advanced,
+        })
+    }
+
+    /// Create a new recorder that will write a single ttyrec file
+    ///
+    /// # Arguments
+    ///
+    /// * `output_file` - Path where the `.ttyrec` recording will be written
+    pub fn new_ttyrec(output_file: &str) -> Result<Self> {
+        Ok(Self {
+            output_file: output_file.to_string(),
+            timing_file: String::new(),
+            format: Format::Ttyrec,
         })
     }
 
+    /// Create a new recorder that will write a single container file
+    ///
+    /// The container is a tar archive holding the typescript, its timing
+    /// file and the recording's metadata, so they can't get separated.
+    ///
+    /// # Arguments
+    ///
+    /// * `output_file` - Path where the `.replay` container will be written
+    pub fn new_container(output_file: &str) -> Result<Self> {
+        Ok(Self {
+            output_file: output_file.to_string(),
+            timing_file: String::new(),
+            format: Format::Container,
+        })
+    }
+
+    /// Create a new recorder that will write a recording in `format`
+    ///
+    /// # Arguments
+    ///
+    /// * `format` - Format of the recording
+    /// * `output_file` - Path where the recording will be written
+    /// * `timing_file` - Path where timing data will be written, used only by
+    ///   the formats that keep it in a separate file
+    pub fn with_format(format: Format, output_file: &str, timing_file: &str) -> Result<Self> {
+        match format {
+            Format::Scriptreplay => Self::new(output_file, timing_file),
+            Format::Asciicast => Self::new_asciicast(output_file),
+            Format::Advanced => Self::new_advanced(output_file, timing_file),
+            Format::Ttyrec => Self::new_ttyrec(output_file),
+            Format::Container => Self::new_container(output_file),
+        }
+    }
+
     /// Record a command execution with timing data
     ///
     /// # Arguments
@@ -74,6 +439,8 @@ impl Recorder {
     /// * `command` - The command to execute and record
     /// * `plain_text` - If true, clean ANSI sequences for better text viewing
     pub fn record_command(&self, mut command: Command, plain_text: bool) -> Result<()> {
+        let metadata = Self::metadata_for(&command);
+
         // Start the command with pipes
         let mut child = command
             .stdout(Stdio::piped())
@@ -83,14 +450,43 @@ impl Recorder {
             .map_err(|e| anyhow!("Failed to start command: {}", e))?;
 
         // Create output files
-        let mut output_writer = std::fs::File::create(&self.output_file)
+        let output_writer = File::create(&self.output_file)
             .map_err(|e| anyhow!("Failed to create output file: {}", e))?;
-        let mut timing_writer = std::fs::File::create(&self.timing_file)
-            .map_err(|e| anyhow!("Failed to create timing file: {}", e))?;
+        let mut writer = match self.format {
+            Format::Scriptreplay => SessionWriter::Scriptreplay {
+                output: output_writer,
+                timing: File::create(&self.timing_file)
+                    .map_err(|e| anyhow!("Failed to create timing file: {}", e))?,
+            },
+            Format::Asciicast => {
+                SessionWriter::Asciicast(AsciicastWriter::new(output_writer, &metadata)?)
+            }
+            Format::Advanced => SessionWriter::Advanced(AdvancedWriter::new(
+                File::create(&self.timing_file)
+                    .map_err(|e| anyhow!("Failed to create timing file: {}", e))?,
+                output_writer,
+                None,
+                &metadata,
+                (&self.timing_file, &self.output_file, None),
+            )?),
+            Format::Ttyrec => SessionWriter::Ttyrec(TtyrecWriter::new(output_writer, &metadata)),
+            Format::Container => SessionWriter::Container {
+                file: output_writer,
+                session: Session {
+                    metadata: metadata.clone(),
+                    events: Vec::new(),
+                },
+            },
+        };
 
         let start_time = Instant::now();
         let mut last_output_time = start_time;
 
+        // Plain text mode keeps parser and decoder state across reads so that
+        // escape sequences and multibyte characters may span chunk boundaries
+        let mut sanitizer = StreamSanitizer::new();
+        let mut decoder = Utf8Decoder::new();
+
         // Handle stdout with byte-level reading for real-time output
         if let Some(mut stdout) = child.stdout.take() {
             let mut buffer = [0u8; 1024];
@@ -99,32 +495,21 @@ impl Recorder {
                 match stdout.read(&mut buffer) {
                     Ok(0) => break, // EOF
                     Ok(bytes_read) => {
-                        let now = Instant::now();
-                        let delay = now.duration_since(last_output_time).as_secs_f64();
-                        last_output_time = now;
-
                         let chunk = &buffer[..bytes_read];
                         let output_data = if plain_text {
-                            // For plain text, convert bytes to string and clean up
-                            let string_data = String::from_utf8_lossy(chunk);
-                            clean_for_display(&string_data).into_bytes()
+                            // For plain text, clean up and decode incrementally
+                            decoder.decode(&sanitizer.feed(chunk)).into_bytes()
                         } else {
                             // For binary format, keep raw bytes
                             chunk.to_vec()
                         };
 
-                        // Write timing info: delay and size
-                        writeln!(timing_writer, "{:.6} {}", delay, output_data.len())
-                            .map_err(|e| anyhow!("Failed to write timing data: {}", e))?;
-
-                        // Write output
-                        output_writer
-                            .write_all(&output_data)
-                            .map_err(|e| anyhow!("Failed to write output: {}", e))?;
-
-                        // Also display to user in real-time
-                        std::io::stdout().write_all(&output_data).unwrap_or(());
-                        std::io::stdout().flush().unwrap_or(());
+                        Self::write_chunk(
+                            &mut writer,
+                            start_time,
+                            &mut last_output_time,
+                            &output_data,
+                        )?;
                     }
                     Err(e) => {
                         eprintln!("Error reading output: {}", e);
@@ -134,11 +519,26 @@ impl Recorder {
             }
         }
 
+        // Flush sequences that were cut off by the end of output
+        if plain_text {
+            let mut trailing = decoder.decode(&sanitizer.finish());
+            trailing.push_str(&decoder.finish());
+            Self::write_chunk(
+                &mut writer,
+                start_time,
+                &mut last_output_time,
+                trailing.as_bytes(),
+            )?;
+        }
+
         // Wait for the command to complete
         let status = child
             .wait()
             .map_err(|e| anyhow!("Failed to wait for command: {}", e))?;
 
+        writer.exit(start_time.elapsed(), status.code())?;
+        writer.finish()?;
+
         if !status.success() {
             return Err(anyhow!(
                 "Command failed with exit code: {:?}",
@@ -148,112 +548,428 @@ impl Recorder {
 
         Ok(())
     }
+
+    /// Write one chunk of output along with its timing entry
+    fn write_chunk(
+        writer: &mut SessionWriter,
+        start_time: Instant,
+        last_output_time: &mut Instant,
+        output_data: &[u8],
+    ) -> Result<()> {
+        // Chunks that decoded to nothing carry no output worth timing
+        if output_data.is_empty() {
+            return Ok(());
+        }
+
+        let now = Instant::now();
+        let delay = now.duration_since(*last_output_time);
+        *last_output_time = now;
+
+        writer.write(now.duration_since(start_time), delay, output_data)?;
+
+        // Also display to user in real-time
+        std::io::stdout().write_all(output_data).unwrap_or(());
+        std::io::stdout().flush().unwrap_or(());
+
+        Ok(())
+    }
+
+    /// Describe the recording for formats that store a header
+    fn metadata_for(command: &Command) -> Metadata {
+        let dimension = |name: &str| std::env::var(name).ok().and_then(|v| v.parse().ok());
+
+        let mut command_line = vec![command.get_program().to_string_lossy().into_owned()];
+        command_line.extend(
+            command
+                .get_args()
+                .map(|arg| arg.to_string_lossy().into_owned()),
+        );
+
+        Metadata {
+            width: Some(dimension("COLUMNS").unwrap_or(DEFAULT_WIDTH)),
+            height: Some(dimension("LINES").unwrap_or(DEFAULT_HEIGHT)),
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .ok()
+                .map(|d| d.as_secs()),
+            command: Some(command_line.join(" ")),
+            env: ["SHELL", "TERM"]
+                .iter()
+                .filter_map(|name| Some((name.to_string(), std::env::var(name).ok()?)))
+                .collect(),
+            ..Metadata::default()
+        }
+    }
 }
 
 impl Player {
     /// Create a new player for the specified session files
     ///
+    /// The format is detected as by [`Player::open`], except that the
+    /// typescript's timing file is `timing_file` instead of one found next to
+    /// it. If `typescript_file` is an asciicast, ttyrec or container recording
+    /// it is played on its own and `timing_file` is ignored.
+    ///
     /// # Arguments
     ///
     /// * `timing_file` - Path to the timing data file
     /// * `typescript_file` - Path to the session output file
     pub fn new(timing_file: &str, typescript_file: &str) -> Result<Self> {
-        // Verify files exist
-        if !std::path::Path::new(timing_file).exists() {
-            return Err(anyhow!("Timing file not found: {}", timing_file));
-        }
-        if !std::path::Path::new(typescript_file).exists() {
-            return Err(anyhow!("Typescript file not found: {}", typescript_file));
-        }
+        Self::detected(typescript_file, Some(timing_file))
+    }
 
+    /// Open a session file, detecting its format
+    ///
+    /// Containers, asciicast and ttyrec recordings are recognized by their
+    /// content, and a typescript by a timing file next to it (`<file>.timing`,
+    /// `<stem>.timing`, or `timing` beside a file named `typescript`).
+    /// gzip-compressed sessions are decompressed as they are read. Files
+    /// that match none of these give an error explaining what was tried.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to the session file
+    pub fn open(path: &str) -> Result<Self> {
+        Self::detected(path, None)
+    }
+
+    /// A player for the session at `path`, in whatever format it is detected as
+    fn detected(path: &str, timing_file: Option<&str>) -> Result<Self> {
+        let detected = detect::detect(path, timing_file)?;
         Ok(Self {
-            timing_file: timing_file.to_string(),
-            typescript_file: typescript_file.to_string(),
+            timing_file: detected
+                .timing_file
+                .or_else(|| timing_file.map(str::to_string))
+                .unwrap_or_default(),
+            typescript_file: path.to_string(),
+            format: detected.format,
         })
     }
 
+    /// Create a player for session files of a known format, skipping detection
+    ///
+    /// # Arguments
+    ///
+    /// * `format` - The format of the files
+    /// * `files` - The session files; the timing file defaults to `<data>.timing`
+    pub fn with_format(format: Format, files: &SessionFiles) -> Self {
+        Self {
+            timing_file: files.timing_file(),
+            typescript_file: files.data.clone(),
+            format,
+        }
+    }
+
+    /// The format of the session being played
+    pub fn format(&self) -> Format {
+        self.format
+    }
+
+    /// Load the whole session into memory
+    pub fn session(&self) -> Result<Session> {
+        self.format.session_format().read(&self.files())
+    }
+
+    /// Write the session in another format
+    ///
+    /// Events are streamed from one format to the other, so large recordings
+    /// convert without being loaded into memory, compressed or not.
+    /// Containers are the exception on both sides.
+    ///
+    /// # Arguments
+    ///
+    /// * `format` - The format to write
+    /// * `files` - Where to write it
+    pub fn convert(&self, format: Format, files: &SessionFiles) -> Result<()> {
+        let events = self.event_stream()?;
+        let writer = format.session_format().writer(&events.metadata, files)?;
+        session::copy_events(events, writer)
+    }
+
+    /// Find the output matching the regular expression `pattern`, with the
+    /// recording time of each match and the text around it
+    ///
+    /// Matches are found in the text of the output, so colors or a timing
+    /// chunk boundary in the middle of one do not hide it.
+    pub fn search(&self, pattern: &str, options: &SearchOptions) -> Result<Vec<SearchMatch>> {
+        search::search(self.event_stream()?, pattern, options)
+    }
+
+    /// Iterate over the output of the session
+    ///
+    /// Files are read as the iterator advances, so this works on recordings
+    /// of any size. Each item carries the block's recording time, the delay
+    /// since the block before it and its bytes; read errors come out as items.
+    pub fn events(&self) -> Result<OutputEvents> {
+        Ok(OutputEvents::new(self.event_stream()?, Duration::ZERO))
+    }
+
+    /// Iterate over the output of the session, each block yielded when it is due
+    ///
+    /// This is the timing of [`Player::replay_to`] without the writing: the
+    /// iterator sleeps for each block's delay, applying the speed, time range
+    /// and idle limit from `options`, before returning it.
+    pub fn scheduled_events(&self, options: &ReplayOptions) -> Result<ScheduledEvents> {
+        let (start, events, max_delay) = self.playback(options)?;
+        Ok(ScheduledEvents::new(
+            OutputEvents::new(events, start),
+            options.speed,
+            max_delay,
+        ))
+    }
+
+    /// Read the session one event at a time
+    fn event_stream(&self) -> Result<EventStream> {
+        self.format.session_format().events(&self.files())
+    }
+
+    /// The files of the session being played
+    pub fn files(&self) -> SessionFiles {
+        match self.format {
+            Format::Scriptreplay | Format::Advanced => {
+                SessionFiles::with_timing(&self.typescript_file, &self.timing_file)
+            }
+            Format::Asciicast | Format::Ttyrec | Format::Container => {
+                SessionFiles::new(&self.typescript_file)
+            }
+        }
+    }
+
     /// Replay the recorded session
     ///
     /// # Arguments
     ///
     /// * `speed_multiplier` - Playback speed (1.0 = normal, 2.0 = 2x speed, 0.5 = half speed)
     pub fn replay(&self, speed_multiplier: f64) -> Result<()> {
-        // Read timing file
-        let timing_content = std::fs::read_to_string(&self.timing_file)
-            .map_err(|e| anyhow!("Failed to read timing file {}: {}", self.timing_file, e))?;
-
-        // Read typescript file
-        let mut typescript_file = std::fs::File::open(&self.typescript_file).map_err(|e| {
-            anyhow!(
-                "Failed to open typescript file {}: {}",
-                self.typescript_file,
-                e
-            )
-        })?;
-
-        println!("🎬 Playing back session with replay-rs");
-        println!("   Speed: {}x | Press Ctrl+C to stop", speed_multiplier);
-        println!();
-
-        // Process each timing line
-        for line in timing_content.lines() {
-            let line = line.trim();
-            if line.is_empty() {
-                continue;
-            }
+        self.replay_with(&ReplayOptions {
+            speed: speed_multiplier,
+            ..ReplayOptions::default()
+        })
+    }
 
-            // Parse timing line: "delay size" or just "delay"
-            let parts: Vec<&str> = line.split_whitespace().collect();
-            if parts.len() < 2 {
-                continue; // Skip malformed lines
-            }
+    /// Replay the recorded session with keyboard controls
+    ///
+    /// The terminal on stdin is put in raw mode and the bottom row shows the
+    /// playback time and speed. Space pauses and resumes, `.` shows the next
+    /// chunk of output while paused, `s` skips the rest of a pause in the
+    /// output, `+` and `-` double and halve the speed, `]` fast-forwards to
+    /// the next marker and pauses there, and `q` or Ctrl+C stops playback.
+    /// The terminal is restored before returning.
+    ///
+    /// # Arguments
+    ///
+    /// * `speed_multiplier` - Initial playback speed (1.0 = normal)
+    pub fn replay_interactive(&self, speed_multiplier: f64) -> Result<()> {
+        self.replay_with(&ReplayOptions {
+            speed: speed_multiplier,
+            interactive: true,
+            ..ReplayOptions::default()
+        })
+    }
 
-            let delay: f64 = parts[0]
-                .parse()
-                .map_err(|e| anyhow!("Invalid delay value '{}': {}", parts[0], e))?;
-            let size: usize = parts[1]
-                .parse()
-                .map_err(|e| anyhow!("Invalid size value '{}': {}", parts[1], e))?;
-
-            // Apply speed multiplier and skip tiny delays
-            let adjusted_delay = delay / speed_multiplier;
-            if adjusted_delay >= 0.0001 {
-                thread::sleep(Duration::from_secs_f64(adjusted_delay));
+    /// Replay part of the recorded session, or replay it with other options
+    ///
+    /// Output before the start point is shown at once: it is run through a
+    /// terminal emulator and the resulting screen is drawn, so playback from
+    /// the middle of a session looks the way the terminal did at that time.
+    ///
+    /// SIGINT, SIGTERM and SIGHUP stop playback cleanly instead of ending the
+    /// process, and terminal modes the output left on are switched off. When
+    /// the session is repeated, the files are opened again for each round so
+    /// nothing builds up between them.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - Speed, time range, repetition and controls
+    pub fn replay_with(&self, options: &ReplayOptions) -> Result<()> {
+        let timeline = if options.interactive || options.progress {
+            let mut timeline = self.timeline()?;
+            if let Some(end) = options.end {
+                timeline.duration = timeline.duration.min(end);
             }
-
-            // Read and output the block
-            let mut buffer = vec![0u8; size];
-            match typescript_file.read_exact(&mut buffer) {
-                Ok(_) => {
-                    // Output the block
-                    print!("{}", String::from_utf8_lossy(&buffer));
-                    std::io::stdout().flush().unwrap_or(());
-                }
-                Err(e) => {
-                    if e.kind() == std::io::ErrorKind::UnexpectedEof {
-                        // Reached end of file
-                        break;
-                    } else {
-                        return Err(anyhow!("Error reading typescript: {}", e));
-                    }
-                }
+            if let Some(pattern) = &options.search {
+                let matches = self.search(pattern, &SearchOptions::default())?;
+                timeline.matches = matches.into_iter().map(|found| found.time).collect();
+            }
+            Some(timeline)
+        } else {
+            None
+        };
+        // Signals stop playback so the terminal can be put back
+        let _interrupts = tty::Interrupts::catch();
+        let mut rounds = 0;
+        loop {
+            if !self.replay_once(options, timeline.as_ref())? || tty::interrupted() {
+                break;
+            }
+            rounds += 1;
+            if matches!(options.repeat, Repeat::Times(times) if rounds >= times) {
+                break;
+            }
+            if !playback::sleep(options.repeat_pause) {
+                break;
+            }
+            if options.reset_between {
+                // Full reset, for programs that left modes or colors behind
+                let mut stdout = std::io::stdout();
+                write!(stdout, "\x1bc")
+                    .and_then(|_| stdout.flush())
+                    .map_err(|e| anyhow!("Failed to write output: {}", e))?;
             }
         }
-
-        println!();
         Ok(())
     }
 
+    /// Play the session once, returning false if the viewer quit
+    fn replay_once(&self, options: &ReplayOptions, timeline: Option<&Timeline>) -> Result<bool> {
+        let Some(timeline) = timeline else {
+            self.replay_to(&mut std::io::stdout().lock(), options)?;
+            return Ok(true);
+        };
+        let (start, events, max_delay) = self.playback(options)?;
+        let mut out = Restoring::new(std::io::stdout(), options.restore_terminal);
+        if options.interactive {
+            controls::play(&mut out, events, start, options.speed, max_delay, timeline)
+        } else {
+            let events = OutputEvents::new(events, start);
+            progress::play(&mut out, events, start, options.speed, max_delay, timeline)?;
+            Ok(true)
+        }
+    }
+
+    /// Read the length and markers of the session without playing it
+    ///
+    /// The session is read once more when it is played, so this can show
+    /// progress from the start of playback.
+    pub fn timeline(&self) -> Result<Timeline> {
+        Timeline::scan(self.event_stream()?)
+    }
+
+    /// Replay the recorded session into any writer, such as a file, a socket
+    /// or a `Vec<u8>`
+    ///
+    /// Output is written as recorded, with the recorded delays between
+    /// blocks. Modes the output turns on and leaves on, such as the alternate
+    /// screen or a hidden cursor, are switched off at the end. Turn off
+    /// [`ReplayOptions::banner`] and [`ReplayOptions::restore_terminal`] to
+    /// get exactly the recorded bytes. Interactive playback needs the
+    /// terminal and is not available here.
+    ///
+    /// # Arguments
+    ///
+    /// * `out` - Where the output goes
+    /// * `options` - Speed and time range
+    pub fn replay_to<W: Write>(&self, out: &mut W, options: &ReplayOptions) -> Result<()> {
+        if options.interactive {
+            return Err(anyhow!("Interactive playback only works on the terminal"));
+        }
+        let events = self.scheduled_events(options)?;
+        let write_error = |e: std::io::Error| anyhow!("Failed to write output: {}", e);
+        // Resets whatever the output left on, however playback ends
+        let mut out = Restoring::new(out, options.restore_terminal);
+
+        if options.banner {
+            writeln!(out, "🎬 Playing back session with replay-rs").map_err(write_error)?;
+            writeln!(out, "   Speed: {}x | Press Ctrl+C to stop", options.speed)
+                .map_err(write_error)?;
+            writeln!(out).map_err(write_error)?;
+        }
+
+        for event in events {
+            out.write_all(&event?.data).map_err(write_error)?;
+            out.flush().map_err(write_error)?;
+        }
+
+        if options.banner {
+            writeln!(out).map_err(write_error)?;
+        }
+        out.flush().map_err(write_error)
+    }
+
+    /// The events to play for `options`, with the recording time they start
+    /// at and the longest wait between outputs
+    fn playback(
+        &self,
+        options: &ReplayOptions,
+    ) -> Result<(Duration, EventStream, Option<Duration>)> {
+        let start = match (&options.start_marker, options.start) {
+            (Some(label), _) => Some(seek::Start::Marker(label)),
+            (None, Some(time)) => Some(seek::Start::Time(time)),
+            (None, None) => None,
+        };
+        let (start, events) = seek::trim(self.event_stream()?, start, options.end)?;
+        let max_delay = options.max_delay.or_else(|| {
+            let limit = events.metadata.idle_time_limit?;
+            Duration::try_from_secs_f64(limit).ok()
+        });
+        Ok((start, events, max_delay))
+    }
+
     /// Replay the session without timing delays (fast dump)
+    ///
+    /// The recorded bytes are written unchanged; use [`Player::dump_to`] to
+    /// clean them up.
     pub fn dump(&self) -> Result<()> {
-        let content = std::fs::read_to_string(&self.typescript_file)
-            .map_err(|e| anyhow!("Failed to read typescript file: {}", e))?;
+        self.dump_to(&mut std::io::stdout().lock(), Sanitize::Raw)
+    }
+
+    /// Write the session's output to any writer without timing delays
+    ///
+    /// # Arguments
+    ///
+    /// * `out` - Where the output goes
+    /// * `sanitize` - How much of the output is cleaned up on the way
+    pub fn dump_to<W: Write>(&self, out: &mut W, sanitize: Sanitize) -> Result<()> {
+        let write_error = |e: std::io::Error| anyhow!("Failed to write output: {}", e);
+        if sanitize == Sanitize::Text {
+            let options = text::TextOptions {
+                mode: text::TextMode::Transcript,
+                ..text::TextOptions::default()
+            };
+            text::export(&self.session()?, &mut *out, &options)?;
+            return out.flush().map_err(write_error);
+        }
 
-        // Clean up only the problematic control sequences but preserve colors
-        let cleaned_content = clean_for_display(&content);
-        print!("{}", cleaned_content);
+        // Filters keep their state between chunks, so sequences split
+        // across reads are still handled
+        let mut sanitizer = StreamSanitizer::new();
+        let mut stripper = sanitize::AnsiStripper::new();
+        let mut write_chunk = |chunk: &[u8]| {
+            let result = match sanitize {
+                Sanitize::StripDangerous => out.write_all(&sanitizer.feed(chunk)),
+                Sanitize::StripAnsi => out.write_all(&stripper.feed(chunk)),
+                Sanitize::Raw | Sanitize::Text => out.write_all(chunk),
+            };
+            result.map_err(write_error)
+        };
+
+        match self.format {
+            Format::Scriptreplay => {
+                // Read the whole typescript, even bytes the timing file does not cover
+                let mut reader = gzip::open(&self.typescript_file)
+                    .map_err(|e| anyhow!("Failed to read typescript file: {}", e))?;
+                let mut buffer = [0u8; 8192];
+                loop {
+                    let bytes_read = reader
+                        .read(&mut buffer)
+                        .map_err(|e| anyhow!("Failed to read typescript file: {}", e))?;
+                    if bytes_read == 0 {
+                        break;
+                    }
+                    write_chunk(&buffer[..bytes_read])?;
+                }
+            }
+            _ => {
+                for event in self.events()? {
+                    write_chunk(&event?.data)?;
+                }
+            }
+        }
 
-        Ok(())
+        if sanitize == Sanitize::StripDangerous {
+            out.write_all(&sanitizer.finish()).map_err(write_error)?;
+        }
+        out.flush().map_err(write_error)
     }
 }
 
@@ -261,68 +977,33 @@ impl Player {
 ///
 /// This function removes sequences like bracketed paste mode but keeps
 /// color codes and cursor movement sequences that are useful for display.
+/// For output that arrives in chunks, use [`StreamSanitizer`] instead so that
+/// sequences split across chunk boundaries are still recognized.
 pub fn clean_for_display(input: &str) -> String {
-    let mut result = String::new();
-    let mut i = 0;
-    let chars: Vec<char> = input.chars().collect();
-
-    while i < chars.len() {
-        let ch = chars[i];
-
-        if ch == '\x1b' {
-            // Handle ESC sequences - preserve color codes but remove problematic ones
-            if i + 1 < chars.len() {
-                let next_ch = chars[i + 1];
-                if next_ch == '[' {
-                    // Look ahead to see what kind of sequence this is
-                    let mut j = i + 2;
-                    let mut sequence = String::new();
-                    while j < chars.len() && !chars[j].is_ascii_alphabetic() && chars[j] != '~' {
-                        sequence.push(chars[j]);
-                        j += 1;
-                    }
-                    if j < chars.len() {
-                        sequence.push(chars[j]);
-                    }
+    let mut sanitizer = StreamSanitizer::new();
+    let mut output = sanitizer.feed(input.as_bytes());
+    output.extend(sanitizer.finish());
 
-                    // Preserve color codes and cursor movement, but skip problematic ones
-                    if sequence.contains("2004") {
-                        // Skip bracketed paste mode sequences
-                        i = j + 1;
-                    } else {
-                        // Keep the ANSI sequence as-is (for colors, cursor movement, etc.)
-                        result.push(ch);
-                        i += 1;
-                    }
-                } else {
-                    // Keep other escape sequences
-                    result.push(ch);
-                    i += 1;
-                }
-            } else {
-                result.push(ch);
-                i += 1;
-            }
-        } else if ch == '?' && i + 5 < chars.len() {
-            // Check for bracketed paste mode sequences
-            let remaining: String = chars[i + 1..i + 5].iter().collect();
-            if remaining == "2004" && (chars[i + 5] == 'h' || chars[i + 5] == 'l') {
-                // Skip bracketed paste mode: ?2004h or ?2004l
-                i += 6;
-            } else {
-                result.push(ch);
-                i += 1;
-            }
-        } else if ch.is_control() && ch != '\t' && ch != '\n' && ch != '\r' {
-            // Skip other problematic control characters except tab, newline, carriage return
-            i += 1;
-        } else {
-            result.push(ch);
-            i += 1;
-        }
-    }
+    // Only whole ASCII bytes and whole C1 characters are removed, so this is lossless
+    String::from_utf8(output).unwrap_or_else(|e| String::from_utf8_lossy(e.as_bytes()).into_owned())
+}
 
-    result
+/// Parse a recording time written as seconds, `MM:SS` or `HH:MM:SS`
+///
+/// The seconds can have a fraction, as in `90.5` or `1:30.5`.
+pub fn parse_time(text: &str) -> Result<Duration> {
+    let invalid = || anyhow!("Invalid time '{}' (expected SS, MM:SS or HH:MM:SS)", text);
+    let mut parts = text.trim().rsplit(':');
+    let seconds: f64 = parts.next().unwrap_or("").parse().map_err(|_| invalid())?;
+    let mut total = seconds;
+    for (unit, part) in [60.0, 3600.0].into_iter().zip(parts.by_ref()) {
+        let value: u64 = part.parse().map_err(|_| invalid())?;
+        total += value as f64 * unit;
+    }
+    if parts.next().is_some() {
+        return Err(invalid());
+    }
+    Duration::try_from_secs_f64(total).map_err(|_| invalid())
 }
 
 #[cfg(test)]
@@ -332,6 +1013,18 @@ mod tests {
     use std::io::Write;
     use std::path::Path;
 
+    #[test]
+    fn test_parse_time() {
+        assert_eq!(parse_time("90").unwrap(), Duration::from_secs(90));
+        assert_eq!(parse_time("1.5").unwrap(), Duration::from_millis(1500));
+        assert_eq!(parse_time("1:30.5").unwrap(), Duration::from_millis(90_500));
+        assert_eq!(parse_time("01:02:03").unwrap(), Duration::from_secs(3723));
+        assert!(parse_time("").is_err());
+        assert!(parse_time("-1").is_err());
+        assert!(parse_time("1:2:3:4").is_err());
+        assert!(parse_time("a:10").is_err());
+    }
+
     #[test]
     fn test_clean_for_display() {
         // Test that color codes are preserved
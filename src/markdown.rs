@@ -0,0 +1,417 @@
+//! Markdown export
+//!
+//! Turns a recorded shell session into the skeleton of a runbook: every
+//! command becomes a fenced `sh` block and the output it printed a `text`
+//! block below it. Commands are found through the OSC 133 shell integration
+//! marks that shells and terminals such as fish, kitty, WezTerm, iTerm2 and
+//! VS Code (OSC 633) emit, and by looking for prompts in the rendered text
+//! when a recording has none. Output is rendered through the built-in
+//! [`Terminal`](crate::terminal::Terminal) emulator, so progress bars and
+//! colors don't leave escape sequences in the document.
+
+use crate::session::{EventKind, Session, DEFAULT_HEIGHT, DEFAULT_WIDTH};
+use crate::text::transcript_lines;
+use anyhow::{anyhow, Result};
+use std::io::Write;
+use std::time::Duration;
+
+/// Characters that end a shell prompt when no prompt is configured
+const PROMPT_ENDINGS: &[char] = &['$', '#', '%', '>', '❯', '➜'];
+
+/// Options for [`export`]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MarkdownOptions {
+    /// Terminal width used to render output; defaults to the recorded width
+    pub cols: Option<u16>,
+    /// Text that ends the shell prompt, such as `"$ "`, for recordings
+    /// without shell integration marks. By default a prompt is a word
+    /// without spaces ending in `$`, `#`, `%`, `>`, `❯` or `➜`.
+    pub prompt: Option<String>,
+    /// Turn the session's markers into `##` headings
+    pub chapters: bool,
+    /// Recording time to start at; earlier output is left out
+    pub start: Option<Duration>,
+    /// Recording time to stop at
+    pub end: Option<Duration>,
+}
+
+/// A piece of the document
+#[derive(Debug, Clone, PartialEq)]
+enum Block {
+    Heading(String),
+    Command(String),
+    Output(Vec<String>),
+    Exit(i32),
+}
+
+/// What the shell is printing, according to its integration marks
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Role {
+    Prompt,
+    Command,
+    Output,
+}
+
+/// Write a session as Markdown with fenced command and output blocks
+pub fn export<W: Write>(session: &Session, mut writer: W, options: &MarkdownOptions) -> Result<()> {
+    let cols = options
+        .cols
+        .or(session.metadata.width)
+        .unwrap_or(DEFAULT_WIDTH)
+        .max(1);
+    let rows = session.metadata.height.unwrap_or(DEFAULT_HEIGHT).max(1);
+
+    // Output between markers, each part headed by the marker before it
+    let mut parts: Vec<(Option<&str>, Vec<u8>)> = vec![(None, Vec::new())];
+    for event in &session.events {
+        if options.end.is_some_and(|end| event.time > end) {
+            break;
+        }
+        if options.start.is_some_and(|start| event.time < start) {
+            continue;
+        }
+        match &event.kind {
+            EventKind::Output(data) => parts.last_mut().unwrap().1.extend_from_slice(data),
+            EventKind::Marker(label) if options.chapters => parts.push((Some(label), Vec::new())),
+            _ => {}
+        }
+    }
+    let integrated = parts.iter().any(|(_, data)| !shell_marks(data).is_empty());
+
+    let mut blocks = Vec::new();
+    for (heading, data) in &parts {
+        if let Some(heading) = heading {
+            blocks.push(Block::Heading(heading.to_string()));
+        }
+        if integrated {
+            integrated_blocks(data, cols, rows, &mut blocks)?;
+        } else {
+            prompt_blocks(data, cols, rows, options.prompt.as_deref(), &mut blocks)?;
+        }
+    }
+
+    let write_error = |e: std::io::Error| anyhow!("Failed to write Markdown: {}", e);
+    if let Some(title) = &session.metadata.title {
+        writeln!(writer, "# {}\n", title.trim()).map_err(write_error)?;
+    }
+    for block in merge_output(blocks) {
+        match block {
+            Block::Heading(heading) => writeln!(writer, "## {}\n", heading.trim()),
+            Block::Command(command) => write_fenced(&mut writer, "sh", &command),
+            Block::Output(lines) => write_fenced(&mut writer, "text", &lines.join("\n")),
+            Block::Exit(status) => writeln!(writer, "_Exited with status {}._\n", status),
+        }
+        .map_err(write_error)?;
+    }
+    writer.flush().map_err(write_error)
+}
+
+/// Split output at the shell's integration marks
+fn integrated_blocks(data: &[u8], cols: u16, rows: u16, blocks: &mut Vec<Block>) -> Result<()> {
+    // Anything before the first prompt, like a login banner, is output
+    let mut role = Role::Output;
+    let mut start = 0;
+    for mark in shell_marks(data) {
+        push_piece(&data[start..mark.start], role, cols, rows, blocks)?;
+        start = mark.end;
+        match mark.kind {
+            b'A' => role = Role::Prompt,
+            b'B' => role = Role::Command,
+            b'C' => role = Role::Output,
+            b'D' => {
+                role = Role::Output;
+                if let Some(status) = mark.status.filter(|status| *status != 0) {
+                    blocks.push(Block::Exit(status));
+                }
+            }
+            _ => {}
+        }
+    }
+    push_piece(&data[start..], role, cols, rows, blocks)
+}
+
+fn push_piece(
+    data: &[u8],
+    role: Role,
+    cols: u16,
+    rows: u16,
+    blocks: &mut Vec<Block>,
+) -> Result<()> {
+    if role == Role::Prompt || data.is_empty() {
+        return Ok(());
+    }
+    let lines = transcript_lines(data, cols, rows)?;
+    if lines.is_empty() {
+        return Ok(());
+    }
+    blocks.push(match role {
+        Role::Command => Block::Command(lines.join("\n")),
+        _ => Block::Output(lines),
+    });
+    Ok(())
+}
+
+/// Split rendered output at the lines that start with a prompt
+fn prompt_blocks(
+    data: &[u8],
+    cols: u16,
+    rows: u16,
+    prompt: Option<&str>,
+    blocks: &mut Vec<Block>,
+) -> Result<()> {
+    let mut output = Vec::new();
+    for line in transcript_lines(data, cols, rows)? {
+        match command_after_prompt(&line, prompt) {
+            Some(command) => {
+                if !output.is_empty() {
+                    blocks.push(Block::Output(std::mem::take(&mut output)));
+                }
+                // A prompt without a command, such as the one left at the end
+                if !command.is_empty() {
+                    blocks.push(Block::Command(command.to_string()));
+                }
+            }
+            None => output.push(line),
+        }
+    }
+    if !output.is_empty() {
+        blocks.push(Block::Output(output));
+    }
+    Ok(())
+}
+
+/// The command typed after the prompt, if the line starts with one
+fn command_after_prompt<'a>(line: &'a str, prompt: Option<&str>) -> Option<&'a str> {
+    match prompt {
+        Some(prompt) => {
+            // Lines have trailing spaces trimmed, so a bare prompt loses its own
+            let end = match line.find(prompt) {
+                Some(index) => index + prompt.len(),
+                None if line.ends_with(prompt.trim_end()) && !prompt.trim_end().is_empty() => {
+                    line.len()
+                }
+                None => return None,
+            };
+            Some(line[end..].trim())
+        }
+        None => {
+            let (word, command) = line.split_once(' ').unwrap_or((line, ""));
+            let mut chars = word.chars().rev();
+            let last = chars.next()?;
+            // Rules out percentages such as `100%` and arrows such as `==>`
+            let is_prompt = PROMPT_ENDINGS.contains(&last)
+                && !chars
+                    .next()
+                    .is_some_and(|c| c.is_ascii_digit() || c == '-' || c == '=');
+            is_prompt.then(|| command.trim())
+        }
+    }
+}
+
+/// Join output blocks that follow each other and drop blank edges
+fn merge_output(blocks: Vec<Block>) -> Vec<Block> {
+    let mut merged: Vec<Block> = Vec::new();
+    for block in blocks {
+        match (merged.last_mut(), block) {
+            (Some(Block::Output(lines)), Block::Output(more)) => lines.extend(more),
+            (_, block) => merged.push(block),
+        }
+    }
+    merged.retain_mut(|block| match block {
+        Block::Output(lines) => {
+            while lines.last().is_some_and(|line| line.is_empty()) {
+                lines.pop();
+            }
+            while lines.first().is_some_and(|line| line.is_empty()) {
+                lines.remove(0);
+            }
+            !lines.is_empty()
+        }
+        _ => true,
+    });
+    merged
+}
+
+/// Write a fenced code block long enough not to be closed by its content
+fn write_fenced<W: Write>(writer: &mut W, language: &str, content: &str) -> std::io::Result<()> {
+    let longest = content.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    let fence = "`".repeat(longest.max(2) + 1);
+    writeln!(writer, "{}{}\n{}\n{}\n", fence, language, content, fence)
+}
+
+/// An OSC 133 or OSC 633 shell integration mark
+#[derive(Debug, Clone, PartialEq)]
+struct ShellMark {
+    /// Byte range of the whole sequence
+    start: usize,
+    end: usize,
+    /// `A` prompt start, `B` command start, `C` output start or
+    /// `D` command finished
+    kind: u8,
+    /// Exit status given with `D`
+    status: Option<i32>,
+}
+
+/// Find the shell integration marks in a block of output
+fn shell_marks(data: &[u8]) -> Vec<ShellMark> {
+    let mut marks = Vec::new();
+    let mut index = 0;
+    while let Some(offset) = find(&data[index..], b"\x1b]") {
+        let start = index + offset;
+        let body_start = start + 2;
+        // The string ends with BEL or ST
+        let Some((body_end, end)) = data[body_start..]
+            .iter()
+            .position(|b| *b == 0x07 || *b == 0x1b)
+            .map(|len| body_start + len)
+            .and_then(|body_end| match data[body_end] {
+                0x07 => Some((body_end, body_end + 1)),
+                _ if data.get(body_end + 1) == Some(&b'\\') => Some((body_end, body_end + 2)),
+                _ => None,
+            })
+        else {
+            index = body_start;
+            continue;
+        };
+        index = end;
+
+        let body = String::from_utf8_lossy(&data[body_start..body_end]);
+        let mut fields = body.split(';');
+        if !matches!(fields.next(), Some("133" | "633")) {
+            continue;
+        }
+        let Some(&[kind]) = fields.next().map(str::as_bytes) else {
+            continue;
+        };
+        if !matches!(kind, b'A'..=b'D') {
+            continue;
+        }
+        let status = (kind == b'D')
+            .then(|| fields.next().and_then(|status| status.parse().ok()))
+            .flatten();
+        marks.push(ShellMark {
+            start,
+            end,
+            kind,
+            status,
+        });
+    }
+    marks
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::Event;
+
+    fn export_markdown(session: &Session, options: &MarkdownOptions) -> String {
+        let mut markdown = Vec::new();
+        export(session, &mut markdown, options).unwrap();
+        String::from_utf8(markdown).unwrap()
+    }
+
+    #[test]
+    fn test_shell_marks() {
+        let marks =
+            shell_marks(b"\x1b]133;A\x07$ \x1b]133;B\x1b\\ls\r\n\x1b]0;title\x07\x1b]633;D;2\x07");
+        let kinds: Vec<(u8, Option<i32>)> = marks.iter().map(|m| (m.kind, m.status)).collect();
+        assert_eq!(kinds, vec![(b'A', None), (b'B', None), (b'D', Some(2))]);
+        assert_eq!((marks[0].start, marks[0].end), (0, 8));
+        assert_eq!((marks[1].start, marks[1].end), (10, 19));
+    }
+
+    #[test]
+    fn test_command_after_prompt() {
+        assert_eq!(
+            command_after_prompt("user@host:~$ ls -la", None),
+            Some("ls -la")
+        );
+        assert_eq!(command_after_prompt("$", None), Some(""));
+        assert_eq!(
+            command_after_prompt("❯ cargo test", None),
+            Some("cargo test")
+        );
+        assert_eq!(command_after_prompt("100% done", None), None);
+        assert_eq!(command_after_prompt("==> Pouring", None), None);
+        assert_eq!(command_after_prompt("total 8", None), None);
+
+        let prompt = Some("]$ ");
+        assert_eq!(
+            command_after_prompt("[me@host dir]$ make", prompt),
+            Some("make")
+        );
+        assert_eq!(command_after_prompt("[me@host dir]$", prompt), Some(""));
+        assert_eq!(command_after_prompt("$ make", prompt), None);
+    }
+
+    #[test]
+    fn test_export_with_shell_integration() {
+        let mut session = Session::new();
+        session.metadata.title = Some("Deploy".to_string());
+        session.metadata.width = Some(40);
+        session.events = vec![
+            Event::output(Duration::ZERO, "Welcome\r\n\x1b]133;A\x07~ $ \x1b]133;B\x07"),
+            Event::output(Duration::from_secs(1), "make\r\n\x1b]133;C\x07[  ] 0%\r"),
+            Event::output(Duration::from_secs(2), "[##] 100%\r\n\x1b]133;D;0\x07"),
+            Event {
+                time: Duration::from_secs(3),
+                kind: EventKind::Marker("Check".to_string()),
+            },
+            Event::output(
+                Duration::from_secs(3),
+                "\x1b]133;A\x07~ $ \x1b]133;B\x07cat ```\r\n\x1b]133;C\x07no ``` here\r\n\x1b]133;D;1\x07\x1b]133;A\x07~ $ ",
+            ),
+        ];
+
+        let options = MarkdownOptions {
+            chapters: true,
+            ..MarkdownOptions::default()
+        };
+        assert_eq!(
+            export_markdown(&session, &options),
+            "# Deploy\n\n\
+             ```text\nWelcome\n```\n\n\
+             ```sh\nmake\n```\n\n\
+             ```text\n[##] 100%\n```\n\n\
+             ## Check\n\n\
+             ````sh\ncat ```\n````\n\n\
+             ````text\nno ``` here\n````\n\n\
+             _Exited with status 1._\n\n"
+        );
+
+        // Markers are only headings when asked for
+        let markdown = export_markdown(&session, &MarkdownOptions::default());
+        assert!(!markdown.contains("## Check"));
+
+        let options = MarkdownOptions {
+            start: Some(Duration::from_secs(3)),
+            ..MarkdownOptions::default()
+        };
+        assert!(export_markdown(&session, &options).starts_with("# Deploy\n\n````sh\ncat"));
+    }
+
+    #[test]
+    fn test_export_with_prompt_detection() {
+        let mut session = Session::new();
+        session.metadata.width = Some(40);
+        session.events = vec![
+            Event::output(Duration::ZERO, "me@box:~$ ls\r\na.txt  b.txt\r\n"),
+            Event::output(
+                Duration::from_secs(1),
+                "me@box:~$ \x1b[1mecho\x1b[0m hi\r\nhi\r\n\r\nme@box:~$ ",
+            ),
+        ];
+
+        assert_eq!(
+            export_markdown(&session, &MarkdownOptions::default()),
+            "```sh\nls\n```\n\n```text\na.txt  b.txt\n```\n\n\
+             ```sh\necho hi\n```\n\n```text\nhi\n```\n\n"
+        );
+    }
+}
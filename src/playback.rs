@@ -0,0 +1,181 @@
+//! Iterating over the output of a session
+//!
+//! [`Player::events`](crate::Player::events) reads a session lazily and
+//! yields each block of output with its recording time and the delay since
+//! the block before it, so tools can walk a recording without going through
+//! the replay loop. [`Player::scheduled_events`](crate::Player::scheduled_events)
+//! does the waiting as well and yields each block when it is due.
+
+use crate::session::{EventKind, EventStream, Metadata};
+use crate::tty;
+use anyhow::Result;
+use std::thread;
+use std::time::Duration;
+
+/// A block of output and when it appeared
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputEvent {
+    /// Recording time of the block
+    pub time: Duration,
+    /// Time since the block before it, or since the start of playback
+    pub delay: Duration,
+    /// The bytes written to the terminal
+    pub data: Vec<u8>,
+}
+
+/// The output blocks of a session, read as they are needed
+///
+/// Errors reading the session are yielded as items; iteration can go on
+/// after them where the format allows it.
+pub struct OutputEvents {
+    events: EventStream,
+    last: Duration,
+}
+
+impl OutputEvents {
+    /// Output of `events`, with delays counted from recording time `start`
+    pub(crate) fn new(events: EventStream, start: Duration) -> Self {
+        Self {
+            events,
+            last: start,
+        }
+    }
+
+    /// Metadata known before the first event
+    pub fn metadata(&self) -> &Metadata {
+        &self.events.metadata
+    }
+}
+
+impl Iterator for OutputEvents {
+    type Item = Result<OutputEvent>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let event = match self.events.next()? {
+                Ok(event) => event,
+                Err(e) => return Some(Err(e)),
+            };
+            if let EventKind::Output(data) = event.kind {
+                let delay = event.time.saturating_sub(self.last);
+                self.last = event.time;
+                return Some(Ok(OutputEvent {
+                    time: event.time,
+                    delay,
+                    data,
+                }));
+            }
+        }
+    }
+}
+
+/// Output blocks yielded when they are due
+///
+/// Each call to `next` sleeps for the block's delay, capped and divided by
+/// the playback speed, before returning it. Iteration ends early when a
+/// looping replay is interrupted by a signal.
+pub struct ScheduledEvents {
+    events: OutputEvents,
+    speed: f64,
+    max_delay: Option<Duration>,
+}
+
+impl ScheduledEvents {
+    pub(crate) fn new(events: OutputEvents, speed: f64, max_delay: Option<Duration>) -> Self {
+        Self {
+            events,
+            speed,
+            max_delay,
+        }
+    }
+
+    /// Metadata known before the first event
+    pub fn metadata(&self) -> &Metadata {
+        self.events.metadata()
+    }
+}
+
+/// Longest single sleep, so an interruption is noticed quickly
+const SLEEP_STEP: Duration = Duration::from_millis(100);
+
+/// Sleep for `duration`, returning false if playback was interrupted
+pub(crate) fn sleep(duration: Duration) -> bool {
+    let mut remaining = duration;
+    while !remaining.is_zero() {
+        if tty::interrupted() {
+            return false;
+        }
+        let step = remaining.min(SLEEP_STEP);
+        thread::sleep(step);
+        remaining -= step;
+    }
+    !tty::interrupted()
+}
+
+/// How long to wait before a block with this delay, at `speed` and with
+/// waits capped at `max_delay` recording time
+pub(crate) fn wait(delay: Duration, speed: f64, max_delay: Option<Duration>) -> Duration {
+    let delay = match max_delay {
+        Some(max_delay) => delay.min(max_delay),
+        None => delay,
+    };
+    Duration::try_from_secs_f64(delay.as_secs_f64() / speed).unwrap_or(Duration::ZERO)
+}
+
+impl Iterator for ScheduledEvents {
+    type Item = Result<OutputEvent>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let event = self.events.next()?;
+        if let Ok(event) = &event {
+            // Skip tiny delays
+            let wait = wait(event.delay, self.speed, self.max_delay);
+            if wait >= Duration::from_micros(100) && !sleep(wait) {
+                return None;
+            }
+        }
+        Some(event)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::Event;
+
+    fn output_events() -> OutputEvents {
+        let events = vec![
+            Event::output(Duration::from_secs(1), "one"),
+            Event {
+                time: Duration::from_secs(2),
+                kind: EventKind::Marker("mark".to_string()),
+            },
+            Event::output(Duration::from_secs(4), "two"),
+        ];
+        let stream = EventStream::new(Metadata::default(), events.into_iter().map(Ok));
+        OutputEvents::new(stream, Duration::ZERO)
+    }
+
+    #[test]
+    fn test_output_events() {
+        let events: Vec<_> = output_events().map(|event| event.unwrap()).collect();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].delay, Duration::from_secs(1));
+        assert_eq!(events[1].time, Duration::from_secs(4));
+        assert_eq!(events[1].delay, Duration::from_secs(3));
+        assert_eq!(events[1].data, b"two");
+    }
+
+    #[test]
+    fn test_scheduled_wait() {
+        let max_delay = Some(Duration::from_secs(2));
+        assert_eq!(
+            wait(Duration::from_secs(1), 2.0, max_delay),
+            Duration::from_millis(500)
+        );
+        assert_eq!(
+            wait(Duration::from_secs(30), 2.0, max_delay),
+            Duration::from_secs(1)
+        );
+    }
+}
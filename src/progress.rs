@@ -0,0 +1,261 @@
+//! Playback progress
+//!
+//! A [`Timeline`] is read from the session files before playback starts, so
+//! the total time and the markers are known up front. The status line built
+//! from it is drawn on the bottom row of the terminal, which a scroll region
+//! keeps out of the recording's way, or written to stderr as a single line
+//! that is redrawn in place when stderr is not a terminal.
+
+use crate::playback::{self, OutputEvents};
+use crate::session::{EventKind, EventStream, DEFAULT_HEIGHT, DEFAULT_WIDTH};
+use crate::tty::{self, Stream};
+use anyhow::{anyhow, Result};
+use std::io::Write;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Longest wait between status line updates
+pub(crate) const STATUS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Width of the progress bar in the status line
+const BAR_WIDTH: usize = 20;
+
+/// The length and markers of a session
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Timeline {
+    /// Recording time of the last event
+    pub duration: Duration,
+    /// Markers in time order, with their labels
+    pub markers: Vec<(Duration, String)>,
+    /// Times of search matches in order, when playing with a search pattern
+    pub matches: Vec<Duration>,
+}
+
+impl Timeline {
+    /// Read the timeline of a session from its events
+    pub(crate) fn scan(events: EventStream) -> Result<Self> {
+        let mut timeline = Timeline::default();
+        for event in events {
+            let event = event?;
+            timeline.duration = timeline.duration.max(event.time);
+            if let EventKind::Marker(label) = event.kind {
+                timeline.markers.push((event.time, label));
+            }
+        }
+        Ok(timeline)
+    }
+
+    /// The first marker after `position`
+    pub fn next_marker(&self, position: Duration) -> Option<&(Duration, String)> {
+        self.markers.iter().find(|(time, _)| *time > position)
+    }
+
+    /// The time of the first search match after `position`
+    pub fn next_match(&self, position: Duration) -> Option<Duration> {
+        self.matches.iter().copied().find(|&time| time > position)
+    }
+
+    /// How far `position` is into the session, from 0.0 to 1.0
+    pub fn fraction(&self, position: Duration) -> f64 {
+        if self.duration.is_zero() {
+            return 1.0;
+        }
+        (position.as_secs_f64() / self.duration.as_secs_f64()).clamp(0.0, 1.0)
+    }
+
+    /// Elapsed and total time, a progress bar, the percentage, the speed and
+    /// the next marker
+    pub fn status(&self, position: Duration, speed: f64) -> String {
+        let fraction = self.fraction(position);
+        let filled = (fraction * BAR_WIDTH as f64).round() as usize;
+        let mut status = format!(
+            "{} / {} {}{} {:>3}%  {}x",
+            clock(position.min(self.duration)),
+            clock(self.duration),
+            "█".repeat(filled),
+            "░".repeat(BAR_WIDTH - filled),
+            (fraction * 100.0).floor(),
+            speed
+        );
+        if let Some((time, label)) = self.next_marker(position) {
+            status.push_str(&format!("  next: {} at {}", label, clock(*time)));
+        }
+        status
+    }
+}
+
+/// A recording time as `MM:SS`, or `H:MM:SS` from an hour on
+fn clock(time: Duration) -> String {
+    let seconds = time.as_secs();
+    if seconds >= 3600 {
+        format!(
+            "{}:{:02}:{:02}",
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60
+        )
+    } else {
+        format!("{:02}:{:02}", seconds / 60, seconds % 60)
+    }
+}
+
+/// A status line kept on the bottom row of a terminal, put back when dropped
+pub(crate) struct StatusRow {
+    stream: Stream,
+    /// Row of the status line, if the stream is a terminal with room for one
+    row: Option<u16>,
+    /// Rewrite the line in place with a carriage return instead
+    inline: bool,
+    cols: u16,
+}
+
+impl StatusRow {
+    /// Reserve the bottom row of the terminal on `stream`, clearing the screen
+    pub(crate) fn new(stream: Stream) -> Self {
+        let terminal = stream.is_terminal();
+        let (cols, rows) = stream.size().unwrap_or((DEFAULT_WIDTH, DEFAULT_HEIGHT));
+        let row = (terminal && rows >= 3).then_some(rows);
+        if let Some(row) = row {
+            let mut out = stream.writer();
+            // Keep the recording's output above the status line
+            write!(out, "\x1b[H\x1b[2J\x1b[1;{}r\x1b[H", row - 1).unwrap_or(());
+            out.flush().unwrap_or(());
+        }
+        // Without a terminal on stderr there is no screen to corrupt
+        let inline = stream == Stream::Stderr && !terminal;
+        Self {
+            stream,
+            row,
+            inline,
+            cols,
+        }
+    }
+
+    /// Draw `status` in reverse video, or rewrite it in place when the
+    /// stream is not a terminal
+    pub(crate) fn draw(&self, status: &str) {
+        let mut out = self.stream.writer();
+        match self.row {
+            Some(row) => {
+                let status: String = status.chars().take(self.cols as usize).collect();
+                write!(
+                    out,
+                    "\x1b7\x1b[{};1H\x1b[0;7m{:<width$}\x1b[0m\x1b8",
+                    row,
+                    status,
+                    width = self.cols as usize
+                )
+                .unwrap_or(());
+            }
+            None if self.inline => {
+                // Padded so a shorter line covers the one before it
+                write!(out, "\r{:<width$}", status, width = self.cols as usize).unwrap_or(())
+            }
+            None => {}
+        }
+        out.flush().unwrap_or(());
+    }
+}
+
+impl Drop for StatusRow {
+    fn drop(&mut self) {
+        let mut out = self.stream.writer();
+        match self.row {
+            Some(row) => {
+                write!(out, "\x1b7\x1b[{};1H\x1b[0m\x1b[2K\x1b[r\x1b8", row).unwrap_or(());
+                writeln!(out, "\x1b[0m").unwrap_or(());
+            }
+            None if self.inline => writeln!(out).unwrap_or(()),
+            None => {}
+        }
+        out.flush().unwrap_or(());
+    }
+}
+
+/// Play output to `out` with a progress line on stderr
+pub(crate) fn play(
+    out: &mut dyn Write,
+    events: OutputEvents,
+    start: Duration,
+    speed: f64,
+    max_delay: Option<Duration>,
+    timeline: &Timeline,
+) -> Result<()> {
+    let row = StatusRow::new(Stream::Stderr);
+    let mut position = start;
+    row.draw(&format!(" ▶ {}", timeline.status(position, speed)));
+
+    for event in events {
+        let event = event?;
+        let wait = playback::wait(event.delay, speed, max_delay);
+        let started = Instant::now();
+        // Sleep in steps so the line keeps up during long pauses
+        while started.elapsed() < wait {
+            if tty::interrupted() {
+                return Ok(());
+            }
+            thread::sleep((wait - started.elapsed()).min(STATUS_INTERVAL));
+            let played = started.elapsed().min(wait).as_secs_f64() / wait.as_secs_f64();
+            position = (event.time - event.delay) + event.delay.mul_f64(played);
+            row.draw(&format!(" ▶ {}", timeline.status(position, speed)));
+        }
+        position = event.time;
+
+        out.write_all(&event.data)
+            .and_then(|_| out.flush())
+            .map_err(|e| anyhow!("Failed to write output: {}", e))?;
+    }
+
+    row.draw(&format!(" ■ {}", timeline.status(position, speed)));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::{Event, Metadata};
+
+    fn timeline() -> Timeline {
+        let events = vec![
+            Event::output(Duration::from_secs(1), "one"),
+            Event {
+                time: Duration::from_secs(30),
+                kind: EventKind::Marker("deploy".to_string()),
+            },
+            Event::output(Duration::from_secs(120), "two"),
+        ];
+        Timeline::scan(EventStream::new(
+            Metadata::default(),
+            events.into_iter().map(Ok),
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_timeline_scan() {
+        let timeline = timeline();
+        assert_eq!(timeline.duration, Duration::from_secs(120));
+        assert_eq!(
+            timeline.markers,
+            vec![(Duration::from_secs(30), "deploy".to_string())]
+        );
+        assert_eq!(timeline.fraction(Duration::from_secs(60)), 0.5);
+        assert_eq!(timeline.fraction(Duration::from_secs(600)), 1.0);
+        assert!(timeline.next_marker(Duration::from_secs(30)).is_none());
+        assert!(timeline.next_match(Duration::ZERO).is_none());
+    }
+
+    #[test]
+    fn test_status() {
+        let timeline = timeline();
+        assert_eq!(
+            timeline.status(Duration::from_secs(12), 2.0),
+            "00:12 / 02:00 ██░░░░░░░░░░░░░░░░░░  10%  2x  next: deploy at 00:30"
+        );
+        assert!(timeline
+            .status(Duration::from_secs(120), 1.0)
+            .starts_with("02:00 / 02:00 ████████████████████ 100%  1x"));
+        assert_eq!(clock(Duration::from_secs(3723)), "1:02:03");
+        assert_eq!(Timeline::default().fraction(Duration::ZERO), 1.0);
+    }
+}
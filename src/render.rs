@@ -0,0 +1,268 @@
+//! Screen states over time, shared by the rendered exports
+//!
+//! Exporters don't draw every output event: events closer together than the
+//! frame interval are merged, long pauses are shortened to the idle time
+//! limit, and a time range can be cut out of the recording.
+
+use crate::session::{EventKind, Session};
+use crate::terminal::{Line, Style, Terminal};
+use anyhow::Result;
+use std::time::Duration;
+
+/// How long the last screen stays up before an animation loops
+pub(crate) const FINAL_HOLD: Duration = Duration::from_secs(2);
+
+/// How a session is turned into a sequence of screen states
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Timeline {
+    /// Highest number of screen states per second, if capped
+    pub fps: Option<f64>,
+    /// Longest pause kept between events, in seconds
+    pub idle_time_limit: Option<f64>,
+    /// Playback speed multiplier
+    pub speed: f64,
+    /// Recording time of the first state; earlier output is fast-forwarded
+    pub start: Option<Duration>,
+    /// Recording time after which the rest of the session is dropped
+    pub end: Option<Duration>,
+}
+
+/// Feed a session through a terminal and report each distinct screen state
+///
+/// `frame` is called with the time the state appears, measured from the
+/// start of the exported range after idle compression and speed are applied.
+/// Returns the time the last event happened on the same scale.
+pub(crate) fn screen_states(
+    session: &Session,
+    terminal: &mut Terminal,
+    timeline: &Timeline,
+    mut frame: impl FnMut(Duration, &Terminal) -> Result<()>,
+) -> Result<Duration> {
+    let interval = timeline
+        .fps
+        .filter(|fps| *fps > 0.0)
+        .map(|fps| Duration::from_secs_f64(1.0 / fps))
+        .unwrap_or_default();
+    let idle_limit = timeline
+        .idle_time_limit
+        .filter(|limit| *limit > 0.0)
+        .map(Duration::from_secs_f64);
+    let speed = if timeline.speed > 0.0 {
+        timeline.speed
+    } else {
+        1.0
+    };
+    let start = timeline.start.unwrap_or_default();
+
+    let mut elapsed = Duration::ZERO;
+    let mut last_event = start;
+    // Time of the first change that hasn't been reported yet
+    let mut pending: Option<Duration> = None;
+    let mut started = false;
+
+    for event in &session.events {
+        if timeline.end.is_some_and(|end| event.time > end) {
+            break;
+        }
+
+        if event.time >= start {
+            if !started {
+                // Whatever the fast-forwarded screen holds is the first state,
+                // unless it changes again within the first frame interval
+                started = true;
+                pending = Some(Duration::ZERO);
+            }
+            let mut gap = event.time.saturating_sub(last_event);
+            if let Some(limit) = idle_limit {
+                gap = gap.min(limit);
+            }
+            elapsed += gap.div_f64(speed);
+            last_event = event.time;
+
+            if let Some(time) = pending {
+                if elapsed > time && elapsed >= time + interval {
+                    frame(time, terminal)?;
+                    pending = None;
+                }
+            }
+        }
+
+        let changed = match &event.kind {
+            EventKind::Output(data) => {
+                terminal.feed(data);
+                true
+            }
+            EventKind::Resize { cols, rows } => {
+                terminal.resize(*cols, *rows);
+                true
+            }
+            _ => false,
+        };
+        if changed && event.time >= start && pending.is_none() {
+            pending = Some(elapsed);
+        }
+    }
+
+    if let Some(time) = pending {
+        frame(time, terminal)?;
+    } else if !started {
+        frame(Duration::ZERO, terminal)?;
+    }
+    Ok(elapsed)
+}
+
+/// Consecutive cells of a line that share a style
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Run<'a> {
+    /// Column of the first cell
+    pub col: usize,
+    /// Columns covered by the run
+    pub width: usize,
+    pub text: String,
+    pub style: &'a Style,
+}
+
+/// Split a line into styled runs, without trailing blank cells
+///
+/// Wide characters end a run, so renderers that position text by column
+/// stay aligned even when a font draws them narrower than two cells.
+pub(crate) fn line_runs(line: &Line) -> Vec<Run<'_>> {
+    let blank = Style::default();
+    let end = line
+        .cells
+        .iter()
+        .rposition(|cell| cell.ch != ' ' || cell.style != blank)
+        .map_or(0, |last| last + 1);
+
+    let mut runs: Vec<Run> = Vec::new();
+    let mut after_wide = false;
+    for (col, cell) in line.cells[..end].iter().enumerate() {
+        if cell.width == 0 {
+            continue;
+        }
+        match runs.last_mut() {
+            Some(run) if !after_wide && run.style == &cell.style => {
+                run.text.push(cell.ch);
+                run.width += cell.width as usize;
+            }
+            _ => runs.push(Run {
+                col,
+                width: cell.width as usize,
+                text: cell.ch.to_string(),
+                style: &cell.style,
+            }),
+        }
+        after_wide = cell.width > 1;
+    }
+    runs
+}
+
+/// Escape text for XML and HTML, replacing characters they can't contain
+pub(crate) fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\t' | '\n' | '\r' => escaped.push(ch),
+            '\u{0}'..='\u{1f}' | '\u{fffe}' | '\u{ffff}' => escaped.push('\u{fffd}'),
+            _ => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::Event;
+
+    fn states(session: &Session, timeline: &Timeline) -> Vec<(Duration, String)> {
+        let mut terminal = Terminal::new(20, 2);
+        let mut states = Vec::new();
+        screen_states(session, &mut terminal, timeline, |time, terminal| {
+            states.push((time, terminal.screen_text()));
+            Ok(())
+        })
+        .unwrap();
+        states
+    }
+
+    #[test]
+    fn test_screen_states() {
+        let mut session = Session::new();
+        session.events = vec![
+            Event::output(Duration::from_millis(100), "a"),
+            Event::output(Duration::from_millis(110), "b"),
+            Event::output(Duration::from_secs(10), "c"),
+        ];
+        let timeline = Timeline {
+            fps: Some(10.0),
+            idle_time_limit: Some(1.0),
+            speed: 1.0,
+            start: None,
+            end: None,
+        };
+
+        assert_eq!(
+            states(&session, &timeline),
+            vec![
+                (Duration::ZERO, String::new()),
+                (Duration::from_millis(100), "ab".to_string()),
+                (Duration::from_millis(1110), "abc".to_string()),
+            ]
+        );
+
+        // Trimming fast-forwards to the start and stops at the end
+        let trimmed = Timeline {
+            start: Some(Duration::from_millis(105)),
+            end: Some(Duration::from_secs(1)),
+            ..timeline
+        };
+        assert_eq!(
+            states(&session, &trimmed),
+            vec![(Duration::ZERO, "ab".to_string())]
+        );
+        let trimmed = Timeline {
+            fps: None,
+            ..trimmed
+        };
+        assert_eq!(
+            states(&session, &trimmed),
+            vec![
+                (Duration::ZERO, "a".to_string()),
+                (Duration::from_millis(5), "ab".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_line_runs() {
+        let mut terminal = Terminal::new(20, 1);
+        terminal.feed("ab\x1b[1mcd\x1b[0m 日本e  ".as_bytes());
+        let runs: Vec<_> = line_runs(&terminal.lines()[0])
+            .into_iter()
+            .map(|run| (run.col, run.width, run.text, run.style.bold))
+            .collect();
+        assert_eq!(
+            runs,
+            vec![
+                (0, 2, "ab".to_string(), false),
+                (2, 2, "cd".to_string(), true),
+                (4, 3, " 日".to_string(), false),
+                (7, 2, "本".to_string(), false),
+                (9, 1, "e".to_string(), false),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(
+            escape("a<b> & \"c\"\u{1}"),
+            "a&lt;b&gt; &amp; &quot;c&quot;\u{fffd}"
+        );
+    }
+}
@@ -0,0 +1,267 @@
+//! Putting the terminal back after playback
+//!
+//! A replayed full-screen program may not get to switch off what it switched
+//! on: playback can end early, fail or be interrupted, and recordings are
+//! often cut before the program exits. [`ModeTracker`] follows the modes the
+//! replayed output sets, and [`Restoring`] writes the sequences that undo the
+//! ones still in effect when it is dropped, whichever way playback ended.
+
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+/// Longest control sequence followed before it is treated as malformed
+const MAX_SEQUENCE_LEN: usize = 64;
+
+/// DEC private modes that are reset, with their default state
+///
+/// Screen switches come first so the rest applies to the primary screen.
+const MODES: &[(u16, bool)] = &[
+    (1049, false), // Alternate screen, saving the cursor
+    (1047, false), // Alternate screen
+    (47, false),   // Alternate screen, old form
+    (1, false),    // Application cursor keys
+    (6, false),    // Origin mode
+    (7, true),     // Auto-wrap
+    (9, false),    // X10 mouse reporting
+    (25, true),    // Visible cursor
+    (1000, false), // Mouse button reporting
+    (1002, false), // Mouse drag reporting
+    (1003, false), // Mouse motion reporting
+    (1004, false), // Focus reporting
+    (1005, false), // UTF-8 mouse coordinates
+    (1006, false), // SGR mouse coordinates
+    (1015, false), // urxvt mouse coordinates
+    (2004, false), // Bracketed paste
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum State {
+    #[default]
+    Ground,
+    /// Seen ESC
+    Escape,
+    /// Inside a CSI sequence, collecting its parameters
+    Csi,
+    /// Inside an OSC, DCS, SOS, PM or APC string, up to BEL or ST
+    String,
+    /// Seen ESC inside a string
+    StringEscape,
+}
+
+/// Follows the terminal modes a stream of output leaves in effect
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ModeTracker {
+    state: State,
+    sequence: Vec<u8>,
+    /// Last state set for each mode in [`MODES`]
+    modes: BTreeMap<u16, bool>,
+    /// Colors or attributes other than the default
+    sgr: bool,
+    scroll_region: bool,
+    application_keypad: bool,
+    cursor_style: bool,
+}
+
+impl ModeTracker {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Follow the next chunk of output
+    pub(crate) fn feed(&mut self, data: &[u8]) {
+        for &byte in data {
+            self.state = match (self.state, byte) {
+                (State::String, 0x07) => State::Ground,
+                (State::String, 0x1b) => State::StringEscape,
+                (State::String, _) => State::String,
+                (State::StringEscape, b'\\') => State::Ground,
+                (State::StringEscape, _) => State::String,
+                (_, 0x18 | 0x1a) => State::Ground,
+                (_, 0x1b) => State::Escape,
+                (State::Escape, b'[') => {
+                    self.sequence.clear();
+                    State::Csi
+                }
+                (State::Escape, b']' | b'P' | b'X' | b'^' | b'_') => State::String,
+                (State::Escape, b'=') => {
+                    self.application_keypad = true;
+                    State::Ground
+                }
+                (State::Escape, b'>') => {
+                    self.application_keypad = false;
+                    State::Ground
+                }
+                (State::Escape, b'c') => {
+                    // A full reset puts everything back already
+                    *self = Self::default();
+                    State::Ground
+                }
+                (State::Escape, _) => State::Ground,
+                (State::Csi, 0x40..=0x7e) => {
+                    self.csi(byte);
+                    State::Ground
+                }
+                (State::Csi, 0x20..=0x3f) if self.sequence.len() < MAX_SEQUENCE_LEN => {
+                    self.sequence.push(byte);
+                    State::Csi
+                }
+                (State::Csi, _) if byte < 0x20 => State::Csi,
+                (State::Csi, _) => State::Ground,
+                (State::Ground, _) => State::Ground,
+            };
+        }
+    }
+
+    /// Apply a complete CSI sequence ending in `last`
+    fn csi(&mut self, last: u8) {
+        let sequence = String::from_utf8_lossy(&self.sequence).into_owned();
+        let params = || {
+            sequence
+                .split(';')
+                .filter_map(|param| param.parse::<u16>().ok())
+        };
+        match (sequence.strip_prefix('?'), last) {
+            (Some(private), b'h' | b'l') => {
+                for mode in private.split(';').filter_map(|param| param.parse().ok()) {
+                    if MODES.iter().any(|&(known, _)| known == mode) {
+                        self.modes.insert(mode, last == b'h');
+                    }
+                }
+            }
+            (None, b'm') => self.sgr = params().any(|param| param != 0),
+            (None, b'r') => self.scroll_region = !sequence.is_empty(),
+            (None, b'q') if sequence.ends_with(' ') => {
+                self.cursor_style = sequence
+                    .trim_end()
+                    .parse::<u16>()
+                    .is_ok_and(|style| style != 0);
+            }
+            _ => {}
+        }
+    }
+
+    /// Sequences that put back every mode the output left changed
+    pub(crate) fn reset_sequence(&self) -> Vec<u8> {
+        let mut reset = String::new();
+        for &(mode, default) in MODES {
+            if self.modes.get(&mode).is_some_and(|&on| on != default) {
+                reset.push_str(&format!(
+                    "\x1b[?{}{}",
+                    mode,
+                    if default { 'h' } else { 'l' }
+                ));
+            }
+        }
+        if self.scroll_region {
+            // Resetting the margins homes the cursor, so keep it where it is
+            reset.push_str("\x1b7\x1b[r\x1b8");
+        }
+        if self.sgr {
+            reset.push_str("\x1b[0m");
+        }
+        if self.application_keypad {
+            reset.push_str("\x1b>");
+        }
+        if self.cursor_style {
+            reset.push_str("\x1b[0 q");
+        }
+        reset.into_bytes()
+    }
+}
+
+/// A writer that follows the modes written through it and resets them when
+/// dropped
+pub(crate) struct Restoring<W: Write> {
+    out: W,
+    /// Not set when restoring is turned off
+    tracker: Option<ModeTracker>,
+}
+
+impl<W: Write> Restoring<W> {
+    pub(crate) fn new(out: W, restore: bool) -> Self {
+        Self {
+            out,
+            tracker: restore.then(ModeTracker::new),
+        }
+    }
+}
+
+impl<W: Write> Write for Restoring<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.out.write(buf)?;
+        if let Some(tracker) = &mut self.tracker {
+            tracker.feed(&buf[..written]);
+        }
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+impl<W: Write> Drop for Restoring<W> {
+    fn drop(&mut self) {
+        let Some(tracker) = &self.tracker else {
+            return;
+        };
+        let reset = tracker.reset_sequence();
+        if !reset.is_empty() {
+            self.out.write_all(&reset).unwrap_or(());
+            self.out.flush().unwrap_or(());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reset_after(chunks: &[&[u8]]) -> String {
+        let mut tracker = ModeTracker::new();
+        for chunk in chunks {
+            tracker.feed(chunk);
+        }
+        String::from_utf8(tracker.reset_sequence()).unwrap()
+    }
+
+    #[test]
+    fn test_modes_left_on_are_reset() {
+        assert_eq!(
+            reset_after(&[b"\x1b[?1049h\x1b[?25l\x1b[?1000;1006h\x1b[1;31mtext"]),
+            "\x1b[?1049l\x1b[?25h\x1b[?1000l\x1b[?1006l\x1b[0m"
+        );
+        // Split across chunks
+        assert_eq!(
+            reset_after(&[b"\x1b[?10", b"49h\x1b", b"=\x1b[2;", b"20r\x1b[4 q"]),
+            "\x1b[?1049l\x1b7\x1b[r\x1b8\x1b>\x1b[0 q"
+        );
+    }
+
+    #[test]
+    fn test_modes_switched_back_are_left_alone() {
+        assert_eq!(
+            reset_after(&[b"\x1b[?1049h\x1b[?25l\x1b[31mvim\x1b[0m\x1b[?25h\x1b[?1049l"]),
+            ""
+        );
+        assert_eq!(reset_after(&[b"\x1b[?1049h\x1b[32m", b"\x1bc"]), "");
+        // Sequences inside strings and unknown modes are ignored
+        assert_eq!(reset_after(&[b"\x1b]0;\x1b[?25l\x07\x1b[?12h"]), "");
+    }
+
+    #[test]
+    fn test_restoring_writer() {
+        let mut output = Vec::new();
+        {
+            let mut out = Restoring::new(&mut output, true);
+            out.write_all(b"\x1b[?25lhidden").unwrap();
+        }
+        assert_eq!(output, b"\x1b[?25lhidden\x1b[?25h");
+
+        let mut output = Vec::new();
+        Restoring::new(&mut output, false)
+            .write_all(b"\x1b[?25l")
+            .unwrap();
+        assert_eq!(output, b"\x1b[?25l");
+    }
+}
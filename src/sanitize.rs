@@ -0,0 +1,361 @@
+//! Streaming ANSI sanitizer
+//!
+//! [`StreamSanitizer`] applies the same cleanup as [`crate::clean_for_display`]
+//! to a byte stream that arrives in chunks. Parser state is kept between calls
+//! to [`StreamSanitizer::feed`], so an escape sequence split across a read
+//! boundary is recognized just like one that arrives in a single chunk.
+//! [`AnsiStripper`] works the same way but removes every escape sequence.
+
+/// Longest CSI sequence buffered before it is treated as malformed and flushed
+const MAX_SEQUENCE_LEN: usize = 64;
+
+/// Bare bracketed paste toggles left behind when the ESC byte was lost
+const BARE_PASTE_PREFIX: &[u8] = b"?2004";
+
+/// How much of the recorded output is cleaned up before it is written
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum Sanitize {
+    /// The recorded bytes, unchanged
+    #[default]
+    Raw,
+    /// Remove bracketed paste toggles and stray control characters but keep
+    /// colors and cursor movement, like the streaming sanitizer
+    StripDangerous,
+    /// Remove every escape sequence and control character except tab,
+    /// newline and carriage return
+    StripAnsi,
+    /// Run the output through the terminal emulator and write the text
+    /// transcript, like the text export
+    Text,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum State {
+    /// Plain text
+    #[default]
+    Ground,
+    /// Seen ESC, waiting for the next byte
+    Escape,
+    /// Inside `ESC [` collecting parameter and intermediate bytes
+    Csi,
+    /// Matching a bare `?2004h` / `?2004l`
+    BarePaste,
+    /// Seen 0xC2, the lead byte of a UTF-8 encoded C1 control
+    C1Lead,
+}
+
+/// A stateful, byte-oriented sanitizer for terminal output
+///
+/// Removes bracketed paste mode toggles (`ESC[?2004h`, `ESC[?2004l` and their
+/// bare `?2004h` / `?2004l` remnants) and control characters other than tab,
+/// newline, carriage return and ESC. Colors, cursor movement and every other
+/// escape sequence are passed through unchanged.
+///
+/// # Example
+///
+/// ```rust
+/// use replay_rs::StreamSanitizer;
+///
+/// let mut sanitizer = StreamSanitizer::new();
+/// let mut output = sanitizer.feed(b"\x1b[?20");
+/// output.extend(sanitizer.feed(b"04hHello\x1b[32m!\x1b[0m"));
+/// output.extend(sanitizer.finish());
+/// assert_eq!(output, b"Hello\x1b[32m!\x1b[0m");
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct StreamSanitizer {
+    state: State,
+    pending: Vec<u8>,
+}
+
+impl StreamSanitizer {
+    /// Create a sanitizer in the ground state
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sanitize the next chunk of output
+    ///
+    /// Bytes that may belong to a sequence continued in the next chunk are held
+    /// back and emitted (or dropped) once the sequence is complete.
+    pub fn feed(&mut self, chunk: &[u8]) -> Vec<u8> {
+        let mut output = Vec::with_capacity(chunk.len());
+        for &byte in chunk {
+            self.push(byte, &mut output);
+        }
+        output
+    }
+
+    /// Flush any partially matched sequence at end of input
+    pub fn finish(&mut self) -> Vec<u8> {
+        self.state = State::Ground;
+        std::mem::take(&mut self.pending)
+    }
+
+    /// Returns true if bytes are held back waiting for more input
+    pub fn has_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    fn push(&mut self, byte: u8, output: &mut Vec<u8>) {
+        match self.state {
+            State::Ground => self.ground(byte, output),
+            State::Escape => {
+                if byte == b'[' {
+                    self.pending.push(byte);
+                    self.state = State::Csi;
+                } else {
+                    // Keep other escape sequences, the ESC itself is harmless
+                    self.flush_pending(output);
+                    self.ground(byte, output);
+                }
+            }
+            State::Csi => match byte {
+                // Parameter and intermediate bytes
+                0x20..=0x3f => {
+                    self.pending.push(byte);
+                    if self.pending.len() > MAX_SEQUENCE_LEN {
+                        self.flush_pending(output);
+                    }
+                }
+                // Final byte completes the sequence
+                0x40..=0x7e => {
+                    self.pending.push(byte);
+                    if contains(&self.pending, b"2004") {
+                        self.pending.clear();
+                        self.state = State::Ground;
+                    } else {
+                        self.flush_pending(output);
+                    }
+                }
+                0x1b => {
+                    // A new sequence interrupts an unterminated one
+                    self.flush_pending(output);
+                    self.ground(byte, output);
+                }
+                // Control characters inside a sequence are dropped
+                _ if is_stripped_control(byte) => {}
+                _ => {
+                    self.flush_pending(output);
+                    self.ground(byte, output);
+                }
+            },
+            State::BarePaste => {
+                let matched = self.pending.len();
+                if matched < BARE_PASTE_PREFIX.len() && byte == BARE_PASTE_PREFIX[matched] {
+                    self.pending.push(byte);
+                } else if matched == BARE_PASTE_PREFIX.len() && (byte == b'h' || byte == b'l') {
+                    self.pending.clear();
+                    self.state = State::Ground;
+                } else {
+                    self.flush_pending(output);
+                    self.ground(byte, output);
+                }
+            }
+            State::C1Lead => {
+                if (0x80..=0x9f).contains(&byte) {
+                    // U+0080..U+009F are C1 control characters
+                    self.pending.clear();
+                    self.state = State::Ground;
+                } else {
+                    self.flush_pending(output);
+                    self.ground(byte, output);
+                }
+            }
+        }
+    }
+
+    fn ground(&mut self, byte: u8, output: &mut Vec<u8>) {
+        match byte {
+            0x1b => self.start(State::Escape, byte),
+            b'?' => self.start(State::BarePaste, byte),
+            0xc2 => self.start(State::C1Lead, byte),
+            _ if is_stripped_control(byte) => {}
+            _ => output.push(byte),
+        }
+    }
+
+    fn start(&mut self, state: State, byte: u8) {
+        self.state = state;
+        self.pending.push(byte);
+    }
+
+    fn flush_pending(&mut self, output: &mut Vec<u8>) {
+        output.append(&mut self.pending);
+        self.state = State::Ground;
+    }
+}
+
+/// Where [`AnsiStripper`] is in the stream
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum StripState {
+    #[default]
+    Ground,
+    /// Seen ESC
+    Escape,
+    /// Inside an escape sequence with intermediate bytes, up to its final byte
+    EscapeIntermediate,
+    /// Inside a CSI sequence, up to its final byte
+    Csi,
+    /// Inside an OSC, DCS, SOS, PM or APC string, up to BEL or ST
+    String,
+    /// Seen ESC inside a string, which starts ST
+    StringEscape,
+}
+
+/// A streaming filter that removes every escape sequence
+///
+/// Control sequences, operating system commands and device control strings
+/// are dropped along with control characters other than tab, newline and
+/// carriage return, leaving only the text. State is kept between chunks.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct AnsiStripper {
+    state: StripState,
+}
+
+impl AnsiStripper {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Strip the next chunk of output
+    pub(crate) fn feed(&mut self, chunk: &[u8]) -> Vec<u8> {
+        let mut output = Vec::with_capacity(chunk.len());
+        for &byte in chunk {
+            self.state = match (self.state, byte) {
+                (_, 0x18 | 0x1a) => StripState::Ground,
+                (StripState::String, 0x07) => StripState::Ground,
+                (StripState::String, 0x1b) => StripState::StringEscape,
+                (StripState::String, _) => StripState::String,
+                (StripState::StringEscape, b'\\') => StripState::Ground,
+                (StripState::StringEscape, _) => StripState::String,
+                (_, 0x1b) => StripState::Escape,
+                (StripState::Escape, b'[') => StripState::Csi,
+                (StripState::Escape, b']' | b'P' | b'X' | b'^' | b'_') => StripState::String,
+                (StripState::Escape | StripState::EscapeIntermediate, 0x20..=0x2f) => {
+                    StripState::EscapeIntermediate
+                }
+                (StripState::Csi, 0x40..=0x7e) => StripState::Ground,
+                (StripState::Escape | StripState::EscapeIntermediate, 0x30..=0x7e) => {
+                    StripState::Ground
+                }
+                // Other control characters are executed inside a sequence
+                (state, _) if byte < 0x20 || byte == 0x7f => {
+                    if state == StripState::Ground && !is_stripped_control(byte) {
+                        output.push(byte);
+                    }
+                    state
+                }
+                (StripState::Ground, _) => {
+                    output.push(byte);
+                    StripState::Ground
+                }
+                (StripState::Csi, _) => StripState::Csi,
+                // A malformed sequence ends at the first byte that cannot be part of it
+                (_, _) => {
+                    output.push(byte);
+                    StripState::Ground
+                }
+            };
+        }
+        output
+    }
+}
+
+/// C0 controls and DEL, except tab, newline, carriage return and ESC
+fn is_stripped_control(byte: u8) -> bool {
+    (byte < 0x20 || byte == 0x7f) && !matches!(byte, b'\t' | b'\n' | b'\r' | 0x1b)
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack
+        .windows(needle.len())
+        .any(|window| window == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sanitize_in_chunks(input: &[u8], chunk_size: usize) -> Vec<u8> {
+        let mut sanitizer = StreamSanitizer::new();
+        let mut output = Vec::new();
+        for chunk in input.chunks(chunk_size) {
+            output.extend(sanitizer.feed(chunk));
+        }
+        output.extend(sanitizer.finish());
+        output
+    }
+
+    #[test]
+    fn test_bracketed_paste_split_across_chunks() {
+        let input = b"\x1b[?2004hprompt$ ls\x1b[?2004l\r\nfile.txt\r\n";
+        for chunk_size in 1..input.len() {
+            assert_eq!(
+                sanitize_in_chunks(input, chunk_size),
+                b"prompt$ ls\r\nfile.txt\r\n",
+                "chunk size {}",
+                chunk_size
+            );
+        }
+    }
+
+    #[test]
+    fn test_colors_and_cursor_movement_preserved() {
+        let input = b"\x1b[1;31mRed\x1b[0m\x1b[2K\x1b[10;5H\x1b]0;title\x07";
+        assert_eq!(
+            sanitize_in_chunks(input, 3),
+            b"\x1b[1;31mRed\x1b[0m\x1b[2K\x1b[10;5H\x1b]0;title"
+        );
+    }
+
+    #[test]
+    fn test_bare_paste_remnants_removed() {
+        assert_eq!(sanitize_in_chunks(b"?2004hA?2004lB", 1), b"AB");
+        // A question mark that is not a paste toggle is kept
+        assert_eq!(sanitize_in_chunks(b"Why?2004x?", 1), b"Why?2004x?");
+    }
+
+    #[test]
+    fn test_control_characters_stripped() {
+        let input = "a\x07b\x08c\x7fd\u{85}e\tf\r\n中".as_bytes();
+        assert_eq!(sanitize_in_chunks(input, 1), "abcde\tf\r\n中".as_bytes());
+    }
+
+    #[test]
+    fn test_finish_flushes_incomplete_sequence() {
+        let mut sanitizer = StreamSanitizer::new();
+        assert_eq!(sanitizer.feed(b"abc\x1b[3"), b"abc");
+        assert!(sanitizer.has_pending());
+        assert_eq!(sanitizer.finish(), b"\x1b[3");
+        assert!(!sanitizer.has_pending());
+    }
+
+    #[test]
+    fn test_ansi_stripper() {
+        let input = "\x1b[1;31mRed\x1b[0m \x1b]0;title\x07\x1b]8;;http://x\x1b\\link\x1b]8;;\x1b\\ \x1b(B\x1b7ok\x1b8\x07\r\n中".as_bytes();
+        for chunk_size in 1..input.len() {
+            let mut stripper = AnsiStripper::new();
+            let output: Vec<u8> = input
+                .chunks(chunk_size)
+                .flat_map(|chunk| stripper.feed(chunk))
+                .collect();
+            assert_eq!(
+                output,
+                "Red link ok\r\n中".as_bytes(),
+                "chunk size {}",
+                chunk_size
+            );
+        }
+    }
+
+    #[test]
+    fn test_matches_clean_for_display() {
+        let input = "\x1b[1;32mBold\x1b[0m\x1b[?2004h\x1b[K?2004l 中文 🎬\x01";
+        assert_eq!(
+            sanitize_in_chunks(input.as_bytes(), 2),
+            crate::clean_for_display(input).as_bytes()
+        );
+    }
+}
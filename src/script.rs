@@ -0,0 +1,788 @@
+//! util-linux `script` compatibility
+//!
+//! util-linux `script` starts every log file with a `Script started on ...`
+//! line that the timing data does not account for, and `scriptreplay` always
+//! skips the first line of a log. This module reads and writes that header.
+//!
+//! It also implements the advanced (multi-stream) timing format written by
+//! `script --log-timing` when more than one stream is logged. Each line starts
+//! with an entry type followed by the delay since the previous entry:
+//!
+//! ```text
+//! H 0.000000 START_TIME 2024-01-01 12:00:00+00:00
+//! H 0.000000 COMMAND vim
+//! O 0.010176 14
+//! I 0.520000 1
+//! S 0.100000 SIGWINCH ROWS=40 COLS=120
+//! H 0.000000 EXIT_CODE 0
+//! ```
+//!
+//! `O` and `I` entries are followed by a byte count to read from the output or
+//! input log, `S` entries record signals and `H` entries carry header info.
+
+use crate::gzip;
+use crate::session::{
+    create_file, seconds, Event, EventKind, EventStream, EventWriter, Metadata, ScriptreplayFrames,
+    ScriptreplayWriter, Session, SessionFiles, SessionFormat,
+};
+use anyhow::{anyhow, Result};
+use std::io::{BufRead, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Prefix of the header line util-linux `script` writes to every log
+const HEADER_PREFIX: &[u8] = b"Script started";
+
+/// The scriptreplay typescript and timing file pair as a [`SessionFormat`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ScriptreplayFormat;
+
+impl SessionFormat for ScriptreplayFormat {
+    fn name(&self) -> &'static str {
+        "scriptreplay"
+    }
+
+    fn read(&self, files: &SessionFiles) -> Result<Session> {
+        Session::read_scriptreplay(&files.timing_file(), &files.data)
+    }
+
+    fn write(&self, session: &Session, files: &SessionFiles) -> Result<()> {
+        session.write_scriptreplay(&files.timing_file(), &files.data)
+    }
+
+    fn events(&self, files: &SessionFiles) -> Result<EventStream> {
+        let frames = ScriptreplayFrames::open(&files.timing_file(), &files.data)?;
+        let mut time = Duration::ZERO;
+        Ok(EventStream::new(
+            Metadata::default(),
+            frames.map(move |frame| {
+                let (delay, data) = frame?;
+                time += delay;
+                Ok(Event::output(time, data))
+            }),
+        ))
+    }
+
+    fn writer(&self, _metadata: &Metadata, files: &SessionFiles) -> Result<Box<dyn EventWriter>> {
+        Ok(Box::new(ScriptreplayWriter::new(
+            create_file(&files.timing_file(), "timing")?,
+            create_file(&files.data, "output")?,
+        )))
+    }
+}
+
+/// The util-linux advanced timing format as a [`SessionFormat`]
+///
+/// Input is read from and written to [`SessionFiles::input`] when it is set.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AdvancedFormat;
+
+impl SessionFormat for AdvancedFormat {
+    fn name(&self) -> &'static str {
+        "advanced"
+    }
+
+    fn read(&self, files: &SessionFiles) -> Result<Session> {
+        Session::read_advanced(&files.timing_file(), &files.data, files.input.as_deref())
+    }
+
+    fn write(&self, session: &Session, files: &SessionFiles) -> Result<()> {
+        session.write_advanced(&files.timing_file(), &files.data, files.input.as_deref())
+    }
+
+    fn events(&self, files: &SessionFiles) -> Result<EventStream> {
+        let mut events =
+            AdvancedEvents::open(&files.timing_file(), &files.data, files.input.as_deref())?;
+        // Header entries come first, so reading up to the first event fills in the metadata
+        let first = events.next();
+        let metadata = events.metadata().clone();
+        Ok(EventStream::new(metadata, first.into_iter().chain(events)))
+    }
+
+    fn writer(&self, metadata: &Metadata, files: &SessionFiles) -> Result<Box<dyn EventWriter>> {
+        let timing_file = files.timing_file();
+        let input = files
+            .input
+            .as_deref()
+            .map(|path| create_file(path, "input"))
+            .transpose()?;
+        Ok(Box::new(AdvancedWriter::new(
+            create_file(&timing_file, "timing")?,
+            create_file(&files.data, "output")?,
+            input,
+            metadata,
+            (&timing_file, &files.data, files.input.as_deref()),
+        )?))
+    }
+}
+
+/// Build the header line written at the top of a log file
+pub(crate) fn header_line(metadata: &Metadata) -> String {
+    let mut line = String::from("Script started");
+    if let Some(timestamp) = metadata.timestamp {
+        line.push_str(" on ");
+        line.push_str(&format_time(timestamp));
+    }
+    if let Some(command) = &metadata.command {
+        line.push_str(&format!(" [COMMAND=\"{}\"]", command));
+    }
+    line.push('\n');
+    line
+}
+
+/// Skip a `Script started ...` header line if the log starts with one
+///
+/// Returns whatever the header reveals about the recording.
+pub(crate) fn skip_header<R: BufRead>(reader: &mut R) -> Result<Metadata> {
+    let buffer = reader
+        .fill_buf()
+        .map_err(|e| anyhow!("Failed to read log file: {}", e))?;
+    if !buffer.starts_with(HEADER_PREFIX) {
+        return Ok(Metadata::default());
+    }
+
+    let mut line = Vec::new();
+    reader
+        .read_until(b'\n', &mut line)
+        .map_err(|e| anyhow!("Failed to read log file: {}", e))?;
+    Ok(parse_header_line(&String::from_utf8_lossy(&line)))
+}
+
+/// Parse `Script started on <time> [COMMAND="<command>" ...]`
+fn parse_header_line(line: &str) -> Metadata {
+    let mut metadata = Metadata::default();
+    let line = line.trim_end();
+
+    let rest = line.strip_prefix("Script started").unwrap_or(line);
+    if let Some(rest) = rest.strip_prefix(" on ") {
+        let time = rest.split(" [").next().unwrap_or(rest);
+        metadata.timestamp = parse_time(time);
+    }
+
+    if let Some(start) = line.find("[COMMAND=\"") {
+        let command = &line[start + "[COMMAND=\"".len()..];
+        if let Some(end) = command.rfind('"') {
+            metadata.command = Some(command[..end].to_string());
+        }
+    }
+
+    metadata
+}
+
+/// Check whether a timing file uses the advanced multi-stream format
+pub(crate) fn is_advanced_timing(path: impl AsRef<Path>) -> bool {
+    let Ok(reader) = gzip::open(path) else {
+        return false;
+    };
+    looks_like_advanced_timing(reader)
+}
+
+/// Check whether timing data starts with an advanced format entry
+pub(crate) fn looks_like_advanced_timing<R: BufRead>(reader: R) -> bool {
+    // Only the first entry matters; don't read a whole file of garbage
+    let mut first_line = String::new();
+    let mut reader = reader.take(4096);
+    while first_line.trim().is_empty() {
+        first_line.clear();
+        match reader.read_line(&mut first_line) {
+            Ok(0) | Err(_) => return false,
+            Ok(_) => {}
+        }
+    }
+
+    let mut parts = first_line.split_whitespace();
+    matches!(parts.next(), Some("O" | "I" | "S" | "H"))
+        && parts
+            .next()
+            .is_some_and(|delay| delay.parse::<f64>().is_ok())
+}
+
+/// Where `I` entries are read from
+enum InputLog {
+    /// No input log, `I` entries only advance time
+    None,
+    /// A separate `--log-in` file
+    Separate(Box<dyn BufRead>),
+    /// Input and output share one `--log-io` file
+    Shared,
+}
+
+/// Lazily reads events from an advanced timing file and its logs
+///
+/// `H` entries update [`AdvancedEvents::metadata`] instead of producing
+/// events, and the `EXIT_CODE` entry becomes an [`EventKind::Exit`] event.
+pub(crate) struct AdvancedEvents {
+    timing: std::io::Lines<Box<dyn BufRead>>,
+    output: Box<dyn BufRead>,
+    input: InputLog,
+    time: Duration,
+    metadata: Metadata,
+    output_file: String,
+    timing_dir: PathBuf,
+    done: bool,
+}
+
+impl AdvancedEvents {
+    /// Open a timing file with its output log and optional input log
+    ///
+    /// If `input_file` is `None` the `INPUT_LOG` header entry is used when it
+    /// names an existing file.
+    pub(crate) fn open(
+        timing_file: &str,
+        output_file: &str,
+        input_file: Option<&str>,
+    ) -> Result<Self> {
+        let timing = gzip::open(timing_file)
+            .map_err(|e| anyhow!("Failed to read timing file {}: {}", timing_file, e))?;
+        let mut output = gzip::open(output_file)
+            .map_err(|e| anyhow!("Failed to open output log {}: {}", output_file, e))?;
+        let metadata = skip_header(&mut output)?;
+
+        let mut events = Self {
+            timing: timing.lines(),
+            output,
+            input: InputLog::None,
+            time: Duration::ZERO,
+            metadata,
+            output_file: output_file.to_string(),
+            timing_dir: Path::new(timing_file)
+                .parent()
+                .map(Path::to_path_buf)
+                .unwrap_or_default(),
+            done: false,
+        };
+        if let Some(input_file) = input_file {
+            events.open_input(input_file)?;
+        }
+        Ok(events)
+    }
+
+    /// Header information collected so far
+    pub(crate) fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    fn open_input(&mut self, input_file: &str) -> Result<()> {
+        let same_file = match (
+            std::fs::canonicalize(&self.output_file),
+            std::fs::canonicalize(input_file),
+        ) {
+            (Ok(output), Ok(input)) => output == input,
+            _ => self.output_file == input_file,
+        };
+
+        self.input = if same_file {
+            InputLog::Shared
+        } else {
+            let mut input = gzip::open(input_file)
+                .map_err(|e| anyhow!("Failed to open input log {}: {}", input_file, e))?;
+            skip_header(&mut input)?;
+            InputLog::Separate(input)
+        };
+        Ok(())
+    }
+
+    fn read_event(&mut self) -> Result<Option<Event>> {
+        while let Some(line) = self.timing.next() {
+            let line = line.map_err(|e| anyhow!("Failed to read timing file: {}", e))?;
+            let line = line.trim_end();
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let mut parts = line.splitn(3, ' ');
+            let entry = parts.next().unwrap_or_default();
+            let delay = parts
+                .next()
+                .ok_or_else(|| anyhow!("Invalid timing entry '{}'", line))?;
+            let delay: f64 = delay
+                .parse()
+                .map_err(|e| anyhow!("Invalid delay value '{}': {}", delay, e))?;
+            let rest = parts.next().unwrap_or_default();
+
+            self.time += seconds(delay.max(0.0))?;
+
+            match entry {
+                "O" => {
+                    let Some(data) = read_block(&mut self.output, rest)? else {
+                        return Ok(None);
+                    };
+                    return Ok(Some(Event::output(self.time, data)));
+                }
+                "I" => {
+                    let reader = match &mut self.input {
+                        InputLog::None => continue,
+                        InputLog::Separate(reader) => reader,
+                        InputLog::Shared => &mut self.output,
+                    };
+                    let Some(data) = read_block(reader, rest)? else {
+                        return Ok(None);
+                    };
+                    return Ok(Some(Event {
+                        time: self.time,
+                        kind: EventKind::Input(data),
+                    }));
+                }
+                "S" => {
+                    if let Some(kind) = parse_signal(rest) {
+                        return Ok(Some(Event {
+                            time: self.time,
+                            kind,
+                        }));
+                    }
+                }
+                "H" => {
+                    if let Some(kind) = self.apply_header(rest)? {
+                        return Ok(Some(Event {
+                            time: self.time,
+                            kind,
+                        }));
+                    }
+                }
+                _ => return Err(anyhow!("Unknown timing entry type '{}'", entry)),
+            }
+        }
+        Ok(None)
+    }
+
+    /// Record an `H <name> <value>` entry, returning an event if it is one
+    fn apply_header(&mut self, entry: &str) -> Result<Option<EventKind>> {
+        let (name, value) = entry.split_once(' ').unwrap_or((entry, ""));
+        let metadata = &mut self.metadata;
+        match name {
+            "START_TIME" => metadata.timestamp = parse_time(value).or(metadata.timestamp),
+            "COMMAND" => metadata.command = Some(value.to_string()),
+            "COLUMNS" => metadata.width = value.parse().ok(),
+            "LINES" => metadata.height = value.parse().ok(),
+            "TERM" | "SHELL" | "TTY" => {
+                metadata.env.insert(name.to_string(), value.to_string());
+            }
+            "INPUT_LOG" => {
+                if matches!(self.input, InputLog::None) {
+                    if let Some(path) = self.resolve_log(value) {
+                        self.open_input(&path)?;
+                    }
+                }
+            }
+            "EXIT_CODE" => {
+                if let Ok(status) = value.trim().parse() {
+                    return Ok(Some(EventKind::Exit(status)));
+                }
+            }
+            _ => {}
+        }
+        Ok(None)
+    }
+}
+
+impl AdvancedEvents {
+    /// Find a log named in a header entry, as given or next to the timing file
+    fn resolve_log(&self, name: &str) -> Option<String> {
+        let candidates = [
+            PathBuf::from(name),
+            self.timing_dir.join(Path::new(name).file_name()?),
+        ];
+        candidates
+            .into_iter()
+            .find(|path| path.is_file())
+            .map(|path| path.to_string_lossy().into_owned())
+    }
+}
+
+impl Iterator for AdvancedEvents {
+    type Item = Result<Event>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let event = self.read_event().transpose();
+        if !matches!(event, Some(Ok(_))) {
+            self.done = true;
+        }
+        event
+    }
+}
+
+/// Read the block of bytes an `O` or `I` entry refers to
+///
+/// Returns `None` if the log ends early.
+fn read_block<R: Read>(reader: &mut R, size: &str) -> Result<Option<Vec<u8>>> {
+    let size: usize = size
+        .trim()
+        .parse()
+        .map_err(|e| anyhow!("Invalid size value '{}': {}", size, e))?;
+
+    // Read through `take` so a corrupt size can't force a huge allocation
+    let mut buffer = Vec::new();
+    reader
+        .take(size as u64)
+        .read_to_end(&mut buffer)
+        .map_err(|e| anyhow!("Error reading log file: {}", e))?;
+    Ok((buffer.len() == size).then_some(buffer))
+}
+
+/// Parse `SIGWINCH ROWS=<rows> COLS=<cols>`; other signals are ignored
+fn parse_signal(entry: &str) -> Option<EventKind> {
+    let mut parts = entry.split_whitespace();
+    if parts.next()? != "SIGWINCH" {
+        return None;
+    }
+
+    let (mut cols, mut rows) = (None, None);
+    for part in parts {
+        match part.split_once('=') {
+            Some(("ROWS", value)) => rows = value.parse().ok(),
+            Some(("COLS", value)) => cols = value.parse().ok(),
+            _ => {}
+        }
+    }
+    Some(EventKind::Resize {
+        cols: cols?,
+        rows: rows?,
+    })
+}
+
+/// Writes the advanced timing format and its log files
+pub(crate) struct AdvancedWriter<W: Write> {
+    timing: W,
+    output: W,
+    input: Option<W>,
+    last: Duration,
+}
+
+impl<W: Write> AdvancedWriter<W> {
+    /// Create a writer, emitting the `H` entries and log headers
+    ///
+    /// `names` are the timing, output and input log names recorded in the
+    /// `TIMING_LOG`, `OUTPUT_LOG` and `INPUT_LOG` header entries.
+    pub(crate) fn new(
+        mut timing: W,
+        mut output: W,
+        mut input: Option<W>,
+        metadata: &Metadata,
+        names: (&str, &str, Option<&str>),
+    ) -> Result<Self> {
+        let header = header_line(metadata);
+        output
+            .write_all(header.as_bytes())
+            .map_err(|e| anyhow!("Failed to write output: {}", e))?;
+        if let Some(input) = &mut input {
+            input
+                .write_all(header.as_bytes())
+                .map_err(|e| anyhow!("Failed to write input log: {}", e))?;
+        }
+
+        let mut entries = Vec::new();
+        if let Some(timestamp) = metadata.timestamp {
+            entries.push(("START_TIME", format_time(timestamp)));
+        }
+        for name in ["TERM", "TTY"] {
+            if let Some(value) = metadata.env.get(name) {
+                entries.push((name, value.clone()));
+            }
+        }
+        if let Some(width) = metadata.width {
+            entries.push(("COLUMNS", width.to_string()));
+        }
+        if let Some(height) = metadata.height {
+            entries.push(("LINES", height.to_string()));
+        }
+        if let Some(shell) = metadata.env.get("SHELL") {
+            entries.push(("SHELL", shell.clone()));
+        }
+        if let Some(command) = &metadata.command {
+            entries.push(("COMMAND", command.clone()));
+        }
+        entries.push(("TIMING_LOG", names.0.to_string()));
+        entries.push(("OUTPUT_LOG", names.1.to_string()));
+        if let Some(input_name) = names.2 {
+            entries.push(("INPUT_LOG", input_name.to_string()));
+        }
+
+        for (name, value) in entries {
+            writeln!(timing, "H {:.6} {} {}", 0.0, name, value)
+                .map_err(|e| anyhow!("Failed to write timing data: {}", e))?;
+        }
+
+        Ok(Self {
+            timing,
+            output,
+            input,
+            last: Duration::ZERO,
+        })
+    }
+
+    /// Append an event; markers have no representation and are skipped
+    pub(crate) fn write_event(&mut self, event: &Event) -> Result<()> {
+        let delay = event.time.saturating_sub(self.last);
+        match &event.kind {
+            EventKind::Output(data) => {
+                self.entry(&format!("O {:.6} {}", delay.as_secs_f64(), data.len()))?;
+                self.output
+                    .write_all(data)
+                    .map_err(|e| anyhow!("Failed to write output: {}", e))?;
+            }
+            EventKind::Input(data) => {
+                let Some(input) = &mut self.input else {
+                    return Ok(());
+                };
+                input
+                    .write_all(data)
+                    .map_err(|e| anyhow!("Failed to write input log: {}", e))?;
+                self.entry(&format!("I {:.6} {}", delay.as_secs_f64(), data.len()))?;
+            }
+            EventKind::Resize { cols, rows } => self.entry(&format!(
+                "S {:.6} SIGWINCH ROWS={} COLS={}",
+                delay.as_secs_f64(),
+                rows,
+                cols
+            ))?,
+            EventKind::Exit(status) => self.entry(&format!(
+                "H {:.6} EXIT_CODE {}",
+                delay.as_secs_f64(),
+                status
+            ))?,
+            // Skipped without advancing so the next entry keeps its full delay
+            EventKind::Marker(_) => return Ok(()),
+        }
+        self.last = self.last.max(event.time);
+        Ok(())
+    }
+
+    /// Write the `DURATION` entry and flush all files
+    pub(crate) fn finish(mut self) -> Result<()> {
+        writeln!(
+            self.timing,
+            "H {:.6} DURATION {:.6}",
+            0.0,
+            self.last.as_secs_f64()
+        )
+        .map_err(|e| anyhow!("Failed to write timing data: {}", e))?;
+
+        self.timing
+            .flush()
+            .map_err(|e| anyhow!("Failed to write timing data: {}", e))?;
+        self.output
+            .flush()
+            .map_err(|e| anyhow!("Failed to write output: {}", e))?;
+        if let Some(input) = &mut self.input {
+            input
+                .flush()
+                .map_err(|e| anyhow!("Failed to write input log: {}", e))?;
+        }
+        Ok(())
+    }
+
+    fn entry(&mut self, line: &str) -> Result<()> {
+        writeln!(self.timing, "{}", line).map_err(|e| anyhow!("Failed to write timing data: {}", e))
+    }
+}
+
+impl<W: Write> EventWriter for AdvancedWriter<W> {
+    fn write_event(&mut self, event: &Event) -> Result<()> {
+        AdvancedWriter::write_event(self, event)
+    }
+
+    fn finish(self: Box<Self>) -> Result<()> {
+        AdvancedWriter::finish(*self)
+    }
+}
+
+/// Format a Unix timestamp the way `script` does, e.g. `2024-01-01 12:00:00+00:00`
+pub(crate) fn format_time(timestamp: u64) -> String {
+    let days = (timestamp / 86_400) as i64;
+    let seconds_of_day = timestamp % 86_400;
+    let (year, month, day) = civil_from_days(days);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}+00:00",
+        year,
+        month,
+        day,
+        seconds_of_day / 3600,
+        seconds_of_day / 60 % 60,
+        seconds_of_day % 60
+    )
+}
+
+/// Parse `YYYY-MM-DD HH:MM:SS` with an optional `±HH:MM` or `±HHMM` offset
+pub(crate) fn parse_time(value: &str) -> Option<u64> {
+    let value = value.trim();
+    let (date, time) = value.split_once([' ', 'T'])?;
+
+    let mut date_parts = date.splitn(3, '-');
+    let year: i64 = date_parts.next()?.parse().ok()?;
+    let month: u32 = date_parts.next()?.parse().ok()?;
+    let day: u32 = date_parts.next()?.parse().ok()?;
+
+    let (clock, offset) = match time.find(['+', '-']) {
+        Some(index) => time.split_at(index),
+        None => (time.trim_end_matches('Z'), ""),
+    };
+    let mut clock_parts = clock.splitn(3, ':');
+    let hour: i64 = clock_parts.next()?.parse().ok()?;
+    let minute: i64 = clock_parts.next()?.parse().ok()?;
+    let second: i64 = clock_parts
+        .next()
+        .unwrap_or("0")
+        .split('.')
+        .next()?
+        .parse()
+        .ok()?;
+
+    let offset_seconds = if offset.is_empty() {
+        0
+    } else {
+        let sign = if offset.starts_with('-') { -1 } else { 1 };
+        let digits: String = offset[1..].chars().filter(char::is_ascii_digit).collect();
+        if digits.len() != 4 {
+            return None;
+        }
+        let hours: i64 = digits[..2].parse().ok()?;
+        let minutes: i64 = digits[2..].parse().ok()?;
+        sign * (hours * 3600 + minutes * 60)
+    };
+
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    let days = days_from_civil(year, month, day);
+    let timestamp = days * 86_400 + hour * 3600 + minute * 60 + second - offset_seconds;
+    u64::try_from(timestamp).ok()
+}
+
+/// Days since 1970-01-01 for a proleptic Gregorian date
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = month as i64;
+    let day_of_year =
+        (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Proleptic Gregorian date for a number of days since 1970-01-01
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_time_round_trip() {
+        assert_eq!(format_time(0), "1970-01-01 00:00:00+00:00");
+        assert_eq!(format_time(1_709_210_096), "2024-02-29 12:34:56+00:00");
+        assert_eq!(parse_time("2024-02-29 12:34:56+00:00"), Some(1_709_210_096));
+        assert_eq!(parse_time("2024-02-29 14:34:56+02:00"), Some(1_709_210_096));
+        assert_eq!(parse_time("2024-02-29 07:34:56-0500"), Some(1_709_210_096));
+        assert_eq!(parse_time("not a date"), None);
+    }
+
+    #[test]
+    fn test_skip_header() -> Result<()> {
+        let log = b"Script started on 2024-02-29 12:34:56+00:00 [COMMAND=\"ls -l\" <not executed on terminal>]\nhello";
+        let mut reader = &log[..];
+        let metadata = skip_header(&mut reader)?;
+        assert_eq!(metadata.timestamp, Some(1_709_210_096));
+        assert_eq!(metadata.command.as_deref(), Some("ls -l"));
+        assert_eq!(reader, b"hello");
+
+        // Logs without a header are left alone
+        let mut reader = &b"hello"[..];
+        assert_eq!(skip_header(&mut reader)?, Metadata::default());
+        assert_eq!(reader, b"hello");
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_signal() {
+        assert_eq!(
+            parse_signal("SIGWINCH ROWS=40 COLS=120"),
+            Some(EventKind::Resize {
+                cols: 120,
+                rows: 40
+            })
+        );
+        assert_eq!(parse_signal("SIGTERM"), None);
+    }
+
+    #[test]
+    fn test_read_shared_log() -> Result<()> {
+        // `script -B` logs both streams to one file in timing order
+        let timing_file = "test_script_shared.timing";
+        let log_file = "test_script_shared.log";
+        fs::write(
+            timing_file,
+            format!(
+                "H 0.000000 COLUMNS 100\nH 0.000000 INPUT_LOG {0}\nH 0.000000 OUTPUT_LOG {0}\n\
+                 O 0.100000 2\nI 0.200000 3\nS 0.050000 SIGWINCH ROWS=30 COLS=90\n\
+                 O 0.010000 3\nH 0.000000 EXIT_CODE 2\n",
+                log_file
+            ),
+        )?;
+        fs::write(
+            log_file,
+            "Script started on 2024-02-29 12:34:56+00:00\n$ ls\na b",
+        )?;
+
+        let mut events = AdvancedEvents::open(timing_file, log_file, None)?;
+        let collected = events.by_ref().collect::<Result<Vec<_>>>()?;
+        let kinds: Vec<_> = collected.iter().map(|e| e.kind.clone()).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                EventKind::Output(b"$ ".to_vec()),
+                EventKind::Input(b"ls\n".to_vec()),
+                EventKind::Resize { cols: 90, rows: 30 },
+                EventKind::Output(b"a b".to_vec()),
+                EventKind::Exit(2),
+            ]
+        );
+        assert_eq!(collected[3].time, Duration::from_millis(360));
+        assert_eq!(events.metadata().width, Some(100));
+        assert_eq!(events.metadata().timestamp, Some(1_709_210_096));
+
+        fs::remove_file(timing_file).unwrap_or(());
+        fs::remove_file(log_file).unwrap_or(());
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_entry() -> Result<()> {
+        let timing_file = "test_script_invalid.timing";
+        let log_file = "test_script_invalid.log";
+        fs::write(timing_file, "O 0.1 1\nX 0.1 1\n")?;
+        fs::write(log_file, "ab")?;
+
+        let results: Vec<_> = AdvancedEvents::open(timing_file, log_file, None)?.collect();
+        assert_eq!(results.len(), 2);
+        assert!(results[1]
+            .as_ref()
+            .unwrap_err()
+            .to_string()
+            .contains("Unknown timing entry type"));
+
+        fs::remove_file(timing_file).unwrap_or(());
+        fs::remove_file(log_file).unwrap_or(());
+        Ok(())
+    }
+}
@@ -0,0 +1,220 @@
+//! Full-text search
+//!
+//! The output of a session is stripped of escape sequences and joined into
+//! one text, so a match is found even when the recording split it across
+//! timing chunks or wrapped part of it in colors. Each match is reported
+//! with the recording time at which it was completely on screen and the
+//! text around it on the same line.
+
+use crate::sanitize::AnsiStripper;
+use crate::session::{EventKind, EventStream};
+use crate::utf8::Utf8Decoder;
+use anyhow::{anyhow, Result};
+use regex::RegexBuilder;
+use std::time::Duration;
+
+/// Options for [`search`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchOptions {
+    /// Match letters in either case
+    pub ignore_case: bool,
+    /// Most characters of surrounding text kept on each side of a match
+    pub context: usize,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        Self {
+            ignore_case: false,
+            context: 40,
+        }
+    }
+}
+
+/// A piece of output matching a search pattern
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchMatch {
+    /// Recording time of the output that completed the match
+    pub time: Duration,
+    /// The matched text
+    pub text: String,
+    /// Text before the match on the same line
+    pub before: String,
+    /// Text after the match on the same line
+    pub after: String,
+}
+
+/// The text of a session's output and when each part of it appeared
+#[derive(Debug, Default)]
+struct Transcript {
+    text: String,
+    /// Length of the text after each block of output, with its time
+    blocks: Vec<(usize, Duration)>,
+    /// A carriage return not yet known to end a line
+    pending_cr: bool,
+}
+
+impl Transcript {
+    fn push(&mut self, text: &str, time: Duration) {
+        for c in text.chars() {
+            if self.pending_cr {
+                // A lone carriage return redraws the line, so the text
+                // before it is kept as a line of its own
+                self.text.push('\n');
+                self.pending_cr = false;
+                if c == '\n' {
+                    continue;
+                }
+            }
+            if c == '\r' {
+                self.pending_cr = true;
+            } else {
+                self.text.push(c);
+            }
+        }
+        self.blocks.push((self.text.len(), time));
+    }
+
+    /// Recording time at which the text up to `end` had been written
+    fn time_at(&self, end: usize) -> Duration {
+        let block = self.blocks.partition_point(|&(len, _)| len < end);
+        self.blocks
+            .get(block)
+            .or(self.blocks.last())
+            .map_or(Duration::ZERO, |&(_, time)| time)
+    }
+}
+
+/// Find every match of the regular expression `pattern` in the output of a
+/// session, in the order the output appeared
+///
+/// Escape sequences and control characters other than line breaks are
+/// removed before searching, and carriage returns start a new line, so `.`
+/// and `$` work on lines as they were shown.
+pub fn search(
+    events: EventStream,
+    pattern: &str,
+    options: &SearchOptions,
+) -> Result<Vec<SearchMatch>> {
+    let regex = RegexBuilder::new(pattern)
+        .case_insensitive(options.ignore_case)
+        .multi_line(true)
+        .build()
+        .map_err(|e| anyhow!("Invalid search pattern '{}': {}", pattern, e))?;
+
+    // Both filters keep their state between blocks, so sequences and
+    // characters split by the recording are handled
+    let mut stripper = AnsiStripper::new();
+    let mut decoder = Utf8Decoder::new();
+    let mut transcript = Transcript::default();
+    let mut last = Duration::ZERO;
+    for event in events {
+        let event = event?;
+        if let EventKind::Output(data) = &event.kind {
+            transcript.push(&decoder.decode(&stripper.feed(data)), event.time);
+            last = event.time;
+        }
+    }
+    transcript.push(&decoder.finish(), last);
+
+    let text = &transcript.text;
+    Ok(regex
+        .find_iter(text)
+        .filter(|found| !found.is_empty())
+        .map(|found| {
+            let line_start = text[..found.start()].rfind('\n').map_or(0, |i| i + 1);
+            let line_end = text[found.end()..]
+                .find('\n')
+                .map_or(text.len(), |i| found.end() + i);
+            let before = &text[line_start..found.start()];
+            let after = &text[found.end()..line_end];
+            SearchMatch {
+                time: transcript.time_at(found.end()),
+                text: found.as_str().to_string(),
+                before: last_chars(before, options.context).to_string(),
+                after: first_chars(after, options.context).to_string(),
+            }
+        })
+        .collect())
+}
+
+/// The first `count` characters of `text`
+fn first_chars(text: &str, count: usize) -> &str {
+    match text.char_indices().nth(count) {
+        Some((end, _)) => &text[..end],
+        None => text,
+    }
+}
+
+/// The last `count` characters of `text`
+fn last_chars(text: &str, count: usize) -> &str {
+    if count == 0 {
+        return "";
+    }
+    match text.char_indices().rev().nth(count - 1) {
+        Some((start, _)) => &text[start..],
+        None => text,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::{Event, Metadata};
+
+    fn recording(chunks: &[(u64, &str)]) -> EventStream {
+        let events: Vec<_> = chunks
+            .iter()
+            .map(|&(millis, data)| Event::output(Duration::from_millis(millis), data))
+            .collect();
+        EventStream::new(Metadata::default(), events.into_iter().map(Ok))
+    }
+
+    #[test]
+    fn test_search_across_chunks_and_sequences() {
+        let events = recording(&[
+            (100, "$ make\r\n"),
+            (1500, "build \x1b[1;31merr"),
+            (1700, "or\x1b[0m: missing ;\r\n"),
+            (2000, "$ "),
+        ]);
+        let matches = search(events, r"error: \w+", &SearchOptions::default()).unwrap();
+        assert_eq!(
+            matches,
+            vec![SearchMatch {
+                time: Duration::from_millis(1700),
+                text: "error: missing".to_string(),
+                before: "build ".to_string(),
+                after: " ;".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_search_options() {
+        let chunks = [
+            (0, "Done\r\n"),
+            (10, "downloading 50%\rdownloading 100%\r\n"),
+        ];
+        let options = SearchOptions {
+            ignore_case: true,
+            context: 4,
+        };
+        let matches = search(recording(&chunks), r"^do\w+", &options).unwrap();
+        let found: Vec<_> = matches.iter().map(|m| m.text.as_str()).collect();
+        assert_eq!(found, ["Done", "downloading", "downloading"]);
+        assert_eq!(matches[2].after, " 100");
+
+        let matches = search(recording(&chunks), "done", &SearchOptions::default()).unwrap();
+        assert!(matches.is_empty());
+        assert!(search(recording(&chunks), "(", &SearchOptions::default()).is_err());
+    }
+
+    #[test]
+    fn test_context_is_cut_at_characters() {
+        assert_eq!(first_chars("résumé", 2), "ré");
+        assert_eq!(last_chars("résumé", 2), "mé");
+        assert_eq!(last_chars("ab", 5), "ab");
+        assert_eq!(last_chars("ab", 0), "");
+    }
+}
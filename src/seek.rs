@@ -0,0 +1,157 @@
+//! Playing part of a session
+//!
+//! Output before the start point is run through the built-in [`Terminal`]
+//! emulator instead of being shown, and playback begins by drawing the
+//! emulated screen. That way a replay that starts in the middle of a
+//! full-screen program or after a screen clear shows what the terminal
+//! really looked like at that moment, without waiting for the earlier output.
+
+use crate::session::{Event, EventKind, EventStream, DEFAULT_HEIGHT, DEFAULT_WIDTH};
+use crate::terminal::Terminal;
+use anyhow::{anyhow, Result};
+use std::time::Duration;
+
+/// Where playback starts
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Start<'a> {
+    /// At a recording time
+    Time(Duration),
+    /// At the first marker with this label
+    Marker(&'a str),
+}
+
+/// Cut a session down to the part between `start` and `end`
+///
+/// Returns the recording time playback starts at and the events from there
+/// on. When anything was skipped, the first event is output that redraws the
+/// screen as it was at the start point. Events after `end` are dropped.
+pub(crate) fn trim(
+    mut events: EventStream,
+    start: Option<Start>,
+    end: Option<Duration>,
+) -> Result<(Duration, EventStream)> {
+    let metadata = events.metadata.clone();
+    let mut terminal = Terminal::new(
+        metadata.width.unwrap_or(DEFAULT_WIDTH),
+        metadata.height.unwrap_or(DEFAULT_HEIGHT),
+    );
+    terminal.set_scrollback_limit(0);
+    // The tty driver does the same to output on its way to the terminal
+    terminal.set_convert_eol(true);
+
+    let mut skipped = false;
+    let (start_time, first) = loop {
+        let event = match events.next().transpose()? {
+            Some(event) => event,
+            None => match start {
+                Some(Start::Marker(label)) => {
+                    return Err(anyhow!("Marker not found in session: {}", label))
+                }
+                Some(Start::Time(time)) => break (time, None),
+                None => break (Duration::ZERO, None),
+            },
+        };
+        match (start, &event.kind) {
+            (None, _) => break (Duration::ZERO, Some(event)),
+            (Some(Start::Time(time)), _) if event.time >= time => break (time, Some(event)),
+            (Some(Start::Marker(label)), EventKind::Marker(marker)) if marker == label => {
+                break (event.time, Some(event))
+            }
+            (_, EventKind::Output(data)) => {
+                terminal.feed(data);
+                skipped = true;
+            }
+            (_, EventKind::Resize { cols, rows }) => terminal.resize(*cols, *rows),
+            _ => {}
+        }
+    };
+
+    let screen = skipped.then(|| Event::output(start_time, terminal.to_ansi()));
+    let events = screen
+        .into_iter()
+        .chain(first)
+        .map(Ok)
+        .chain(events)
+        .take_while(move |event| match (event, end) {
+            (Ok(event), Some(end)) => event.time <= end,
+            _ => true,
+        });
+    Ok((start_time, EventStream::new(metadata, events)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::Metadata;
+
+    fn stream(events: Vec<Event>) -> EventStream {
+        let metadata = Metadata {
+            width: Some(20),
+            height: Some(3),
+            ..Metadata::default()
+        };
+        EventStream::new(metadata, events.into_iter().map(Ok))
+    }
+
+    fn recording() -> Vec<Event> {
+        vec![
+            Event::output(Duration::from_secs(1), "one\r\n"),
+            Event::output(Duration::from_secs(2), "\x1b[2Jtwo"),
+            Event {
+                time: Duration::from_secs(3),
+                kind: EventKind::Marker("deploy".to_string()),
+            },
+            Event::output(Duration::from_secs(4), " three"),
+            Event::output(Duration::from_secs(5), " four"),
+        ]
+    }
+
+    fn output(events: EventStream) -> Vec<(u64, Vec<u8>)> {
+        events
+            .map(|event| event.unwrap())
+            .filter_map(|event| match event.kind {
+                EventKind::Output(data) => Some((event.time.as_secs(), data)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_trim_by_time() {
+        let (start, events) = trim(stream(recording()), None, None).unwrap();
+        assert_eq!(start, Duration::ZERO);
+        assert_eq!(output(events).len(), 4);
+
+        let (start, events) = trim(
+            stream(recording()),
+            Some(Start::Time(Duration::from_millis(3500))),
+            Some(Duration::from_secs(4)),
+        )
+        .unwrap();
+        assert_eq!(start, Duration::from_millis(3500));
+        let output = output(events);
+        assert_eq!(output.len(), 2);
+        assert_eq!(output[1], (4, b" three".to_vec()));
+
+        // The first event redraws the screen as it was at the start point
+        let mut terminal = Terminal::new(20, 3);
+        terminal.feed(&output[0].1);
+        assert_eq!(terminal.screen_text(), "\ntwo");
+        assert_eq!(terminal.cursor(), (3, 1));
+    }
+
+    #[test]
+    fn test_trim_to_marker() {
+        let (start, events) =
+            trim(stream(recording()), Some(Start::Marker("deploy")), None).unwrap();
+        assert_eq!(start, Duration::from_secs(3));
+        let output = output(events);
+        assert_eq!(output.len(), 3);
+        assert_eq!(output[0].0, 3);
+
+        let error = trim(stream(recording()), Some(Start::Marker("missing")), None)
+            .err()
+            .unwrap();
+        assert!(error.to_string().contains("Marker not found"));
+    }
+}
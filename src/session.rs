@@ -0,0 +1,583 @@
+//! In-memory session model
+//!
+//! A [`Session`] holds the metadata and the ordered list of events of a
+//! recording independently of the file format it was read from. Every reader in
+//! the crate produces one and every writer consumes one, which makes converting
+//! between formats a matter of reading with one and writing with the other.
+
+use crate::gzip;
+use crate::script::{AdvancedEvents, AdvancedWriter};
+use anyhow::{anyhow, Result};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufRead, BufWriter, Read, Write};
+use std::time::Duration;
+
+/// Terminal width used when a recording does not specify one
+pub const DEFAULT_WIDTH: u16 = 80;
+
+/// Terminal height used when a recording does not specify one
+pub const DEFAULT_HEIGHT: u16 = 24;
+
+/// Descriptive information about a recording
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Metadata {
+    /// Terminal width in columns, if known
+    pub width: Option<u16>,
+    /// Terminal height in rows, if known
+    pub height: Option<u16>,
+    /// Unix timestamp of the start of the recording
+    pub timestamp: Option<u64>,
+    /// Human readable title
+    pub title: Option<String>,
+    /// The command that was recorded
+    pub command: Option<String>,
+    /// Selected environment variables such as `SHELL` and `TERM`
+    pub env: BTreeMap<String, String>,
+    /// Longest pause to keep during playback, in seconds
+    pub idle_time_limit: Option<f64>,
+}
+
+/// The payload of a single session event
+#[derive(Debug, Clone, PartialEq)]
+pub enum EventKind {
+    /// Bytes written to the terminal
+    Output(Vec<u8>),
+    /// Bytes typed by the user
+    Input(Vec<u8>),
+    /// The terminal was resized
+    Resize {
+        /// New width in columns
+        cols: u16,
+        /// New height in rows
+        rows: u16,
+    },
+    /// A named point of interest, such as a chapter
+    Marker(String),
+    /// The recorded process exited with this status
+    Exit(i32),
+}
+
+/// A timestamped session event
+#[derive(Debug, Clone, PartialEq)]
+pub struct Event {
+    /// Time since the start of the recording
+    pub time: Duration,
+    /// What happened
+    pub kind: EventKind,
+}
+
+impl Event {
+    /// Create an output event
+    pub fn output(time: Duration, data: impl Into<Vec<u8>>) -> Self {
+        Self {
+            time,
+            kind: EventKind::Output(data.into()),
+        }
+    }
+}
+
+/// A recorded terminal session held in memory
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Session {
+    /// Information about the recording
+    pub metadata: Metadata,
+    /// Events ordered by time
+    pub events: Vec<Event>,
+}
+
+impl Session {
+    /// Create an empty session
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Time of the last event
+    pub fn duration(&self) -> Duration {
+        self.events.last().map(|e| e.time).unwrap_or_default()
+    }
+
+    /// Iterate over output events as `(time, bytes)` pairs
+    pub fn output(&self) -> impl Iterator<Item = (Duration, &[u8])> {
+        self.events.iter().filter_map(|event| match &event.kind {
+            EventKind::Output(data) => Some((event.time, data.as_slice())),
+            _ => None,
+        })
+    }
+
+    /// Read a scriptreplay typescript and timing file pair
+    ///
+    /// # Arguments
+    ///
+    /// * `timing_file` - Path to the timing data file
+    /// * `typescript_file` - Path to the session output file
+    pub fn read_scriptreplay(timing_file: &str, typescript_file: &str) -> Result<Self> {
+        session_from_frames(ScriptreplayFrames::open(timing_file, typescript_file)?)
+    }
+
+    /// Write the session as a scriptreplay typescript and timing file pair
+    ///
+    /// Only output events are written; the format has no place for the rest.
+    pub fn write_scriptreplay(&self, timing_file: &str, typescript_file: &str) -> Result<()> {
+        let timing = BufWriter::new(
+            File::create(timing_file)
+                .map_err(|e| anyhow!("Failed to create timing file: {}", e))?,
+        );
+        let typescript = BufWriter::new(
+            File::create(typescript_file)
+                .map_err(|e| anyhow!("Failed to create output file: {}", e))?,
+        );
+
+        let mut writer = ScriptreplayWriter::new(timing, typescript);
+        for event in &self.events {
+            writer.write_event(event)?;
+        }
+        Box::new(writer).finish()
+    }
+
+    /// Read a util-linux advanced timing file and its logs
+    ///
+    /// `H` entries fill in the metadata. When `input_file` is `None` the
+    /// `INPUT_LOG` named by the timing file is used if it exists.
+    ///
+    /// # Arguments
+    ///
+    /// * `timing_file` - Path to the advanced timing file
+    /// * `output_file` - Path to the output log
+    /// * `input_file` - Path to the input log, if any
+    pub fn read_advanced(
+        timing_file: &str,
+        output_file: &str,
+        input_file: Option<&str>,
+    ) -> Result<Self> {
+        let mut events = AdvancedEvents::open(timing_file, output_file, input_file)?;
+        let mut session = Session::new();
+        for event in events.by_ref() {
+            session.events.push(event?);
+        }
+
+        // Header entries take precedence over the log header line
+        session.metadata = events.metadata().clone();
+        Ok(session)
+    }
+
+    /// Write the session as a util-linux advanced timing file and logs
+    ///
+    /// Input events are only written when `input_file` is given; markers
+    /// have no place in the format and are dropped.
+    pub fn write_advanced(
+        &self,
+        timing_file: &str,
+        output_file: &str,
+        input_file: Option<&str>,
+    ) -> Result<()> {
+        let create = |path: &str, what: &str| {
+            File::create(path).map_err(|e| anyhow!("Failed to create {} file: {}", what, e))
+        };
+        let input = input_file
+            .map(|path| create(path, "input").map(BufWriter::new))
+            .transpose()?;
+
+        let mut writer = AdvancedWriter::new(
+            BufWriter::new(create(timing_file, "timing")?),
+            BufWriter::new(create(output_file, "output")?),
+            input,
+            &self.metadata,
+            (timing_file, output_file, input_file),
+        )?;
+        for event in &self.events {
+            writer.write_event(event)?;
+        }
+        writer.finish()
+    }
+
+    /// Read a ttyrec recording
+    pub fn read_ttyrec<R: Read>(reader: R) -> Result<Self> {
+        crate::ttyrec::read(reader)
+    }
+
+    /// Write the output events as a ttyrec recording
+    pub fn write_ttyrec<W: Write>(&self, writer: W) -> Result<()> {
+        crate::ttyrec::write(self, writer)
+    }
+
+    /// Read an asciicast v1, v2 or v3 recording
+    pub fn read_asciicast<R: BufRead>(reader: R) -> Result<Self> {
+        crate::asciicast::read(reader)
+    }
+
+    /// Write the session as an asciicast v2 recording
+    pub fn write_asciicast<W: Write>(&self, writer: W) -> Result<()> {
+        crate::asciicast::write(self, writer)
+    }
+}
+
+/// The files a session is stored in
+///
+/// Single-file formats only use `data`. Formats that keep timing in a
+/// separate file also use `timing`, which defaults to `<data>.timing` like
+/// the command line tools assume, and the advanced format can log input to
+/// its own file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SessionFiles {
+    /// The recording, or the typescript/output log of a file pair
+    pub data: String,
+    /// Timing file for formats that keep timing separately
+    pub timing: Option<String>,
+    /// Input log for formats that record input separately
+    pub input: Option<String>,
+}
+
+impl SessionFiles {
+    /// A session stored in a single file, or with the default timing file
+    pub fn new(data: &str) -> Self {
+        Self {
+            data: data.to_string(),
+            ..Self::default()
+        }
+    }
+
+    /// A session stored with an explicit timing file
+    pub fn with_timing(data: &str, timing: &str) -> Self {
+        Self {
+            data: data.to_string(),
+            timing: Some(timing.to_string()),
+            input: None,
+        }
+    }
+
+    /// The timing file, defaulting to `<data>.timing`
+    pub fn timing_file(&self) -> String {
+        self.timing
+            .clone()
+            .unwrap_or_else(|| format!("{}.timing", self.data))
+    }
+}
+
+/// A session file format that can be read into and written from a [`Session`]
+///
+/// Each supported format has an implementation, such as
+/// [`AsciicastFormat`](crate::AsciicastFormat), and
+/// [`Format::session_format`](crate::Format::session_format) picks the one
+/// for a [`Format`](crate::Format).
+pub trait SessionFormat {
+    /// Short name of the format, such as `asciicast`
+    fn name(&self) -> &'static str;
+
+    /// Read a whole session
+    fn read(&self, files: &SessionFiles) -> Result<Session>;
+
+    /// Write a whole session, replacing existing files
+    ///
+    /// Events the format has no place for are dropped.
+    fn write(&self, session: &Session, files: &SessionFiles) -> Result<()>;
+
+    /// Read a session one event at a time
+    ///
+    /// The default reads the whole session first. Formats that can be read
+    /// incrementally override it, so converting a large recording doesn't
+    /// need to hold it in memory.
+    fn events(&self, files: &SessionFiles) -> Result<EventStream> {
+        let session = self.read(files)?;
+        Ok(EventStream::new(
+            session.metadata,
+            session.events.into_iter().map(Ok),
+        ))
+    }
+
+    /// Create a writer that takes a session one event at a time
+    fn writer(&self, metadata: &Metadata, files: &SessionFiles) -> Result<Box<dyn EventWriter>>;
+}
+
+/// The events of a session as they are read, along with its metadata
+///
+/// Created by [`SessionFormat::events`].
+pub struct EventStream {
+    /// Metadata known before the first event
+    pub metadata: Metadata,
+    events: Box<dyn Iterator<Item = Result<Event>>>,
+}
+
+impl EventStream {
+    /// Wrap an iterator of events
+    pub fn new(metadata: Metadata, events: impl Iterator<Item = Result<Event>> + 'static) -> Self {
+        Self {
+            metadata,
+            events: Box::new(events),
+        }
+    }
+}
+
+impl Iterator for EventStream {
+    type Item = Result<Event>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.events.next()
+    }
+}
+
+/// Writes a session one event at a time
+///
+/// Created by [`SessionFormat::writer`]. Events must be written in time
+/// order, and [`EventWriter::finish`] must be called to complete the files.
+pub trait EventWriter {
+    /// Append an event; events the format has no place for are skipped
+    fn write_event(&mut self, event: &Event) -> Result<()>;
+
+    /// Flush everything that is still buffered
+    fn finish(self: Box<Self>) -> Result<()>;
+}
+
+/// Stream every event of `events` through `writer` and finish it
+pub(crate) fn copy_events(events: EventStream, mut writer: Box<dyn EventWriter>) -> Result<()> {
+    for event in events {
+        writer.write_event(&event?)?;
+    }
+    writer.finish()
+}
+
+/// Writes a scriptreplay typescript and timing file pair
+///
+/// Only output is stored; delays are measured between output events.
+pub(crate) struct ScriptreplayWriter<W: Write> {
+    timing: W,
+    typescript: W,
+    last: Duration,
+}
+
+impl<W: Write> ScriptreplayWriter<W> {
+    pub(crate) fn new(timing: W, typescript: W) -> Self {
+        Self {
+            timing,
+            typescript,
+            last: Duration::ZERO,
+        }
+    }
+}
+
+impl<W: Write> EventWriter for ScriptreplayWriter<W> {
+    fn write_event(&mut self, event: &Event) -> Result<()> {
+        let EventKind::Output(data) = &event.kind else {
+            return Ok(());
+        };
+        let delay = event.time.saturating_sub(self.last);
+        self.last = event.time;
+
+        writeln!(self.timing, "{:.6} {}", delay.as_secs_f64(), data.len())
+            .map_err(|e| anyhow!("Failed to write timing data: {}", e))?;
+        self.typescript
+            .write_all(data)
+            .map_err(|e| anyhow!("Failed to write output: {}", e))
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<()> {
+        self.timing
+            .flush()
+            .map_err(|e| anyhow!("Failed to write timing data: {}", e))?;
+        self.typescript
+            .flush()
+            .map_err(|e| anyhow!("Failed to write output: {}", e))
+    }
+}
+
+/// Open a file for buffered reading, decompressing it if it is gzipped,
+/// and naming it in the error
+pub(crate) fn open_file(path: &str, what: &str) -> Result<Box<dyn BufRead>> {
+    gzip::open(path).map_err(|e| anyhow!("Failed to open {} file {}: {}", what, path, e))
+}
+
+/// Create a file for buffered writing
+pub(crate) fn create_file(path: &str, what: &str) -> Result<BufWriter<File>> {
+    File::create(path)
+        .map(BufWriter::new)
+        .map_err(|e| anyhow!("Failed to create {} file: {}", what, e))
+}
+
+/// Lazily reads `(delay, bytes)` blocks from a scriptreplay file pair
+///
+/// Blank and malformed timing lines are skipped like scriptreplay does, and
+/// a typescript that ends early simply ends the iteration.
+pub(crate) struct ScriptreplayFrames<T: BufRead = Box<dyn BufRead>, S: BufRead = Box<dyn BufRead>> {
+    timing: std::io::Lines<T>,
+    typescript: S,
+    done: bool,
+}
+
+impl ScriptreplayFrames {
+    pub(crate) fn open(timing_file: &str, typescript_file: &str) -> Result<Self> {
+        let timing = gzip::open(timing_file)
+            .map_err(|e| anyhow!("Failed to read timing file {}: {}", timing_file, e))?;
+        let typescript = gzip::open(typescript_file)
+            .map_err(|e| anyhow!("Failed to open typescript file {}: {}", typescript_file, e))?;
+        Self::new(timing, typescript)
+    }
+}
+
+impl<T: BufRead, S: BufRead> ScriptreplayFrames<T, S> {
+    pub(crate) fn new(timing: T, typescript: S) -> Result<Self> {
+        Ok(Self {
+            timing: timing.lines(),
+            typescript,
+            done: false,
+        })
+    }
+
+    fn read_frame(&mut self) -> Result<Option<(Duration, Vec<u8>)>> {
+        for line in self.timing.by_ref() {
+            let line = line.map_err(|e| anyhow!("Failed to read timing file: {}", e))?;
+            let Some((delay, size)) = parse_timing_line(&line)? else {
+                continue;
+            };
+
+            // Read through `take` so a corrupt size can't force a huge allocation
+            let mut buffer = Vec::new();
+            (&mut self.typescript)
+                .take(size as u64)
+                .read_to_end(&mut buffer)
+                .map_err(|e| anyhow!("Error reading typescript: {}", e))?;
+            if buffer.len() < size {
+                // Reached end of file
+                return Ok(None);
+            }
+
+            return Ok(Some((delay, buffer)));
+        }
+        Ok(None)
+    }
+}
+
+impl<T: BufRead, S: BufRead> Iterator for ScriptreplayFrames<T, S> {
+    type Item = Result<(Duration, Vec<u8>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let frame = self.read_frame().transpose();
+        if !matches!(frame, Some(Ok(_))) {
+            self.done = true;
+        }
+        frame
+    }
+}
+
+/// Collect `(delay, bytes)` blocks into a session of output events
+pub(crate) fn session_from_frames(
+    frames: impl Iterator<Item = Result<(Duration, Vec<u8>)>>,
+) -> Result<Session> {
+    let mut session = Session::new();
+    let mut time = Duration::ZERO;
+
+    for frame in frames {
+        let (delay, data) = frame?;
+        time += delay;
+        session.events.push(Event::output(time, data));
+    }
+
+    Ok(session)
+}
+
+/// Parse a `delay size` timing line
+///
+/// Returns `None` for blank and malformed lines, which scriptreplay skips.
+/// Negative delays are treated as zero.
+pub(crate) fn parse_timing_line(line: &str) -> Result<Option<(Duration, usize)>> {
+    let parts: Vec<&str> = line.split_whitespace().collect();
+    if parts.len() < 2 {
+        return Ok(None);
+    }
+
+    let delay: f64 = parts[0]
+        .parse()
+        .map_err(|e| anyhow!("Invalid delay value '{}': {}", parts[0], e))?;
+    let size: usize = parts[1]
+        .parse()
+        .map_err(|e| anyhow!("Invalid size value '{}': {}", parts[1], e))?;
+
+    Ok(Some((seconds(delay.max(0.0))?, size)))
+}
+
+/// Convert fractional seconds to a `Duration`, rounded to microseconds
+pub(crate) fn seconds(value: f64) -> Result<Duration> {
+    if !value.is_finite() || value < 0.0 {
+        return Err(anyhow!("Invalid time value: {}", value));
+    }
+    Ok(Duration::from_micros((value * 1_000_000.0).round() as u64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_scriptreplay_round_trip() -> Result<()> {
+        let timing_file = "test_session_round_trip.timing";
+        let typescript_file = "test_session_round_trip.out";
+
+        let mut session = Session::new();
+        session
+            .events
+            .push(Event::output(Duration::from_millis(100), "Hello"));
+        session.events.push(Event {
+            time: Duration::from_millis(150),
+            kind: EventKind::Marker("chapter".to_string()),
+        });
+        session
+            .events
+            .push(Event::output(Duration::from_millis(350), " World\n"));
+        session.write_scriptreplay(timing_file, typescript_file)?;
+
+        assert_eq!(fs::read_to_string(timing_file)?, "0.100000 5\n0.250000 7\n");
+
+        let read_back = Session::read_scriptreplay(timing_file, typescript_file)?;
+        let output: Vec<_> = read_back.output().collect();
+        assert_eq!(
+            output,
+            vec![
+                (Duration::from_millis(100), b"Hello".as_slice()),
+                (Duration::from_millis(350), b" World\n".as_slice()),
+            ]
+        );
+        assert_eq!(read_back.duration(), Duration::from_millis(350));
+
+        fs::remove_file(timing_file).unwrap_or(());
+        fs::remove_file(typescript_file).unwrap_or(());
+        Ok(())
+    }
+
+    #[test]
+    fn test_scriptreplay_keeps_leading_script_started() -> Result<()> {
+        let timing_file = "test_session_script_started.timing";
+        let typescript_file = "test_session_script_started.out";
+
+        // Only advanced logs have a header; here the text is recorded output
+        let mut session = Session::new();
+        session.events = vec![
+            Event::output(Duration::from_millis(100), "Script started by me\n"),
+            Event::output(Duration::from_millis(200), "hello\n"),
+        ];
+        session.write_scriptreplay(timing_file, typescript_file)?;
+
+        let read_back = Session::read_scriptreplay(timing_file, typescript_file)?;
+        assert_eq!(read_back.events, session.events);
+
+        fs::remove_file(timing_file).unwrap_or(());
+        fs::remove_file(typescript_file).unwrap_or(());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_timing_line() -> Result<()> {
+        assert_eq!(
+            parse_timing_line("0.5 12")?,
+            Some((Duration::from_millis(500), 12))
+        );
+        assert_eq!(parse_timing_line("")?, None);
+        assert_eq!(parse_timing_line("0.5")?, None);
+        assert!(parse_timing_line("abc 5").is_err());
+        assert!(parse_timing_line("inf 5").is_err());
+        assert_eq!(parse_timing_line("-1.0 5")?, Some((Duration::ZERO, 5)));
+        Ok(())
+    }
+}
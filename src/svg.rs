@@ -0,0 +1,451 @@
+//! Animated SVG export
+//!
+//! Like svg-term and termtosvg, sessions are played through the built-in
+//! [`Terminal`] emulator and every distinct screen state becomes a keyframe
+//! of a CSS animation inside a single self-contained SVG file. Text is real
+//! SVG text, so it stays sharp at any size and can be selected and copied.
+//! Each distinct line is defined once and reused by the frames showing it,
+//! which keeps files small for recordings that mostly scroll.
+
+use crate::render::{escape, line_runs, screen_states, Timeline, FINAL_HOLD};
+use crate::session::{Session, DEFAULT_HEIGHT, DEFAULT_WIDTH};
+use crate::terminal::{Line, Terminal};
+use crate::theme::Theme;
+use anyhow::{anyhow, Result};
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::io::Write;
+use std::time::Duration;
+
+/// Font size of the terminal text, in pixels
+const FONT_SIZE: f64 = 14.0;
+
+/// Advance of one monospace character at [`FONT_SIZE`]
+const CELL_WIDTH: f64 = 8.4;
+
+/// Height of a text row
+const LINE_HEIGHT: f64 = 18.0;
+
+/// Space between the screen and the edge of the image
+const PADDING: f64 = 10.0;
+
+/// Height of the title bar drawn with window chrome
+const TITLE_BAR: f64 = 30.0;
+
+const FONT_FAMILY: &str =
+    "ui-monospace, SFMono-Regular, Menlo, Monaco, Consolas, 'DejaVu Sans Mono', monospace";
+
+/// Options for [`export`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct SvgOptions {
+    /// Color theme
+    pub theme: Theme,
+    /// Terminal width; defaults to the recorded width
+    pub cols: Option<u16>,
+    /// Terminal height; defaults to the recorded height
+    pub rows: Option<u16>,
+    /// Draw a window frame with a title bar around the terminal
+    pub window: bool,
+    /// Highest number of keyframes per second
+    pub fps: f64,
+    /// Longest pause kept between keyframes, in seconds; defaults to the
+    /// recording's own idle time limit
+    pub idle_time_limit: Option<f64>,
+    /// Playback speed multiplier
+    pub speed: f64,
+    /// Recording time to start at; earlier output is fast-forwarded
+    pub start: Option<Duration>,
+    /// Recording time to stop at
+    pub end: Option<Duration>,
+}
+
+impl Default for SvgOptions {
+    fn default() -> Self {
+        Self {
+            theme: Theme::default(),
+            cols: None,
+            rows: None,
+            window: false,
+            fps: 30.0,
+            idle_time_limit: None,
+            speed: 1.0,
+            start: None,
+            end: None,
+        }
+    }
+}
+
+/// Render a session as an animated SVG
+pub fn export<W: Write>(session: &Session, mut writer: W, options: &SvgOptions) -> Result<()> {
+    let cols = options
+        .cols
+        .or(session.metadata.width)
+        .unwrap_or(DEFAULT_WIDTH)
+        .max(1);
+    let rows = options
+        .rows
+        .or(session.metadata.height)
+        .unwrap_or(DEFAULT_HEIGHT)
+        .max(1);
+
+    let timeline = Timeline {
+        fps: Some(options.fps),
+        idle_time_limit: options.idle_time_limit.or(session.metadata.idle_time_limit),
+        speed: options.speed,
+        start: options.start,
+        end: options.end,
+    };
+    let mut terminal = Terminal::new(cols, rows);
+    terminal.set_scrollback_limit(0);
+    terminal.set_convert_eol(true);
+
+    let mut frames = Frames::new(&options.theme);
+    let end = screen_states(session, &mut terminal, &timeline, |time, terminal| {
+        frames.add(time, terminal);
+        Ok(())
+    })?;
+    let title = session
+        .metadata
+        .title
+        .clone()
+        .or_else(|| terminal.title().map(str::to_string))
+        .or_else(|| session.metadata.command.clone());
+
+    let svg = frames.document(cols, rows, end, title.as_deref(), options.window);
+    writer
+        .write_all(svg.as_bytes())
+        .and_then(|_| writer.flush())
+        .map_err(|e| anyhow!("Failed to write SVG: {}", e))
+}
+
+/// A distinct screen: the lines it shows and the cursor position
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct Screen {
+    lines: Vec<usize>,
+    cursor: Option<(u16, u16)>,
+}
+
+/// Screen states collected during playback
+struct Frames<'a> {
+    theme: &'a Theme,
+    /// Markup of each distinct line, indexed by line id
+    lines: Vec<String>,
+    line_ids: HashMap<Line, usize>,
+    screens: Vec<Screen>,
+    screen_ids: HashMap<Screen, usize>,
+    /// When each screen is shown, in order
+    keyframes: Vec<(Duration, usize)>,
+}
+
+impl<'a> Frames<'a> {
+    fn new(theme: &'a Theme) -> Self {
+        Self {
+            theme,
+            lines: Vec::new(),
+            line_ids: HashMap::new(),
+            screens: Vec::new(),
+            screen_ids: HashMap::new(),
+            keyframes: Vec::new(),
+        }
+    }
+
+    fn add(&mut self, time: Duration, terminal: &Terminal) {
+        let lines = terminal
+            .lines()
+            .iter()
+            .map(|line| self.line_id(line))
+            .collect();
+        let cursor = terminal.cursor_visible().then(|| terminal.cursor());
+        let screen = Screen { lines, cursor };
+
+        let id = match self.screen_ids.get(&screen) {
+            Some(&id) => id,
+            None => {
+                let id = self.screens.len();
+                self.screens.push(screen.clone());
+                self.screen_ids.insert(screen, id);
+                id
+            }
+        };
+
+        match self.keyframes.last_mut() {
+            Some((_, last)) if *last == id => {}
+            // States too close together to be seen replace each other
+            Some((last_time, last)) if *last_time == time => *last = id,
+            _ => self.keyframes.push((time, id)),
+        }
+    }
+
+    fn line_id(&mut self, line: &Line) -> usize {
+        if let Some(&id) = self.line_ids.get(line) {
+            return id;
+        }
+        let id = self.lines.len();
+        self.lines.push(self.line_markup(line));
+        self.line_ids.insert(line.clone(), id);
+        id
+    }
+
+    /// Background rectangles and text of one line, drawn at the origin
+    fn line_markup(&self, line: &Line) -> String {
+        let mut backgrounds = String::new();
+        let mut text = String::new();
+
+        for run in line_runs(line) {
+            let (foreground, background) = self.theme.cell_colors(run.style);
+            let x = run.col as f64 * CELL_WIDTH;
+            if background != self.theme.background {
+                let _ = write!(
+                    backgrounds,
+                    r#"<rect x="{}" width="{}" height="{}" fill="{}"/>"#,
+                    number(x),
+                    number(run.width as f64 * CELL_WIDTH),
+                    number(LINE_HEIGHT),
+                    background.hex()
+                );
+            }
+
+            let content = run.text.trim_end();
+            let decorated = run.style.underline || run.style.strikethrough;
+            if run.style.hidden || (content.trim_start().is_empty() && !decorated) {
+                continue;
+            }
+            let content = if decorated { &run.text } else { content };
+
+            let mut attributes = format!(r#" x="{}""#, number(x));
+            if foreground != self.theme.foreground {
+                let _ = write!(attributes, r#" fill="{}""#, foreground.hex());
+            }
+            if run.style.bold {
+                attributes.push_str(r#" font-weight="bold""#);
+            }
+            if run.style.italic {
+                attributes.push_str(r#" font-style="italic""#);
+            }
+            let decorations: Vec<&str> = [
+                (run.style.underline, "underline"),
+                (run.style.strikethrough, "line-through"),
+            ]
+            .iter()
+            .filter(|(enabled, _)| *enabled)
+            .map(|(_, name)| *name)
+            .collect();
+            if !decorations.is_empty() {
+                let _ = write!(
+                    attributes,
+                    r#" text-decoration="{}""#,
+                    decorations.join(" ")
+                );
+            }
+
+            let span = format!("<tspan{}>{}</tspan>", attributes, escape(content));
+            match &run.style.link {
+                Some(link) => {
+                    let _ = write!(text, r#"<a xlink:href="{}">{}</a>"#, escape(link), span);
+                }
+                None => text.push_str(&span),
+            }
+        }
+
+        if text.is_empty() {
+            backgrounds
+        } else {
+            format!(
+                r#"{}<text y="{}">{}</text>"#,
+                backgrounds,
+                number(LINE_HEIGHT * 0.75),
+                text
+            )
+        }
+    }
+
+    fn document(
+        &self,
+        cols: u16,
+        rows: u16,
+        end: Duration,
+        title: Option<&str>,
+        window: bool,
+    ) -> String {
+        let screen_width = cols as f64 * CELL_WIDTH;
+        let screen_height = rows as f64 * LINE_HEIGHT;
+        let top = if window { TITLE_BAR } else { PADDING };
+        let width = screen_width + 2.0 * PADDING;
+        let height = screen_height + top + PADDING;
+        let theme = self.theme;
+
+        let mut svg = String::new();
+        let _ = write!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink" width="{w}" height="{h}" viewBox="0 0 {w} {h}">"#,
+            w = number(width),
+            h = number(height)
+        );
+
+        // Styles and the animation moving between keyframes
+        let last = self
+            .keyframes
+            .last()
+            .map_or(Duration::ZERO, |(time, _)| *time);
+        let duration = end.max(last) + FINAL_HOLD;
+        let _ = write!(
+            svg,
+            "<style>.screen text{{font-family:{};font-size:{}px;white-space:pre;fill:{}}}",
+            FONT_FAMILY,
+            number(FONT_SIZE),
+            theme.foreground.hex()
+        );
+        if self.keyframes.len() > 1 {
+            let _ = write!(
+                svg,
+                ".frames{{animation:frames {}s steps(1,end) infinite}}@keyframes frames{{",
+                number(duration.as_secs_f64())
+            );
+            for (time, id) in &self.keyframes {
+                let percent = time.as_secs_f64() / duration.as_secs_f64() * 100.0;
+                let _ = write!(
+                    svg,
+                    "{}%{{transform:translateY({}px)}}",
+                    number(percent),
+                    number(-(*id as f64) * screen_height)
+                );
+            }
+            svg.push('}');
+        }
+        svg.push_str("</style>");
+
+        let _ = write!(
+            svg,
+            r#"<rect width="100%" height="100%" rx="{}" fill="{}"/>"#,
+            if window { 6 } else { 0 },
+            theme.background.hex()
+        );
+        if window {
+            for (index, color) in ["#ff5f58", "#ffbd2e", "#18c132"].iter().enumerate() {
+                let _ = write!(
+                    svg,
+                    r#"<circle cx="{}" cy="{}" r="6" fill="{}"/>"#,
+                    number(PADDING + 6.0 + index as f64 * 20.0),
+                    number(TITLE_BAR / 2.0),
+                    color
+                );
+            }
+            if let Some(title) = title {
+                // Leave room for the buttons on both sides
+                let fits = ((width - 2.0 * (PADDING + 60.0)) / 7.2).max(1.0) as usize;
+                let title = if title.chars().count() > fits {
+                    let mut short: String = title.chars().take(fits - 1).collect();
+                    short.push('…');
+                    short
+                } else {
+                    title.to_string()
+                };
+                let _ = write!(
+                    svg,
+                    r#"<text x="{}" y="{}" text-anchor="middle" font-family="{}" font-size="12" fill="{}">{}</text>"#,
+                    number(width / 2.0),
+                    number(TITLE_BAR / 2.0 + 4.0),
+                    FONT_FAMILY,
+                    theme.foreground.blend(theme.background, 0.4).hex(),
+                    escape(&title)
+                );
+            }
+        }
+
+        // The screens are stacked vertically and the animation scrolls the
+        // stack so that only the current one is inside the viewport
+        let _ = write!(
+            svg,
+            r#"<svg class="screen" x="{}" y="{}" width="{}" height="{}"><defs>"#,
+            number(PADDING),
+            number(top),
+            number(screen_width),
+            number(screen_height)
+        );
+        for (id, markup) in self.lines.iter().enumerate() {
+            if !markup.is_empty() {
+                let _ = write!(svg, r#"<g id="l{}">{}</g>"#, id, markup);
+            }
+        }
+        svg.push_str(r#"</defs><g class="frames">"#);
+        let cursor_color = theme.foreground.hex();
+        for (index, screen) in self.screens.iter().enumerate() {
+            let _ = write!(
+                svg,
+                r#"<g transform="translate(0 {})">"#,
+                number(index as f64 * screen_height)
+            );
+            for (row, &id) in screen.lines.iter().enumerate() {
+                if !self.lines[id].is_empty() {
+                    let _ = write!(
+                        svg,
+                        r##"<use xlink:href="#l{}" y="{}"/>"##,
+                        id,
+                        number(row as f64 * LINE_HEIGHT)
+                    );
+                }
+            }
+            if let Some((col, row)) = screen.cursor {
+                let _ = write!(
+                    svg,
+                    r#"<rect x="{}" y="{}" width="{}" height="{}" fill="{}" fill-opacity="0.6"/>"#,
+                    number(col as f64 * CELL_WIDTH),
+                    number(row as f64 * LINE_HEIGHT),
+                    number(CELL_WIDTH),
+                    number(LINE_HEIGHT),
+                    cursor_color
+                );
+            }
+            svg.push_str("</g>");
+        }
+        svg.push_str("</g></svg></svg>\n");
+        svg
+    }
+}
+
+/// Format a coordinate without needless trailing zeros
+fn number(value: f64) -> String {
+    let text = format!("{:.4}", value);
+    let text = text.trim_end_matches('0').trim_end_matches('.');
+    if text == "-0" {
+        "0".to_string()
+    } else {
+        text.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::Event;
+
+    #[test]
+    fn test_number() {
+        assert_eq!(number(8.4), "8.4");
+        assert_eq!(number(18.0), "18");
+        assert_eq!(number(-0.0), "0");
+    }
+
+    #[test]
+    fn test_keyframes_reuse_screens_and_lines() {
+        let mut session = Session::new();
+        session.metadata.width = Some(10);
+        session.metadata.height = Some(2);
+        session.events = vec![
+            Event::output(Duration::from_secs(1), "\x1b[?25lone"),
+            Event::output(Duration::from_secs(2), "\rtwo"),
+            Event::output(Duration::from_secs(3), "\rone"),
+        ];
+
+        let mut svg = Vec::new();
+        export(&session, &mut svg, &SvgOptions::default()).unwrap();
+        let svg = String::from_utf8(svg).unwrap();
+
+        // Blank, "one" and "two" are the only distinct screens
+        assert_eq!(svg.matches("<g transform=").count(), 3);
+        assert_eq!(svg.matches(">one<").count(), 1);
+        assert!(svg.contains("translateY(-36px)}"));
+        assert_eq!(svg.matches("{transform:translateY").count(), 4);
+        assert!(svg.ends_with("</svg></svg>\n"));
+    }
+}
@@ -0,0 +1,1509 @@
+//! In-process terminal emulator
+//!
+//! [`Terminal`] interprets the bytes of a recording the way an xterm-style
+//! terminal would and keeps the resulting screen, so exporters can render
+//! what the viewer actually saw instead of raw escape sequences. It handles
+//! UTF-8 and wide characters, cursor movement, erasing, scroll regions, the
+//! alternate screen, SGR colors and attributes, OSC titles and OSC 8
+//! hyperlinks, and the DEC line drawing character set. Anything it does not
+//! understand is consumed and ignored, as a real terminal would.
+
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+/// Lines kept above the screen when nothing else is configured
+pub const DEFAULT_SCROLLBACK: usize = 10_000;
+
+/// Longest OSC string accepted before the rest of it is ignored
+const MAX_OSC_LEN: usize = 4096;
+
+/// Most parameters kept for a single CSI sequence
+const MAX_PARAMS: usize = 32;
+
+/// A cell color
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Color {
+    /// The terminal's default foreground or background
+    #[default]
+    Default,
+    /// One of the 256 indexed colors; 0-15 are the theme's ANSI colors
+    Indexed(u8),
+    /// A 24-bit color
+    Rgb(u8, u8, u8),
+}
+
+/// Text attributes applied to a cell
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Style {
+    /// Foreground color
+    pub fg: Color,
+    /// Background color
+    pub bg: Color,
+    /// Bold or increased intensity
+    pub bold: bool,
+    /// Faint or decreased intensity
+    pub faint: bool,
+    /// Italic
+    pub italic: bool,
+    /// Underlined
+    pub underline: bool,
+    /// Blinking
+    pub blink: bool,
+    /// Foreground and background swapped
+    pub inverse: bool,
+    /// Invisible text
+    pub hidden: bool,
+    /// Crossed out
+    pub strikethrough: bool,
+    /// Target of an OSC 8 hyperlink
+    pub link: Option<Arc<str>>,
+}
+
+/// A single character cell
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Cell {
+    /// The character shown in the cell
+    pub ch: char,
+    /// Columns taken by the character: 1, 2 for wide characters, or 0 for
+    /// the cell covered by the right half of a wide character
+    pub width: u8,
+    /// Attributes of the cell
+    pub style: Style,
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Self {
+            ch: ' ',
+            width: 1,
+            style: Style::default(),
+        }
+    }
+}
+
+/// A row of cells
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Line {
+    /// Cells from left to right
+    pub cells: Vec<Cell>,
+    /// Whether the text continues on the next line because it wrapped
+    pub wrapped: bool,
+}
+
+impl Line {
+    fn blank(cols: usize, style: &Style) -> Self {
+        Self {
+            cells: vec![blank_cell(style); cols],
+            wrapped: false,
+        }
+    }
+
+    /// The text of the line without trailing spaces
+    pub fn text(&self) -> String {
+        let text: String = self
+            .cells
+            .iter()
+            .filter(|cell| cell.width > 0)
+            .map(|cell| cell.ch)
+            .collect();
+        text.trim_end().to_string()
+    }
+}
+
+/// A blank cell keeps the current background color, like xterm does
+fn blank_cell(style: &Style) -> Cell {
+    Cell {
+        style: Style {
+            bg: style.bg,
+            ..Style::default()
+        },
+        ..Cell::default()
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct SavedCursor {
+    x: usize,
+    y: usize,
+    pen: Style,
+    origin_mode: bool,
+    charsets: [bool; 2],
+    active_charset: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum State {
+    Ground,
+    Escape,
+    /// `ESC` followed by intermediate bytes such as `(` or `#`
+    EscapeIntermediate(u8),
+    Csi,
+    Osc,
+    OscEscape,
+    /// DCS, SOS, PM and APC strings, which are ignored
+    Ignore,
+    IgnoreEscape,
+}
+
+/// A terminal screen driven by the bytes of a recording
+#[derive(Debug, Clone)]
+pub struct Terminal {
+    cols: usize,
+    rows: usize,
+    lines: Vec<Line>,
+    /// The primary screen while the alternate screen is shown
+    primary: Option<Vec<Line>>,
+    scrollback: VecDeque<Line>,
+    scrollback_limit: usize,
+    x: usize,
+    y: usize,
+    pending_wrap: bool,
+    pen: Style,
+    saved: SavedCursor,
+    scroll_top: usize,
+    scroll_bottom: usize,
+    tabs: Vec<bool>,
+    cursor_visible: bool,
+    autowrap: bool,
+    origin_mode: bool,
+    insert_mode: bool,
+    convert_eol: bool,
+    /// Whether G0 and G1 are the DEC line drawing set
+    charsets: [bool; 2],
+    active_charset: usize,
+    last_char: Option<char>,
+    title: Option<String>,
+    state: State,
+    params: Vec<Vec<u16>>,
+    private: Option<u8>,
+    intermediate: Option<u8>,
+    osc: Vec<u8>,
+    utf8: Vec<u8>,
+}
+
+impl Terminal {
+    /// Create a blank terminal of the given size
+    pub fn new(cols: u16, rows: u16) -> Self {
+        let cols = (cols as usize).max(1);
+        let rows = (rows as usize).max(1);
+        Self {
+            cols,
+            rows,
+            lines: vec![Line::blank(cols, &Style::default()); rows],
+            primary: None,
+            scrollback: VecDeque::new(),
+            scrollback_limit: DEFAULT_SCROLLBACK,
+            x: 0,
+            y: 0,
+            pending_wrap: false,
+            pen: Style::default(),
+            saved: SavedCursor::default(),
+            scroll_top: 0,
+            scroll_bottom: rows - 1,
+            tabs: default_tabs(cols),
+            cursor_visible: true,
+            autowrap: true,
+            origin_mode: false,
+            insert_mode: false,
+            convert_eol: false,
+            charsets: [false; 2],
+            active_charset: 0,
+            last_char: None,
+            title: None,
+            state: State::Ground,
+            params: Vec::new(),
+            private: None,
+            intermediate: None,
+            osc: Vec::new(),
+            utf8: Vec::new(),
+        }
+    }
+
+    /// Treat a line feed as carriage return plus line feed
+    ///
+    /// This matches what the tty driver does to program output before it
+    /// reaches a terminal, which recordings captured without a pty lack.
+    pub fn set_convert_eol(&mut self, enable: bool) {
+        self.convert_eol = enable;
+    }
+
+    /// Number of columns
+    pub fn cols(&self) -> u16 {
+        self.cols as u16
+    }
+
+    /// Number of rows
+    pub fn rows(&self) -> u16 {
+        self.rows as u16
+    }
+
+    /// Cursor position as `(column, row)`, both starting at zero
+    pub fn cursor(&self) -> (u16, u16) {
+        (self.x as u16, self.y as u16)
+    }
+
+    /// Whether the cursor is shown
+    pub fn cursor_visible(&self) -> bool {
+        self.cursor_visible
+    }
+
+    /// Whether the alternate screen is active
+    pub fn alternate_screen(&self) -> bool {
+        self.primary.is_some()
+    }
+
+    /// The window title last set with OSC 0 or OSC 2
+    pub fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+
+    /// The visible lines from top to bottom
+    pub fn lines(&self) -> &[Line] {
+        &self.lines
+    }
+
+    /// Lines scrolled off the top of the primary screen, oldest first
+    pub fn scrollback(&self) -> impl Iterator<Item = &Line> {
+        self.scrollback.iter()
+    }
+
+    /// Remove the lines in the scrollback and return them, oldest first
+    pub fn take_scrollback(&mut self) -> Vec<Line> {
+        std::mem::take(&mut self.scrollback).into()
+    }
+
+    /// Limit the number of lines kept in the scrollback
+    pub fn set_scrollback_limit(&mut self, limit: usize) {
+        self.scrollback_limit = limit;
+        self.trim_scrollback();
+    }
+
+    /// The visible text, one line per row, without trailing blank lines
+    pub fn screen_text(&self) -> String {
+        let mut lines: Vec<String> = self.lines.iter().map(Line::text).collect();
+        while lines.last().is_some_and(|line| line.is_empty()) {
+            lines.pop();
+        }
+        lines.join("\n")
+    }
+
+    /// Escape sequences that bring a real terminal to the state of this one
+    ///
+    /// The screen is cleared and redrawn with its styles and hyperlinks, and
+    /// the cursor, pen, scroll region and title are restored. When the
+    /// alternate screen is active the primary screen is drawn first, so it
+    /// comes back when the program switches back to it.
+    pub fn to_ansi(&self) -> Vec<u8> {
+        let mut out = String::from("\x1b[0m\x1b[H\x1b[2J");
+        if let Some(primary) = &self.primary {
+            draw_lines(&mut out, primary);
+            out.push_str(&format!(
+                "\x1b[{};{}H\x1b[?1049h\x1b[H\x1b[2J",
+                self.saved.y + 1,
+                self.saved.x + 1
+            ));
+        }
+        draw_lines(&mut out, &self.lines);
+
+        if self.scroll_top > 0 || self.scroll_bottom < self.rows - 1 {
+            out.push_str(&format!(
+                "\x1b[{};{}r",
+                self.scroll_top + 1,
+                self.scroll_bottom + 1
+            ));
+        }
+        out.push_str(&format!("\x1b[{};{}H", self.y + 1, self.x + 1));
+        out.push_str(&sgr_sequence(&self.pen));
+        if let Some(link) = &self.pen.link {
+            out.push_str(&format!("\x1b]8;;{}\x1b\\", link));
+        }
+        if !self.cursor_visible {
+            out.push_str("\x1b[?25l");
+        }
+        if let Some(title) = &self.title {
+            out.push_str(&format!("\x1b]2;{}\x07", title));
+        }
+        out.into_bytes()
+    }
+
+    /// Change the size of the screen
+    ///
+    /// Lines are not reflowed. When the screen gets shorter, blank lines below
+    /// the cursor are dropped first and then lines move into the scrollback.
+    pub fn resize(&mut self, cols: u16, rows: u16) {
+        let cols = (cols as usize).max(1);
+        let rows = (rows as usize).max(1);
+
+        let blank = Style::default();
+        let mut lines = std::mem::take(&mut self.lines);
+        while lines.len() > rows {
+            let last_is_blank = lines.last().is_some_and(|line| line.text().is_empty());
+            if lines.len() - 1 > self.y && last_is_blank {
+                lines.pop();
+            } else {
+                let line = lines.remove(0);
+                self.y = self.y.saturating_sub(1);
+                if self.primary.is_none() {
+                    self.push_scrollback(line);
+                }
+            }
+        }
+        lines.resize(rows, Line::blank(cols, &blank));
+        for line in &mut lines {
+            line.cells.resize(cols, blank_cell(&blank));
+        }
+        self.lines = lines;
+
+        if let Some(primary) = &mut self.primary {
+            primary.truncate(rows);
+            primary.resize(rows, Line::blank(cols, &blank));
+            for line in primary.iter_mut() {
+                line.cells.resize(cols, blank_cell(&blank));
+            }
+        }
+
+        self.cols = cols;
+        self.rows = rows;
+        self.scroll_top = 0;
+        self.scroll_bottom = rows - 1;
+        self.tabs = default_tabs(cols);
+        self.x = self.x.min(cols - 1);
+        self.y = self.y.min(rows - 1);
+        self.pending_wrap = false;
+        for line in &mut self.lines {
+            fix_wide_edge(line);
+        }
+    }
+
+    /// Interpret a block of output
+    pub fn feed(&mut self, data: &[u8]) {
+        for &byte in data {
+            self.advance(byte);
+        }
+    }
+
+    fn advance(&mut self, byte: u8) {
+        match self.state {
+            State::Ground => self.ground(byte),
+            State::Escape => self.escape(byte),
+            State::EscapeIntermediate(intermediate) => {
+                self.state = State::Ground;
+                self.escape_dispatch(intermediate, byte);
+            }
+            State::Csi => self.csi(byte),
+            State::Osc => match byte {
+                0x07 => self.osc_dispatch(),
+                0x1b => self.state = State::OscEscape,
+                _ => {
+                    if self.osc.len() < MAX_OSC_LEN {
+                        self.osc.push(byte);
+                    }
+                }
+            },
+            State::OscEscape => {
+                if byte == b'\\' {
+                    self.osc_dispatch();
+                } else {
+                    // An escape inside OSC aborts it and starts a new sequence
+                    self.osc.clear();
+                    self.state = State::Escape;
+                    self.escape(byte);
+                }
+            }
+            State::Ignore => {
+                if byte == 0x1b {
+                    self.state = State::IgnoreEscape;
+                } else if byte == 0x07 {
+                    self.state = State::Ground;
+                }
+            }
+            State::IgnoreEscape => {
+                self.state = if byte == b'\\' {
+                    State::Ground
+                } else {
+                    State::Ignore
+                };
+            }
+        }
+    }
+
+    fn ground(&mut self, byte: u8) {
+        if !self.utf8.is_empty() || byte >= 0x80 {
+            self.utf8_byte(byte);
+            return;
+        }
+        match byte {
+            0x1b => {
+                self.state = State::Escape;
+                self.intermediate = None;
+            }
+            0x20..=0x7e => self.print(byte as char),
+            _ => self.control(byte),
+        }
+    }
+
+    fn utf8_byte(&mut self, byte: u8) {
+        if self.utf8.is_empty() {
+            if utf8_len(byte) == 0 {
+                self.print(char::REPLACEMENT_CHARACTER);
+                return;
+            }
+            self.utf8.push(byte);
+            return;
+        }
+
+        if byte & 0xc0 != 0x80 {
+            // The sequence was cut short; replace it and start over
+            self.utf8.clear();
+            self.print(char::REPLACEMENT_CHARACTER);
+            self.ground(byte);
+            return;
+        }
+
+        self.utf8.push(byte);
+        if self.utf8.len() == utf8_len(self.utf8[0]) {
+            let ch = std::str::from_utf8(&self.utf8)
+                .ok()
+                .and_then(|s| s.chars().next())
+                .unwrap_or(char::REPLACEMENT_CHARACTER);
+            self.utf8.clear();
+            self.print(ch);
+        }
+    }
+
+    fn control(&mut self, byte: u8) {
+        match byte {
+            0x08 => {
+                self.x = self.x.saturating_sub(1);
+                self.pending_wrap = false;
+            }
+            0x09 => self.tab_forward(1),
+            0x0a..=0x0c => self.linefeed(),
+            0x0d => {
+                self.x = 0;
+                self.pending_wrap = false;
+            }
+            0x0e => self.active_charset = 1,
+            0x0f => self.active_charset = 0,
+            _ => {}
+        }
+    }
+
+    fn escape(&mut self, byte: u8) {
+        self.state = State::Ground;
+        match byte {
+            b'[' => {
+                self.params.clear();
+                self.params.push(Vec::new());
+                self.private = None;
+                self.intermediate = None;
+                self.state = State::Csi;
+            }
+            b']' => {
+                self.osc.clear();
+                self.state = State::Osc;
+            }
+            b'P' | b'X' | b'^' | b'_' => self.state = State::Ignore,
+            b' '..=b'/' => self.state = State::EscapeIntermediate(byte),
+            b'7' => self.save_cursor(),
+            b'8' => self.restore_cursor(),
+            b'D' => self.index(),
+            b'E' => {
+                self.x = 0;
+                self.index();
+            }
+            b'H' => self.tabs[self.x] = true,
+            b'M' => self.reverse_index(),
+            b'c' => self.reset(),
+            0x1b => self.state = State::Escape,
+            // Controls are still executed in the middle of a sequence
+            0x00..=0x1f => {
+                self.state = State::Escape;
+                self.control(byte);
+            }
+            _ => {}
+        }
+    }
+
+    fn escape_dispatch(&mut self, intermediate: u8, byte: u8) {
+        let slot = match intermediate {
+            b'(' => 0,
+            b')' => 1,
+            b'#' if byte == b'8' => {
+                // DECALN fills the screen with E
+                for line in &mut self.lines {
+                    for cell in &mut line.cells {
+                        *cell = Cell {
+                            ch: 'E',
+                            ..Cell::default()
+                        };
+                    }
+                }
+                return;
+            }
+            _ => return,
+        };
+        self.charsets[slot] = byte == b'0';
+    }
+
+    fn csi(&mut self, byte: u8) {
+        match byte {
+            b'0'..=b'9' => {
+                let param = self.params.last_mut().expect("params never empty");
+                if param.is_empty() {
+                    param.push(0);
+                }
+                let value = param.last_mut().expect("param just pushed");
+                *value = value
+                    .saturating_mul(10)
+                    .saturating_add((byte - b'0') as u16);
+            }
+            b';' => {
+                if self.params.len() < MAX_PARAMS {
+                    self.params.push(Vec::new());
+                }
+            }
+            b':' => {
+                let param = self.params.last_mut().expect("params never empty");
+                if param.is_empty() {
+                    param.push(0);
+                }
+                param.push(0);
+            }
+            b'<'..=b'?' => self.private = Some(byte),
+            b' '..=b'/' => self.intermediate = Some(byte),
+            0x40..=0x7e => {
+                self.state = State::Ground;
+                self.csi_dispatch(byte);
+            }
+            0x1b => self.state = State::Escape,
+            0x00..=0x1f => self.control(byte),
+            _ => self.state = State::Ground,
+        }
+    }
+
+    /// The first value of parameter `index`, or `default` if it is missing or zero
+    fn param(&self, index: usize, default: usize) -> usize {
+        match self.params.get(index).and_then(|param| param.first()) {
+            Some(&value) if value > 0 => value as usize,
+            _ => default,
+        }
+    }
+
+    fn csi_dispatch(&mut self, action: u8) {
+        if self.intermediate.is_some() {
+            // Cursor style, soft reset and similar sequences don't change the screen
+            if self.intermediate == Some(b'!') && action == b'p' {
+                self.soft_reset();
+            }
+            return;
+        }
+        if let Some(private) = self.private {
+            match (private, action) {
+                (b'?', b'h') => self.set_private_modes(true),
+                (b'?', b'l') => self.set_private_modes(false),
+                _ => {}
+            }
+            return;
+        }
+
+        let n = self.param(0, 1);
+        match action {
+            b'@' => self.insert_chars(n),
+            b'A' => self.cursor_up(n),
+            b'B' | b'e' => self.cursor_down(n),
+            b'C' | b'a' => self.cursor_forward(n),
+            b'D' => self.cursor_back(n),
+            b'E' => {
+                self.cursor_down(n);
+                self.x = 0;
+            }
+            b'F' => {
+                self.cursor_up(n);
+                self.x = 0;
+            }
+            b'G' | b'`' => {
+                self.x = (n - 1).min(self.cols - 1);
+                self.pending_wrap = false;
+            }
+            b'H' | b'f' => {
+                let row = self.param(0, 1) - 1;
+                let col = self.param(1, 1) - 1;
+                self.goto(col, row);
+            }
+            b'I' => self.tab_forward(n),
+            b'J' => self.erase_display(self.param(0, 0)),
+            b'K' => self.erase_line(self.param(0, 0)),
+            b'L' => self.insert_lines(n),
+            b'M' => self.delete_lines(n),
+            b'P' => self.delete_chars(n),
+            b'S' => self.scroll_up(n),
+            b'T' => self.scroll_down(n),
+            b'X' => self.erase_chars(n),
+            b'Z' => self.tab_back(n),
+            b'b' => {
+                if let Some(ch) = self.last_char {
+                    for _ in 0..n.min(self.cols * self.rows) {
+                        self.print(ch);
+                    }
+                }
+            }
+            b'd' => {
+                let col = self.x;
+                self.goto(col, n - 1);
+            }
+            b'g' => match self.param(0, 0) {
+                0 => self.tabs[self.x] = false,
+                3 => self.tabs.iter_mut().for_each(|tab| *tab = false),
+                _ => {}
+            },
+            b'h' | b'l' if self.params.iter().any(|param| param.first() == Some(&4)) => {
+                self.insert_mode = action == b'h';
+            }
+            b'm' => self.sgr(),
+            b'r' => {
+                let top = self.param(0, 1) - 1;
+                let bottom = self.param(1, self.rows).min(self.rows) - 1;
+                if top < bottom {
+                    self.scroll_top = top;
+                    self.scroll_bottom = bottom;
+                    self.goto(0, 0);
+                }
+            }
+            b's' => self.save_cursor(),
+            b'u' => self.restore_cursor(),
+            _ => {}
+        }
+    }
+
+    fn set_private_modes(&mut self, enable: bool) {
+        let modes: Vec<u16> = self
+            .params
+            .iter()
+            .filter_map(|p| p.first().copied())
+            .collect();
+        for mode in modes {
+            match mode {
+                6 => {
+                    self.origin_mode = enable;
+                    self.goto(0, 0);
+                }
+                7 => self.autowrap = enable,
+                25 => self.cursor_visible = enable,
+                47 | 1047 => self.set_alternate_screen(enable, false),
+                1048 => {
+                    if enable {
+                        self.save_cursor();
+                    } else {
+                        self.restore_cursor();
+                    }
+                }
+                1049 => self.set_alternate_screen(enable, true),
+                _ => {}
+            }
+        }
+    }
+
+    fn set_alternate_screen(&mut self, enable: bool, save_cursor: bool) {
+        if enable && self.primary.is_none() {
+            if save_cursor {
+                self.save_cursor();
+            }
+            let blank = vec![Line::blank(self.cols, &Style::default()); self.rows];
+            self.primary = Some(std::mem::replace(&mut self.lines, blank));
+        } else if !enable {
+            if let Some(primary) = self.primary.take() {
+                self.lines = primary;
+                if save_cursor {
+                    self.restore_cursor();
+                }
+            }
+        }
+    }
+
+    fn sgr(&mut self) {
+        let params = std::mem::take(&mut self.params);
+        let mut iter = params.iter().peekable();
+
+        while let Some(param) = iter.next() {
+            let code = param.first().copied().unwrap_or(0);
+            match code {
+                0 => {
+                    self.pen = Style {
+                        link: self.pen.link.take(),
+                        ..Style::default()
+                    }
+                }
+                1 => self.pen.bold = true,
+                2 => self.pen.faint = true,
+                3 => self.pen.italic = true,
+                4 => self.pen.underline = param.get(1).is_none_or(|&style| style != 0),
+                5 | 6 => self.pen.blink = true,
+                7 => self.pen.inverse = true,
+                8 => self.pen.hidden = true,
+                9 => self.pen.strikethrough = true,
+                21 => self.pen.underline = true,
+                22 => {
+                    self.pen.bold = false;
+                    self.pen.faint = false;
+                }
+                23 => self.pen.italic = false,
+                24 => self.pen.underline = false,
+                25 => self.pen.blink = false,
+                27 => self.pen.inverse = false,
+                28 => self.pen.hidden = false,
+                29 => self.pen.strikethrough = false,
+                30..=37 => self.pen.fg = Color::Indexed((code - 30) as u8),
+                38 => self.pen.fg = extended_color(param, &mut iter),
+                39 => self.pen.fg = Color::Default,
+                40..=47 => self.pen.bg = Color::Indexed((code - 40) as u8),
+                48 => self.pen.bg = extended_color(param, &mut iter),
+                49 => self.pen.bg = Color::Default,
+                90..=97 => self.pen.fg = Color::Indexed((code - 90 + 8) as u8),
+                100..=107 => self.pen.bg = Color::Indexed((code - 100 + 8) as u8),
+                _ => {}
+            }
+        }
+        self.params = params;
+    }
+
+    fn osc_dispatch(&mut self) {
+        self.state = State::Ground;
+        let osc = String::from_utf8_lossy(&std::mem::take(&mut self.osc)).into_owned();
+        let (command, rest) = osc.split_once(';').unwrap_or((&osc, ""));
+        match command {
+            "0" | "2" => self.title = Some(rest.to_string()),
+            "8" => {
+                // OSC 8 ; params ; uri
+                let uri = rest.split_once(';').map(|(_, uri)| uri).unwrap_or("");
+                self.pen.link = (!uri.is_empty()).then(|| Arc::from(uri));
+            }
+            _ => {}
+        }
+    }
+
+    fn print(&mut self, ch: char) {
+        let ch = if self.charsets[self.active_charset] {
+            dec_graphics(ch)
+        } else {
+            ch
+        };
+        let width = char_width(ch);
+        if width == 0 {
+            // Combining characters are not stored
+            return;
+        }
+        self.last_char = Some(ch);
+
+        if self.pending_wrap && self.autowrap {
+            self.lines[self.y].wrapped = true;
+            self.x = 0;
+            self.index();
+        }
+        self.pending_wrap = false;
+
+        if width == 2 && self.x == self.cols - 1 {
+            if self.autowrap && self.cols > 1 {
+                self.put(self.x, blank_cell(&self.pen));
+                self.lines[self.y].wrapped = true;
+                self.x = 0;
+                self.index();
+            } else {
+                self.x = self.cols.saturating_sub(2);
+            }
+        }
+        if width > self.cols {
+            return;
+        }
+
+        if self.insert_mode {
+            self.insert_chars(width);
+        }
+        let style = self.pen.clone();
+        self.put(
+            self.x,
+            Cell {
+                ch,
+                width: width as u8,
+                style: style.clone(),
+            },
+        );
+        if width == 2 {
+            self.put(
+                self.x + 1,
+                Cell {
+                    ch: ' ',
+                    width: 0,
+                    style,
+                },
+            );
+        }
+
+        self.x += width;
+        if self.x >= self.cols {
+            self.x = self.cols - 1;
+            self.pending_wrap = true;
+        }
+    }
+
+    /// Write a cell, blanking any wide character it cuts in half
+    fn put(&mut self, x: usize, cell: Cell) {
+        let cols = self.cols;
+        let line = &mut self.lines[self.y];
+        if line.cells[x].width == 0 && x > 0 {
+            line.cells[x - 1] = blank_cell(&line.cells[x - 1].style);
+        }
+        if line.cells[x].width == 2 && x + 1 < cols && cell.width != 2 {
+            line.cells[x + 1] = blank_cell(&line.cells[x + 1].style);
+        }
+        line.cells[x] = cell;
+    }
+
+    fn goto(&mut self, col: usize, row: usize) {
+        let (top, bottom) = if self.origin_mode {
+            (self.scroll_top, self.scroll_bottom)
+        } else {
+            (0, self.rows - 1)
+        };
+        self.x = col.min(self.cols - 1);
+        self.y = (top + row).min(bottom);
+        self.pending_wrap = false;
+    }
+
+    fn cursor_up(&mut self, n: usize) {
+        let top = if self.y >= self.scroll_top {
+            self.scroll_top
+        } else {
+            0
+        };
+        self.y = self.y.saturating_sub(n).max(top);
+        self.pending_wrap = false;
+    }
+
+    fn cursor_down(&mut self, n: usize) {
+        let bottom = if self.y <= self.scroll_bottom {
+            self.scroll_bottom
+        } else {
+            self.rows - 1
+        };
+        self.y = (self.y + n).min(bottom);
+        self.pending_wrap = false;
+    }
+
+    fn cursor_forward(&mut self, n: usize) {
+        self.x = (self.x + n).min(self.cols - 1);
+        self.pending_wrap = false;
+    }
+
+    fn cursor_back(&mut self, n: usize) {
+        self.x = self.x.saturating_sub(n);
+        self.pending_wrap = false;
+    }
+
+    fn tab_forward(&mut self, n: usize) {
+        for _ in 0..n {
+            self.x = (self.x + 1..self.cols)
+                .find(|&x| self.tabs[x])
+                .unwrap_or(self.cols - 1);
+        }
+        self.pending_wrap = false;
+    }
+
+    fn tab_back(&mut self, n: usize) {
+        for _ in 0..n {
+            self.x = (0..self.x).rev().find(|&x| self.tabs[x]).unwrap_or(0);
+        }
+        self.pending_wrap = false;
+    }
+
+    fn linefeed(&mut self) {
+        self.index();
+        if self.convert_eol {
+            self.x = 0;
+            self.pending_wrap = false;
+        }
+    }
+
+    fn index(&mut self) {
+        if self.y == self.scroll_bottom {
+            self.scroll_up(1);
+        } else if self.y < self.rows - 1 {
+            self.y += 1;
+        }
+    }
+
+    fn reverse_index(&mut self) {
+        if self.y == self.scroll_top {
+            self.scroll_down(1);
+        } else {
+            self.y = self.y.saturating_sub(1);
+        }
+    }
+
+    fn scroll_up(&mut self, n: usize) {
+        let (top, bottom) = (self.scroll_top, self.scroll_bottom);
+        let n = n.min(bottom - top + 1);
+        for _ in 0..n {
+            let line = self.lines.remove(top);
+            if top == 0 && self.primary.is_none() {
+                self.push_scrollback(line);
+            }
+            self.lines.insert(bottom, Line::blank(self.cols, &self.pen));
+        }
+    }
+
+    fn scroll_down(&mut self, n: usize) {
+        let (top, bottom) = (self.scroll_top, self.scroll_bottom);
+        let n = n.min(bottom - top + 1);
+        for _ in 0..n {
+            self.lines.remove(bottom);
+            self.lines.insert(top, Line::blank(self.cols, &self.pen));
+        }
+    }
+
+    fn insert_lines(&mut self, n: usize) {
+        if self.y < self.scroll_top || self.y > self.scroll_bottom {
+            return;
+        }
+        let n = n.min(self.scroll_bottom - self.y + 1);
+        for _ in 0..n {
+            self.lines.remove(self.scroll_bottom);
+            self.lines.insert(self.y, Line::blank(self.cols, &self.pen));
+        }
+        self.x = 0;
+        self.pending_wrap = false;
+    }
+
+    fn delete_lines(&mut self, n: usize) {
+        if self.y < self.scroll_top || self.y > self.scroll_bottom {
+            return;
+        }
+        let n = n.min(self.scroll_bottom - self.y + 1);
+        for _ in 0..n {
+            self.lines.remove(self.y);
+            self.lines
+                .insert(self.scroll_bottom, Line::blank(self.cols, &self.pen));
+        }
+        self.x = 0;
+        self.pending_wrap = false;
+    }
+
+    fn insert_chars(&mut self, n: usize) {
+        let blank = blank_cell(&self.pen);
+        let (x, cols) = (self.x, self.cols);
+        let line = &mut self.lines[self.y];
+        let n = n.min(cols - x);
+        line.cells.truncate(cols - n);
+        for _ in 0..n {
+            line.cells.insert(x, blank.clone());
+        }
+        fix_wide_edge(line);
+        self.pending_wrap = false;
+    }
+
+    fn delete_chars(&mut self, n: usize) {
+        let blank = blank_cell(&self.pen);
+        let (x, cols) = (self.x, self.cols);
+        let line = &mut self.lines[self.y];
+        let n = n.min(cols - x);
+        line.cells.drain(x..x + n);
+        line.cells.resize(cols, blank);
+        if line.cells[x].width == 0 {
+            line.cells[x] = blank_cell(&line.cells[x].style);
+        }
+        self.pending_wrap = false;
+    }
+
+    fn erase_chars(&mut self, n: usize) {
+        let end = (self.x + n).min(self.cols);
+        self.erase_cells(self.y, self.x, end);
+        self.pending_wrap = false;
+    }
+
+    fn erase_cells(&mut self, row: usize, start: usize, end: usize) {
+        let blank = blank_cell(&self.pen);
+        let line = &mut self.lines[row];
+        // Don't leave half of a wide character behind
+        if start > 0 && line.cells[start].width == 0 {
+            line.cells[start - 1] = blank.clone();
+        }
+        if end < line.cells.len() && line.cells[end].width == 0 {
+            line.cells[end] = blank.clone();
+        }
+        for cell in &mut line.cells[start..end] {
+            *cell = blank.clone();
+        }
+        if end == line.cells.len() {
+            line.wrapped = false;
+        }
+    }
+
+    fn erase_line(&mut self, mode: usize) {
+        match mode {
+            0 => self.erase_cells(self.y, self.x, self.cols),
+            1 => self.erase_cells(self.y, 0, self.x + 1),
+            2 => self.erase_cells(self.y, 0, self.cols),
+            _ => {}
+        }
+        self.pending_wrap = false;
+    }
+
+    fn erase_display(&mut self, mode: usize) {
+        match mode {
+            0 => {
+                self.erase_cells(self.y, self.x, self.cols);
+                for row in self.y + 1..self.rows {
+                    self.erase_cells(row, 0, self.cols);
+                }
+            }
+            1 => {
+                for row in 0..self.y {
+                    self.erase_cells(row, 0, self.cols);
+                }
+                self.erase_cells(self.y, 0, self.x + 1);
+            }
+            2 => {
+                for row in 0..self.rows {
+                    self.erase_cells(row, 0, self.cols);
+                }
+            }
+            3 => self.scrollback.clear(),
+            _ => {}
+        }
+        self.pending_wrap = false;
+    }
+
+    fn save_cursor(&mut self) {
+        self.saved = SavedCursor {
+            x: self.x,
+            y: self.y,
+            pen: self.pen.clone(),
+            origin_mode: self.origin_mode,
+            charsets: self.charsets,
+            active_charset: self.active_charset,
+        };
+    }
+
+    fn restore_cursor(&mut self) {
+        let saved = self.saved.clone();
+        self.x = saved.x.min(self.cols - 1);
+        self.y = saved.y.min(self.rows - 1);
+        self.pen = saved.pen;
+        self.origin_mode = saved.origin_mode;
+        self.charsets = saved.charsets;
+        self.active_charset = saved.active_charset;
+        self.pending_wrap = false;
+    }
+
+    fn soft_reset(&mut self) {
+        self.pen = Style::default();
+        self.cursor_visible = true;
+        self.autowrap = true;
+        self.origin_mode = false;
+        self.insert_mode = false;
+        self.scroll_top = 0;
+        self.scroll_bottom = self.rows - 1;
+        self.charsets = [false; 2];
+        self.active_charset = 0;
+    }
+
+    fn reset(&mut self) {
+        let mut terminal = Terminal::new(self.cols as u16, self.rows as u16);
+        terminal.scrollback = std::mem::take(&mut self.scrollback);
+        terminal.scrollback_limit = self.scrollback_limit;
+        terminal.convert_eol = self.convert_eol;
+        *self = terminal;
+    }
+
+    fn push_scrollback(&mut self, line: Line) {
+        if self.scrollback_limit == 0 {
+            return;
+        }
+        self.scrollback.push_back(line);
+        self.trim_scrollback();
+    }
+
+    fn trim_scrollback(&mut self) {
+        while self.scrollback.len() > self.scrollback_limit {
+            self.scrollback.pop_front();
+        }
+    }
+}
+
+/// Draw lines from the top of the screen, leaving the pen reset
+fn draw_lines(out: &mut String, lines: &[Line]) {
+    let blank = Style::default();
+    for (y, line) in lines.iter().enumerate() {
+        let Some(end) = line
+            .cells
+            .iter()
+            .rposition(|cell| cell.ch != ' ' || cell.style != blank)
+        else {
+            continue;
+        };
+        out.push_str(&format!("\x1b[{};1H", y + 1));
+        let mut style = &blank;
+        for cell in line.cells[..=end].iter().filter(|cell| cell.width > 0) {
+            if &cell.style != style {
+                out.push_str(&sgr_sequence(&cell.style));
+                if cell.style.link != style.link {
+                    let link = cell.style.link.as_deref().unwrap_or("");
+                    out.push_str(&format!("\x1b]8;;{}\x1b\\", link));
+                }
+                style = &cell.style;
+            }
+            out.push(cell.ch);
+        }
+        if style.link.is_some() {
+            out.push_str("\x1b]8;;\x1b\\");
+        }
+        out.push_str("\x1b[0m");
+    }
+}
+
+/// The SGR sequence that sets exactly the attributes of `style`
+fn sgr_sequence(style: &Style) -> String {
+    let mut codes = vec!["0".to_string()];
+    for (enabled, code) in [
+        (style.bold, "1"),
+        (style.faint, "2"),
+        (style.italic, "3"),
+        (style.underline, "4"),
+        (style.blink, "5"),
+        (style.inverse, "7"),
+        (style.hidden, "8"),
+        (style.strikethrough, "9"),
+    ] {
+        if enabled {
+            codes.push(code.to_string());
+        }
+    }
+    for (color, base) in [(style.fg, 30), (style.bg, 40)] {
+        match color {
+            Color::Default => {}
+            Color::Indexed(n) if n < 8 => codes.push((base + n as u16).to_string()),
+            Color::Indexed(n) if n < 16 => codes.push((base + 60 + n as u16 - 8).to_string()),
+            Color::Indexed(n) => codes.push(format!("{};5;{}", base + 8, n)),
+            Color::Rgb(r, g, b) => codes.push(format!("{};2;{};{};{}", base + 8, r, g, b)),
+        }
+    }
+    format!("\x1b[{}m", codes.join(";"))
+}
+
+/// Tab stops every eight columns
+fn default_tabs(cols: usize) -> Vec<bool> {
+    (0..cols).map(|x| x > 0 && x % 8 == 0).collect()
+}
+
+/// Blank a wide character cut in half by the right edge of the line
+fn fix_wide_edge(line: &mut Line) {
+    if let Some(last) = line.cells.last_mut() {
+        if last.width == 2 {
+            *last = blank_cell(&last.style);
+        }
+    }
+    if line.cells.first().is_some_and(|cell| cell.width == 0) {
+        line.cells[0] = blank_cell(&line.cells[0].style);
+    }
+}
+
+/// Length of a UTF-8 sequence from its first byte, or 0 if it can't start one
+fn utf8_len(byte: u8) -> usize {
+    match byte {
+        0xc2..=0xdf => 2,
+        0xe0..=0xef => 3,
+        0xf0..=0xf4 => 4,
+        _ => 0,
+    }
+}
+
+/// Parse the color following SGR 38 or 48, in either `;` or `:` form
+fn extended_color<'a>(
+    param: &[u16],
+    rest: &mut std::iter::Peekable<impl Iterator<Item = &'a Vec<u16>>>,
+) -> Color {
+    if param.len() > 1 {
+        // 38:5:n or 38:2:[colorspace:]r:g:b
+        return match param[1] {
+            5 => param
+                .get(2)
+                .map_or(Color::Default, |&n| Color::Indexed(n as u8)),
+            2 => {
+                let rgb = &param[2..];
+                let rgb = if rgb.len() >= 4 { &rgb[1..] } else { rgb };
+                match rgb {
+                    [r, g, b, ..] => Color::Rgb(*r as u8, *g as u8, *b as u8),
+                    _ => Color::Default,
+                }
+            }
+            _ => Color::Default,
+        };
+    }
+
+    let mut next = || rest.next().and_then(|p| p.first().copied()).unwrap_or(0);
+    match next() {
+        5 => Color::Indexed(next() as u8),
+        2 => {
+            let (r, g, b) = (next(), next(), next());
+            Color::Rgb(r as u8, g as u8, b as u8)
+        }
+        _ => Color::Default,
+    }
+}
+
+/// Map a character through the DEC special graphics set
+fn dec_graphics(ch: char) -> char {
+    match ch {
+        '`' => '◆',
+        'a' => '▒',
+        'f' => '°',
+        'g' => '±',
+        'j' => '┘',
+        'k' => '┐',
+        'l' => '┌',
+        'm' => '└',
+        'n' => '┼',
+        'o' => '⎺',
+        'p' => '⎻',
+        'q' => '─',
+        'r' => '⎼',
+        's' => '⎽',
+        't' => '├',
+        'u' => '┤',
+        'v' => '┴',
+        'w' => '┬',
+        'x' => '│',
+        'y' => '≤',
+        'z' => '≥',
+        '{' => 'π',
+        '|' => '≠',
+        '}' => '£',
+        '~' => '·',
+        _ => ch,
+    }
+}
+
+/// Number of columns a character occupies
+///
+/// A compact approximation of `wcwidth`: combining marks and zero width
+/// characters take no space, East Asian wide and fullwidth characters and
+/// most emoji take two columns.
+pub fn char_width(ch: char) -> usize {
+    let c = ch as u32;
+    match c {
+        0x0300..=0x036f
+        | 0x0483..=0x0489
+        | 0x0591..=0x05bd
+        | 0x0610..=0x061a
+        | 0x064b..=0x065f
+        | 0x200b..=0x200f
+        | 0x20d0..=0x20ff
+        | 0xfe00..=0xfe0f
+        | 0xfe20..=0xfe2f => 0,
+        0x1100..=0x115f
+        | 0x231a..=0x231b
+        | 0x2329..=0x232a
+        | 0x23e9..=0x23ec
+        | 0x23f0
+        | 0x23f3
+        | 0x25fd..=0x25fe
+        | 0x2614..=0x2615
+        | 0x2648..=0x2653
+        | 0x267f
+        | 0x2693
+        | 0x26a1
+        | 0x26aa..=0x26ab
+        | 0x26bd..=0x26be
+        | 0x26c4..=0x26c5
+        | 0x26ce
+        | 0x26d4
+        | 0x26ea
+        | 0x26f2..=0x26f3
+        | 0x26f5
+        | 0x26fa
+        | 0x26fd
+        | 0x2705
+        | 0x270a..=0x270b
+        | 0x2728
+        | 0x274c
+        | 0x274e
+        | 0x2753..=0x2755
+        | 0x2757
+        | 0x2795..=0x2797
+        | 0x27b0
+        | 0x27bf
+        | 0x2b1b..=0x2b1c
+        | 0x2b50
+        | 0x2b55
+        | 0x2e80..=0x303e
+        | 0x3041..=0x33ff
+        | 0x3400..=0x4dbf
+        | 0x4e00..=0x9fff
+        | 0xa000..=0xa4cf
+        | 0xa960..=0xa97f
+        | 0xac00..=0xd7a3
+        | 0xf900..=0xfaff
+        | 0xfe10..=0xfe19
+        | 0xfe30..=0xfe6f
+        | 0xff00..=0xff60
+        | 0xffe0..=0xffe6
+        | 0x1f004
+        | 0x1f0cf
+        | 0x1f18e
+        | 0x1f191..=0x1f19a
+        | 0x1f200..=0x1f251
+        | 0x1f300..=0x1f64f
+        | 0x1f680..=0x1f6ff
+        | 0x1f7e0..=0x1f7eb
+        | 0x1f90c..=0x1f9ff
+        | 0x1fa70..=0x1faff
+        | 0x20000..=0x3fffd => 2,
+        _ => 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn terminal(cols: u16, rows: u16, data: &str) -> Terminal {
+        let mut terminal = Terminal::new(cols, rows);
+        terminal.feed(data.as_bytes());
+        terminal
+    }
+
+    #[test]
+    fn test_text_and_wrapping() {
+        let term = terminal(5, 4, "hello world\r\nab");
+        assert_eq!(term.screen_text(), "hello\n worl\nd\nab");
+        assert!(term.lines()[0].wrapped);
+        assert_eq!(term.scrollback().count(), 0);
+
+        let mut term = terminal(5, 2, "1\r\n2\r\n3\r\n4");
+        assert_eq!(term.screen_text(), "3\n4");
+        let scrolled: Vec<_> = term.scrollback().map(Line::text).collect();
+        assert_eq!(scrolled, vec!["1", "2"]);
+        let taken: Vec<_> = term.take_scrollback().iter().map(Line::text).collect();
+        assert_eq!(taken, scrolled);
+        assert_eq!(term.scrollback().count(), 0);
+
+        let mut term = terminal(5, 2, "ab\ncd");
+        assert_eq!(term.screen_text(), "ab\n  cd");
+        term.set_convert_eol(true);
+        term.feed(b"\nef");
+        assert_eq!(term.screen_text(), "  cd\nef");
+    }
+
+    #[test]
+    fn test_cursor_movement_and_erase() {
+        let term = terminal(10, 3, "abcdef\x1b[3D\x1b[K\x1b[2;4Hxy\x1b[1;1H\x1b[2P");
+        assert_eq!(term.screen_text(), "c\n   xy");
+        assert_eq!(term.cursor(), (0, 0));
+
+        let term = terminal(10, 3, "line1\r\nline2\x1b[2J");
+        assert_eq!(term.screen_text(), "");
+    }
+
+    #[test]
+    fn test_sgr_colors() {
+        let term = terminal(
+            20,
+            1,
+            "\x1b[1;31ma\x1b[38;5;200mb\x1b[48:2::1:2:3mc\x1b[0md",
+        );
+        let cells = &term.lines()[0].cells;
+        assert_eq!(cells[0].style.fg, Color::Indexed(1));
+        assert!(cells[0].style.bold);
+        assert_eq!(cells[1].style.fg, Color::Indexed(200));
+        assert_eq!(cells[2].style.bg, Color::Rgb(1, 2, 3));
+        assert_eq!(cells[3].style, Style::default());
+    }
+
+    #[test]
+    fn test_wide_characters_and_utf8() {
+        let mut term = Terminal::new(6, 2);
+        let bytes = "中文ab".as_bytes();
+        // Split in the middle of a character
+        term.feed(&bytes[..2]);
+        term.feed(&bytes[2..]);
+        assert_eq!(term.screen_text(), "中文ab");
+        assert_eq!(term.lines()[0].cells[1].width, 0);
+        assert_eq!(term.cursor(), (5, 0));
+
+        // Overwriting half of a wide character blanks the other half
+        term.feed(b"\x1b[1;2Hx");
+        assert_eq!(term.screen_text(), " x文ab");
+    }
+
+    #[test]
+    fn test_alternate_screen_and_scroll_region() {
+        let term = terminal(10, 3, "shell\x1b[?1049hfull screen\x1b[?1049l");
+        assert_eq!(term.screen_text(), "shell");
+        assert!(!term.alternate_screen());
+
+        let term = terminal(10, 4, "top\x1b[2;3r\x1b[2;1Ha\r\nb\r\nc\x1b[r");
+        assert_eq!(term.screen_text(), "top\nb\nc");
+    }
+
+    #[test]
+    fn test_osc_title_and_hyperlink() {
+        let term = terminal(
+            20,
+            1,
+            "\x1b]0;my title\x07\x1b]8;;https://example.com\x1b\\link\x1b]8;;\x1b\\ x",
+        );
+        assert_eq!(term.title(), Some("my title"));
+        let cells = &term.lines()[0].cells;
+        assert_eq!(cells[0].style.link.as_deref(), Some("https://example.com"));
+        assert_eq!(cells[5].style.link, None);
+        assert_eq!(term.screen_text(), "link x");
+    }
+
+    #[test]
+    fn test_line_drawing_and_ignored_strings() {
+        let term = terminal(10, 1, "\x1b(0lqk\x1b(B\x1bP1$r\x1b\\x");
+        assert_eq!(term.screen_text(), "┌─┐x");
+    }
+
+    #[test]
+    fn test_to_ansi_recreates_state() {
+        let source = terminal(
+            12,
+            4,
+            "\x1b]2;build\x07plain \x1b[1;31mred\x1b[0m\r\n\x1b[48;5;200m \x1b[0m\
+             \x1b]8;;https://example.com\x07link\x1b]8;;\x07 \x1b[38;2;1;2;3m\
+             \x1b[?1049h\x1b[3;4Hfull\x1b[2;3r\x1b[3;2H\x1b[?25l",
+        );
+        let mut copy = terminal(12, 4, "junk\r\nto clear");
+        copy.feed(&source.to_ansi());
+
+        assert_eq!(copy.lines(), source.lines());
+        assert_eq!(copy.cursor(), source.cursor());
+        assert_eq!(copy.title(), Some("build"));
+        assert!(copy.alternate_screen());
+        assert!(!copy.cursor_visible());
+        // The pen carries over to output that follows
+        copy.feed(b"x");
+        let mut source = source;
+        source.feed(b"x");
+        assert_eq!(copy.lines(), source.lines());
+
+        // Leaving the alternate screen shows the same primary screen
+        copy.feed(b"\x1b[?1049l");
+        source.feed(b"\x1b[?1049l");
+        assert_eq!(copy.lines(), source.lines());
+        assert_eq!(copy.cursor(), source.cursor());
+    }
+
+    #[test]
+    fn test_resize() {
+        let mut term = terminal(10, 4, "a\r\nb\r\nc\r\nd");
+        term.resize(5, 2);
+        assert_eq!(term.screen_text(), "c\nd");
+        assert_eq!(term.cursor(), (1, 1));
+
+        term.resize(8, 3);
+        assert_eq!(term.lines()[0].cells.len(), 8);
+        assert_eq!(term.screen_text(), "c\nd");
+    }
+}
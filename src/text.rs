@@ -0,0 +1,326 @@
+//! Plain-text export
+//!
+//! Raw typescripts are full of carriage returns, cursor movement and screen
+//! clears, so progress bars and redrawn prompts turn into noise when the
+//! bytes are pasted into a ticket. Here the session is played through the
+//! built-in [`Terminal`] emulator instead and only the text that ended up on
+//! screen is written, either as the final screen with its scrollback or as a
+//! transcript of the lines in the order they were completed.
+
+use crate::session::{Event, EventKind, Session, DEFAULT_HEIGHT, DEFAULT_WIDTH};
+use crate::terminal::{Line, Terminal, DEFAULT_SCROLLBACK};
+use anyhow::{anyhow, Result};
+use std::io::Write;
+use std::time::Duration;
+
+/// What text is written
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TextMode {
+    /// The scrollback followed by the final screen, one row per line, as
+    /// the terminal shows them once the session has ended
+    #[default]
+    Screen,
+    /// Every line of the primary screen once it is complete, with wrapped
+    /// rows joined and runs of blank lines collapsed. Full-screen programs
+    /// on the alternate screen leave nothing behind, as in a terminal's
+    /// scrollback. Lines are written as they scroll away rather than kept
+    /// until the end.
+    Transcript,
+}
+
+/// Options for [`export`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextOptions {
+    /// What text is written
+    pub mode: TextMode,
+    /// Terminal width; defaults to the recorded width
+    pub cols: Option<u16>,
+    /// Terminal height; defaults to the recorded height
+    pub rows: Option<u16>,
+    /// Most scrollback lines kept in [`TextMode::Screen`]
+    pub scrollback: usize,
+    /// Recording time to start at; lines that scrolled away before it are
+    /// left out
+    pub start: Option<Duration>,
+    /// Recording time to stop at
+    pub end: Option<Duration>,
+}
+
+impl Default for TextOptions {
+    fn default() -> Self {
+        Self {
+            mode: TextMode::default(),
+            cols: None,
+            rows: None,
+            scrollback: DEFAULT_SCROLLBACK,
+            start: None,
+            end: None,
+        }
+    }
+}
+
+/// Write the text a session leaves on the terminal
+pub fn export<W: Write>(session: &Session, mut writer: W, options: &TextOptions) -> Result<()> {
+    let cols = options
+        .cols
+        .or(session.metadata.width)
+        .unwrap_or(DEFAULT_WIDTH)
+        .max(1);
+    let rows = options
+        .rows
+        .or(session.metadata.height)
+        .unwrap_or(DEFAULT_HEIGHT)
+        .max(1);
+
+    let mut terminal = Terminal::new(cols, rows);
+    terminal.set_convert_eol(true);
+    // A transcript takes the scrollback after every event, so nothing is
+    // dropped however much a single event scrolls
+    terminal.set_scrollback_limit(match options.mode {
+        TextMode::Screen => options.scrollback,
+        TextMode::Transcript => usize::MAX,
+    });
+    let mut transcript = Transcript::new(&mut writer);
+
+    let start = options.start.unwrap_or_default();
+    let mut started = false;
+    for event in &session.events {
+        if options.end.is_some_and(|end| event.time > end) {
+            break;
+        }
+        if !started && event.time >= start {
+            started = true;
+            terminal.take_scrollback();
+        }
+
+        match &event.kind {
+            EventKind::Output(data) => terminal.feed(data),
+            EventKind::Resize { cols, rows } => terminal.resize(*cols, *rows),
+            _ => continue,
+        }
+        if options.mode == TextMode::Transcript {
+            let lines = terminal.take_scrollback();
+            if started {
+                for line in &lines {
+                    transcript.push(line)?;
+                }
+            }
+        }
+    }
+    if !started {
+        terminal.take_scrollback();
+    }
+
+    match options.mode {
+        TextMode::Screen => {
+            let mut lines: Vec<String> = terminal
+                .take_scrollback()
+                .iter()
+                .chain(terminal.lines())
+                .map(Line::text)
+                .collect();
+            while lines.last().is_some_and(|line| line.is_empty()) {
+                lines.pop();
+            }
+            for line in lines {
+                writeln!(writer, "{}", line).map_err(|e| anyhow!("Failed to write text: {}", e))?;
+            }
+        }
+        TextMode::Transcript => {
+            // What is left on the primary screen completes the transcript
+            if terminal.alternate_screen() {
+                terminal.feed(b"\x1b[?1047l");
+            }
+            let (_, cursor_row) = terminal.cursor();
+            let mut lines = terminal.lines().to_vec();
+            while lines.len() > cursor_row as usize + 1
+                && lines.last().is_some_and(|line| line.text().is_empty())
+            {
+                lines.pop();
+            }
+            for line in &lines {
+                transcript.push(line)?;
+            }
+            transcript.finish()?;
+        }
+    }
+
+    writer
+        .flush()
+        .map_err(|e| anyhow!("Failed to write text: {}", e))
+}
+
+/// Render a block of output on a blank terminal and return its transcript lines
+pub(crate) fn transcript_lines(data: &[u8], cols: u16, rows: u16) -> Result<Vec<String>> {
+    let mut session = Session::new();
+    session.events.push(Event::output(Duration::ZERO, data));
+    let options = TextOptions {
+        mode: TextMode::Transcript,
+        cols: Some(cols),
+        rows: Some(rows),
+        ..TextOptions::default()
+    };
+    let mut text = Vec::new();
+    export(&session, &mut text, &options)?;
+    Ok(String::from_utf8_lossy(&text)
+        .lines()
+        .map(str::to_string)
+        .collect())
+}
+
+/// Joins wrapped rows into lines and collapses blank lines as they arrive
+struct Transcript<'a, W: Write> {
+    writer: &'a mut W,
+    /// Text of a line whose rows wrapped and continue on the next row
+    partial: String,
+    /// Whether a blank line is waiting for the next line with text
+    blank: bool,
+    /// Whether any line has been written yet
+    written: bool,
+}
+
+impl<'a, W: Write> Transcript<'a, W> {
+    fn new(writer: &'a mut W) -> Self {
+        Self {
+            writer,
+            partial: String::new(),
+            blank: false,
+            written: false,
+        }
+    }
+
+    fn push(&mut self, line: &Line) -> Result<()> {
+        if line.wrapped {
+            // Spaces before the wrap point are part of the text
+            self.partial.extend(
+                line.cells
+                    .iter()
+                    .filter(|cell| cell.width > 0)
+                    .map(|cell| cell.ch),
+            );
+            return Ok(());
+        }
+
+        self.partial.push_str(&line.text());
+        let text = std::mem::take(&mut self.partial);
+        let text = text.trim_end();
+        if text.is_empty() {
+            self.blank = self.written;
+            return Ok(());
+        }
+        if self.blank {
+            writeln!(self.writer).map_err(|e| anyhow!("Failed to write text: {}", e))?;
+            self.blank = false;
+        }
+        self.written = true;
+        writeln!(self.writer, "{}", text).map_err(|e| anyhow!("Failed to write text: {}", e))
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        let text = std::mem::take(&mut self.partial);
+        let text = text.trim_end();
+        if text.is_empty() {
+            return Ok(());
+        }
+        if self.blank {
+            writeln!(self.writer).map_err(|e| anyhow!("Failed to write text: {}", e))?;
+        }
+        writeln!(self.writer, "{}", text).map_err(|e| anyhow!("Failed to write text: {}", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(cols: u16, rows: u16, output: &[&str]) -> Session {
+        let mut session = Session::new();
+        session.metadata.width = Some(cols);
+        session.metadata.height = Some(rows);
+        session.events = output
+            .iter()
+            .enumerate()
+            .map(|(i, data)| Event::output(Duration::from_secs(i as u64), *data))
+            .collect();
+        session
+    }
+
+    fn export_text(session: &Session, options: &TextOptions) -> String {
+        let mut text = Vec::new();
+        export(session, &mut text, options).unwrap();
+        String::from_utf8(text).unwrap()
+    }
+
+    #[test]
+    fn test_screen_with_scrollback() {
+        let session = session(
+            20,
+            3,
+            &[
+                "Downloading\r\n",
+                "10%\r50%\r100%\r\n",
+                "one\r\ntwo\r\n\x1b[31mthree\x1b[0m\r\n",
+            ],
+        );
+        let options = TextOptions::default();
+        assert_eq!(
+            export_text(&session, &options),
+            "Downloading\n100%\none\ntwo\nthree\n"
+        );
+
+        let options = TextOptions {
+            scrollback: 1,
+            ..TextOptions::default()
+        };
+        assert_eq!(export_text(&session, &options), "one\ntwo\nthree\n");
+
+        // A full-screen program still showing is what the viewer saw last
+        let session = session_with_alternate_screen();
+        assert_eq!(
+            export_text(&session, &TextOptions::default()),
+            "$ top\nCPU 3%\n"
+        );
+    }
+
+    #[test]
+    fn test_transcript() {
+        let session = session(
+            5,
+            3,
+            &[
+                "hello world\r\n\r\n\r\n\r\n",
+                "a\r\nb\r\n\x1b[?1049h\x1b[2Jfull\r\nscreen",
+                "\x1b[?1049l$ ",
+            ],
+        );
+        let options = TextOptions {
+            mode: TextMode::Transcript,
+            ..TextOptions::default()
+        };
+        assert_eq!(export_text(&session, &options), "hello world\n\na\nb\n$\n");
+
+        let options = TextOptions {
+            mode: TextMode::Transcript,
+            start: Some(Duration::from_secs(1)),
+            ..TextOptions::default()
+        };
+        assert_eq!(export_text(&session, &options), "a\nb\n$\n");
+
+        let options = TextOptions {
+            mode: TextMode::Transcript,
+            ..TextOptions::default()
+        };
+        assert_eq!(
+            export_text(&session_with_alternate_screen(), &options),
+            "shell\n"
+        );
+    }
+
+    fn session_with_alternate_screen() -> Session {
+        session(
+            20,
+            4,
+            &["shell\r\n", "\x1b[?1049h\x1b[H$ top\r\nCPU 1%\x1b[2;5H3%"],
+        )
+    }
+}
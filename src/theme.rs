@@ -0,0 +1,218 @@
+//! Color themes for rendered exports
+//!
+//! A [`Theme`] turns the colors of a terminal cell into concrete RGB values.
+//! The 16 ANSI colors, foreground and background come from the theme; the
+//! rest of the 256 color palette is the standard xterm color cube and
+//! grayscale ramp.
+
+use crate::terminal::{Color, Style};
+
+/// A 24-bit color
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+impl Rgb {
+    /// The color as a CSS hex string such as `#1e1e1e`
+    pub fn hex(&self) -> String {
+        format!("#{:02x}{:02x}{:02x}", self.0, self.1, self.2)
+    }
+
+    /// Mix two colors; `amount` is the share of `other`, from 0.0 to 1.0
+    pub fn blend(&self, other: Rgb, amount: f64) -> Rgb {
+        let mix = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * amount).round() as u8;
+        Rgb(
+            mix(self.0, other.0),
+            mix(self.1, other.1),
+            mix(self.2, other.2),
+        )
+    }
+
+    fn from_hex(value: u32) -> Rgb {
+        Rgb((value >> 16) as u8, (value >> 8) as u8, value as u8)
+    }
+}
+
+/// Colors used to render a terminal
+#[derive(Debug, Clone, PartialEq)]
+pub struct Theme {
+    /// Default text color
+    pub foreground: Rgb,
+    /// Default background color
+    pub background: Rgb,
+    /// The 16 ANSI colors: black, red, green, yellow, blue, magenta, cyan,
+    /// white, then their bright variants
+    pub palette: [Rgb; 16],
+}
+
+/// The built-in themes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum BuiltinTheme {
+    /// Light gray on near black
+    #[default]
+    Dark,
+    /// Dark gray on white
+    Light,
+    /// Solarized with its dark background
+    SolarizedDark,
+    /// Solarized with its light background
+    SolarizedLight,
+    /// Dracula
+    Dracula,
+    /// Monokai
+    Monokai,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::builtin(BuiltinTheme::default())
+    }
+}
+
+impl Theme {
+    /// One of the built-in themes
+    pub fn builtin(name: BuiltinTheme) -> Theme {
+        let (foreground, background, palette): (u32, u32, [u32; 16]) = match name {
+            BuiltinTheme::Dark => (
+                0xcccccc,
+                0x121314,
+                [
+                    0x000000, 0xdd3c69, 0x4ebf22, 0xddaf3c, 0x26b0d7, 0xb954e1, 0x54e1b9, 0xd9d9d9,
+                    0x4d4d4d, 0xdd3c69, 0x4ebf22, 0xddaf3c, 0x26b0d7, 0xb954e1, 0x54e1b9, 0xffffff,
+                ],
+            ),
+            BuiltinTheme::Light => (
+                0x1e1e1e,
+                0xffffff,
+                [
+                    0x000000, 0xcd3131, 0x00bc00, 0x949800, 0x0451a5, 0xbc05bc, 0x0598bc, 0x555555,
+                    0x666666, 0xcd3131, 0x14ce14, 0xb5ba00, 0x0451a5, 0xbc05bc, 0x0598bc, 0xa5a5a5,
+                ],
+            ),
+            BuiltinTheme::SolarizedDark | BuiltinTheme::SolarizedLight => {
+                let (foreground, background) = if name == BuiltinTheme::SolarizedDark {
+                    (0x839496, 0x002b36)
+                } else {
+                    (0x657b83, 0xfdf6e3)
+                };
+                (
+                    foreground,
+                    background,
+                    [
+                        0x073642, 0xdc322f, 0x859900, 0xb58900, 0x268bd2, 0xd33682, 0x2aa198,
+                        0xeee8d5, 0x002b36, 0xcb4b16, 0x586e75, 0x657b83, 0x839496, 0x6c71c4,
+                        0x93a1a1, 0xfdf6e3,
+                    ],
+                )
+            }
+            BuiltinTheme::Dracula => (
+                0xf8f8f2,
+                0x282a36,
+                [
+                    0x21222c, 0xff5555, 0x50fa7b, 0xf1fa8c, 0xbd93f9, 0xff79c6, 0x8be9fd, 0xf8f8f2,
+                    0x6272a4, 0xff6e6e, 0x69ff94, 0xffffa5, 0xd6acff, 0xff92df, 0xa4ffff, 0xffffff,
+                ],
+            ),
+            BuiltinTheme::Monokai => (
+                0xf8f8f2,
+                0x272822,
+                [
+                    0x272822, 0xf92672, 0xa6e22e, 0xf4bf75, 0x66d9ef, 0xae81ff, 0xa1efe4, 0xf8f8f2,
+                    0x75715e, 0xf92672, 0xa6e22e, 0xf4bf75, 0x66d9ef, 0xae81ff, 0xa1efe4, 0xf9f8f5,
+                ],
+            ),
+        };
+
+        Theme {
+            foreground: Rgb::from_hex(foreground),
+            background: Rgb::from_hex(background),
+            palette: palette.map(Rgb::from_hex),
+        }
+    }
+
+    /// Resolve one of the 256 indexed colors
+    pub fn indexed(&self, index: u8) -> Rgb {
+        match index {
+            0..=15 => self.palette[index as usize],
+            16..=231 => {
+                let level = |value: u8| if value == 0 { 0 } else { 55 + value * 40 };
+                let index = index - 16;
+                Rgb(level(index / 36), level(index / 6 % 6), level(index % 6))
+            }
+            _ => {
+                let gray = 8 + (index - 232) * 10;
+                Rgb(gray, gray, gray)
+            }
+        }
+    }
+
+    /// The foreground and background colors a cell is drawn with
+    ///
+    /// Bold text in one of the eight basic colors uses the bright variant,
+    /// faint text is blended towards the background, and inverse and hidden
+    /// text are applied.
+    pub fn cell_colors(&self, style: &Style) -> (Rgb, Rgb) {
+        let mut foreground = match style.fg {
+            Color::Default => self.foreground,
+            Color::Indexed(index @ 0..=7) if style.bold => self.palette[index as usize + 8],
+            Color::Indexed(index) => self.indexed(index),
+            Color::Rgb(r, g, b) => Rgb(r, g, b),
+        };
+        let mut background = match style.bg {
+            Color::Default => self.background,
+            Color::Indexed(index) => self.indexed(index),
+            Color::Rgb(r, g, b) => Rgb(r, g, b),
+        };
+
+        if style.inverse {
+            std::mem::swap(&mut foreground, &mut background);
+        }
+        if style.faint {
+            foreground = foreground.blend(background, 0.4);
+        }
+        if style.hidden {
+            foreground = background;
+        }
+        (foreground, background)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_themes() {
+        assert_eq!(Theme::default().background.hex(), "#121314");
+        assert_eq!(
+            Theme::builtin(BuiltinTheme::SolarizedLight)
+                .background
+                .hex(),
+            "#fdf6e3"
+        );
+        assert_ne!(
+            Theme::builtin(BuiltinTheme::Dracula),
+            Theme::builtin(BuiltinTheme::Monokai)
+        );
+    }
+
+    #[test]
+    fn test_cell_colors() {
+        let theme = Theme::default();
+        assert_eq!(theme.indexed(16), Rgb(0, 0, 0));
+        assert_eq!(theme.indexed(231), Rgb(255, 255, 255));
+        assert_eq!(theme.indexed(196), Rgb(255, 0, 0));
+        assert_eq!(theme.indexed(244), Rgb(128, 128, 128));
+
+        let style = Style {
+            fg: Color::Indexed(1),
+            bold: true,
+            inverse: true,
+            ..Style::default()
+        };
+        assert_eq!(
+            theme.cell_colors(&style),
+            (theme.background, theme.palette[9])
+        );
+    }
+}
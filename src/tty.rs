@@ -0,0 +1,218 @@
+//! Keyboard input from the controlling terminal
+//!
+//! Interactive playback reads single key presses, so the terminal is put in
+//! raw mode: no line buffering, no echo, and Ctrl+C arrives as a key instead
+//! of a signal so the player can restore the terminal before it exits.
+//! [`RawMode`] puts the original settings back when it is dropped.
+//! [`Interrupts`] turns termination signals into a flag that playback checks,
+//! so it can stop and clean up instead of being killed mid-screen.
+
+use anyhow::{anyhow, Result};
+use std::io::{IsTerminal, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+/// Set when a signal caught by [`Interrupts`] arrives
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// The terminal in raw mode, restored when dropped
+pub(crate) struct RawMode {
+    #[cfg(unix)]
+    original: libc::termios,
+}
+
+#[cfg(unix)]
+impl RawMode {
+    /// Put the terminal on stdin in raw mode
+    pub(crate) fn enable() -> Result<Self> {
+        // SAFETY: termios is plain data, and both calls only read or write
+        // the struct passed to them
+        unsafe {
+            let mut original: libc::termios = std::mem::zeroed();
+            if libc::tcgetattr(libc::STDIN_FILENO, &mut original) != 0 {
+                return Err(anyhow!(
+                    "Failed to read terminal settings: {}",
+                    std::io::Error::last_os_error()
+                ));
+            }
+            let mut raw = original;
+            raw.c_lflag &= !(libc::ICANON | libc::ECHO | libc::ISIG | libc::IEXTEN);
+            raw.c_iflag &= !(libc::IXON | libc::ICRNL);
+            raw.c_cc[libc::VMIN] = 1;
+            raw.c_cc[libc::VTIME] = 0;
+            if libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &raw) != 0 {
+                return Err(anyhow!(
+                    "Failed to put the terminal in raw mode: {}",
+                    std::io::Error::last_os_error()
+                ));
+            }
+            Ok(Self { original })
+        }
+    }
+
+    /// Wait up to `timeout` for a key press and return its byte
+    pub(crate) fn read_key(&self, timeout: Duration) -> Result<Option<u8>> {
+        let mut poll = libc::pollfd {
+            fd: libc::STDIN_FILENO,
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        let timeout = timeout.as_millis().min(i32::MAX as u128) as i32;
+        // SAFETY: poll gets one valid pollfd and read a one-byte buffer
+        unsafe {
+            match libc::poll(&mut poll, 1, timeout) {
+                0 => Ok(None),
+                n if n < 0 => {
+                    let error = std::io::Error::last_os_error();
+                    if error.kind() == std::io::ErrorKind::Interrupted {
+                        Ok(None)
+                    } else {
+                        Err(anyhow!("Failed to wait for input: {}", error))
+                    }
+                }
+                _ => {
+                    let mut key = 0u8;
+                    match libc::read(libc::STDIN_FILENO, (&mut key as *mut u8).cast(), 1) {
+                        1 => Ok(Some(key)),
+                        0 => Err(anyhow!("Input closed")),
+                        _ => Err(anyhow!(
+                            "Failed to read input: {}",
+                            std::io::Error::last_os_error()
+                        )),
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        // SAFETY: restores the settings read in `enable`
+        unsafe {
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &self.original);
+        }
+    }
+}
+
+#[cfg(not(unix))]
+impl RawMode {
+    pub(crate) fn enable() -> Result<Self> {
+        Err(anyhow!("Interactive playback needs a Unix terminal"))
+    }
+
+    pub(crate) fn read_key(&self, _timeout: Duration) -> Result<Option<u8>> {
+        Ok(None)
+    }
+}
+
+/// An output stream that may be a terminal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Stream {
+    Stdout,
+    Stderr,
+}
+
+impl Stream {
+    /// A handle to write to the stream
+    pub(crate) fn writer(self) -> Box<dyn Write> {
+        match self {
+            Stream::Stdout => Box::new(std::io::stdout()),
+            Stream::Stderr => Box::new(std::io::stderr()),
+        }
+    }
+
+    pub(crate) fn is_terminal(self) -> bool {
+        match self {
+            Stream::Stdout => std::io::stdout().is_terminal(),
+            Stream::Stderr => std::io::stderr().is_terminal(),
+        }
+    }
+
+    /// The size of the terminal on the stream as `(columns, rows)`
+    pub(crate) fn size(self) -> Option<(u16, u16)> {
+        #[cfg(unix)]
+        {
+            let fd = match self {
+                Stream::Stdout => libc::STDOUT_FILENO,
+                Stream::Stderr => libc::STDERR_FILENO,
+            };
+            // SAFETY: TIOCGWINSZ fills in the winsize passed to it
+            unsafe {
+                let mut size: libc::winsize = std::mem::zeroed();
+                if libc::ioctl(fd, libc::TIOCGWINSZ, &mut size) == 0
+                    && size.ws_col > 0
+                    && size.ws_row > 0
+                {
+                    return Some((size.ws_col, size.ws_row));
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Whether a signal caught by [`Interrupts`] has arrived
+pub(crate) fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+/// SIGINT, SIGTERM and SIGHUP caught instead of ending the process, until
+/// dropped
+pub(crate) struct Interrupts {
+    #[cfg(unix)]
+    previous: Vec<(libc::c_int, libc::sighandler_t)>,
+}
+
+#[cfg(unix)]
+extern "C" fn on_signal(_signal: libc::c_int) {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+}
+
+impl Interrupts {
+    /// Catch termination signals, clearing any earlier interruption
+    pub(crate) fn catch() -> Self {
+        INTERRUPTED.store(false, Ordering::SeqCst);
+        #[cfg(unix)]
+        {
+            let handler = on_signal as extern "C" fn(libc::c_int) as libc::sighandler_t;
+            let previous = [libc::SIGINT, libc::SIGTERM, libc::SIGHUP]
+                .into_iter()
+                // SAFETY: the handler only stores to an atomic
+                .map(|signal| (signal, unsafe { libc::signal(signal, handler) }))
+                .collect();
+            Self { previous }
+        }
+        #[cfg(not(unix))]
+        Self {}
+    }
+}
+
+impl Drop for Interrupts {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        for &(signal, handler) in &self.previous {
+            // SAFETY: puts back the handler that was installed before
+            unsafe {
+                libc::signal(signal, handler);
+            }
+        }
+        // The interruption belonged to this playback; later calls start afresh
+        INTERRUPTED.store(false, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interruption_ends_with_the_guard() {
+        let interrupts = Interrupts::catch();
+        INTERRUPTED.store(true, Ordering::SeqCst);
+        assert!(interrupted());
+        drop(interrupts);
+        assert!(!interrupted());
+    }
+}
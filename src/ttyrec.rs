@@ -0,0 +1,364 @@
+//! ttyrec support
+//!
+//! ttyrec is a binary format made of frames. Each frame starts with a 12 byte
+//! header of three little-endian 32-bit unsigned integers, followed by the
+//! frame data:
+//!
+//! ```text
+//! sec   seconds of the wall clock time the data was written
+//! usec  microseconds within that second
+//! len   number of data bytes that follow
+//! ```
+//!
+//! Times are absolute, so a [`Session`] read from a ttyrec file starts at the
+//! first frame and keeps that frame's time in [`Metadata::timestamp`]. Only
+//! output is stored; input, resize and marker events are dropped on write.
+
+use crate::gzip;
+use crate::session::{
+    create_file, open_file, Event, EventKind, EventStream, EventWriter, Metadata, Session,
+    SessionFiles, SessionFormat,
+};
+use anyhow::{anyhow, Result};
+use std::io::{ErrorKind, Read, Write};
+use std::path::Path;
+use std::time::Duration;
+
+/// Size of a frame header in bytes
+const HEADER_LEN: usize = 12;
+
+/// Number of frames checked when sniffing a file
+const SNIFF_FRAMES: usize = 16;
+
+/// The ttyrec format as a [`SessionFormat`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TtyrecFormat;
+
+impl SessionFormat for TtyrecFormat {
+    fn name(&self) -> &'static str {
+        "ttyrec"
+    }
+
+    fn read(&self, files: &SessionFiles) -> Result<Session> {
+        read(open_file(&files.data, "ttyrec")?)
+    }
+
+    fn write(&self, session: &Session, files: &SessionFiles) -> Result<()> {
+        write(session, create_file(&files.data, "output")?)
+    }
+
+    fn events(&self, files: &SessionFiles) -> Result<EventStream> {
+        let mut frames = TtyrecReader::new(open_file(&files.data, "ttyrec")?);
+        // The recording's start time is only known from its first frame
+        let first = frames.next();
+        let metadata = Metadata {
+            timestamp: frames.start().map(|start| start.as_secs()),
+            ..Metadata::default()
+        };
+        Ok(EventStream::new(metadata, first.into_iter().chain(frames)))
+    }
+
+    fn writer(&self, metadata: &Metadata, files: &SessionFiles) -> Result<Box<dyn EventWriter>> {
+        Ok(Box::new(TtyrecWriter::new(
+            create_file(&files.data, "output")?,
+            metadata,
+        )))
+    }
+}
+
+/// Read a ttyrec recording into a [`Session`]
+pub fn read<R: Read>(reader: R) -> Result<Session> {
+    let mut frames = TtyrecReader::new(reader);
+    let mut session = Session::new();
+    for event in frames.by_ref() {
+        session.events.push(event?);
+    }
+    session.metadata.timestamp = frames.start().map(|start| start.as_secs());
+    Ok(session)
+}
+
+/// Write the output events of a [`Session`] as a ttyrec recording
+pub fn write<W: Write>(session: &Session, writer: W) -> Result<()> {
+    let mut writer = TtyrecWriter::new(writer, &session.metadata);
+    for event in &session.events {
+        writer.write_event(event)?;
+    }
+    writer.finish()?;
+    Ok(())
+}
+
+/// Check whether a file looks like a ttyrec recording
+///
+/// The first few frame headers must be consistent: microseconds below one
+/// second, time never going backwards and lengths that fit in the file.
+pub fn is_ttyrec(path: impl AsRef<Path>) -> bool {
+    let path = path.as_ref();
+    // The length of a compressed recording is only known once it is read
+    let size = match gzip::is_compressed(path) {
+        true => u64::MAX,
+        false => match std::fs::metadata(path) {
+            Ok(metadata) => metadata.len(),
+            Err(_) => return false,
+        },
+    };
+    let Ok(reader) = gzip::open(path) else {
+        return false;
+    };
+    looks_like_ttyrec(reader, size)
+}
+
+/// Check whether a stream of at most `size` bytes looks like a ttyrec
+/// recording
+pub(crate) fn looks_like_ttyrec<R: Read>(mut reader: R, size: u64) -> bool {
+    let mut position = 0u64;
+    let mut last = Duration::ZERO;
+    for _ in 0..SNIFF_FRAMES {
+        if position == size {
+            break;
+        }
+        let (time, len) = match read_header(&mut reader) {
+            Ok(Some(header)) => header,
+            // A short recording whose length was not known up front
+            Ok(None) if position > 0 => break,
+            _ => return false,
+        };
+        position += HEADER_LEN as u64 + len as u64;
+        if time < last || position > size {
+            return false;
+        }
+        last = time;
+
+        // Without a known size, a frame must still be there in full
+        match std::io::copy(&mut (&mut reader).take(len as u64), &mut std::io::sink()) {
+            Ok(copied) if copied == len as u64 => {}
+            _ => return false,
+        }
+    }
+    position > 0
+}
+
+/// Lazily reads output events from a ttyrec stream
+///
+/// Event times are relative to the first frame. A frame cut off by the end
+/// of the stream ends the iteration, which is how recordings interrupted
+/// mid-write usually look.
+pub struct TtyrecReader<R: Read> {
+    reader: R,
+    start: Option<Duration>,
+    last: Duration,
+    done: bool,
+}
+
+impl<R: Read> TtyrecReader<R> {
+    /// Create a reader over a ttyrec stream
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            start: None,
+            last: Duration::ZERO,
+            done: false,
+        }
+    }
+
+    /// Wall clock time of the first frame, once it has been read
+    pub fn start(&self) -> Option<Duration> {
+        self.start
+    }
+
+    fn read_event(&mut self) -> Result<Option<Event>> {
+        let Some((time, len)) = read_header(&mut self.reader)? else {
+            return Ok(None);
+        };
+
+        // Read through `take` so a corrupt length can't force a huge allocation
+        let mut data = Vec::new();
+        (&mut self.reader)
+            .take(len as u64)
+            .read_to_end(&mut data)
+            .map_err(|e| anyhow!("Failed to read ttyrec frame: {}", e))?;
+        if data.len() < len {
+            return Ok(None);
+        }
+
+        let start = *self.start.get_or_insert(time);
+        // Clocks can step backwards; keep events in order
+        let time = time.saturating_sub(start).max(self.last);
+        self.last = time;
+        Ok(Some(Event::output(time, data)))
+    }
+}
+
+impl<R: Read> Iterator for TtyrecReader<R> {
+    type Item = Result<Event>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let event = self.read_event().transpose();
+        if !matches!(event, Some(Ok(_))) {
+            self.done = true;
+        }
+        event
+    }
+}
+
+/// Read a frame header, returning `None` at the end of the stream
+fn read_header<R: Read>(reader: &mut R) -> Result<Option<(Duration, usize)>> {
+    let mut header = [0u8; HEADER_LEN];
+    let mut filled = 0;
+    while filled < HEADER_LEN {
+        match reader.read(&mut header[filled..]) {
+            Ok(0) => return Ok(None),
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return Err(anyhow!("Failed to read ttyrec header: {}", e)),
+        }
+    }
+
+    let field = |index: usize| {
+        let bytes = &header[index * 4..index * 4 + 4];
+        u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+    };
+    let (sec, usec, len) = (field(0), field(1), field(2));
+    if usec >= 1_000_000 {
+        return Err(anyhow!("Invalid ttyrec header: usec {} out of range", usec));
+    }
+
+    Ok(Some((Duration::new(sec as u64, usec * 1000), len as usize)))
+}
+
+/// Incremental ttyrec writer
+///
+/// Frame times are the event times added to [`Metadata::timestamp`], or to
+/// the Unix epoch when the recording has no timestamp.
+#[derive(Debug)]
+pub struct TtyrecWriter<W: Write> {
+    writer: W,
+    start: Duration,
+}
+
+impl<W: Write> TtyrecWriter<W> {
+    /// Create a writer; ttyrec has no header so nothing is written yet
+    pub fn new(writer: W, metadata: &Metadata) -> Self {
+        Self {
+            writer,
+            start: Duration::from_secs(metadata.timestamp.unwrap_or_default()),
+        }
+    }
+
+    /// Append an event; only output is stored
+    pub fn write_event(&mut self, event: &Event) -> Result<()> {
+        let EventKind::Output(data) = &event.kind else {
+            return Ok(());
+        };
+
+        let time = self.start + event.time;
+        let sec = u32::try_from(time.as_secs())
+            .map_err(|_| anyhow!("Time {}s does not fit in a ttyrec header", time.as_secs()))?;
+        let len = u32::try_from(data.len())
+            .map_err(|_| anyhow!("Frame of {} bytes is too large for ttyrec", data.len()))?;
+
+        let mut header = [0u8; HEADER_LEN];
+        header[..4].copy_from_slice(&sec.to_le_bytes());
+        header[4..8].copy_from_slice(&time.subsec_micros().to_le_bytes());
+        header[8..].copy_from_slice(&len.to_le_bytes());
+
+        self.writer
+            .write_all(&header)
+            .and_then(|_| self.writer.write_all(data))
+            .map_err(|e| anyhow!("Failed to write ttyrec frame: {}", e))
+    }
+
+    /// Flush the underlying writer and return it
+    pub fn finish(mut self) -> Result<W> {
+        self.writer
+            .flush()
+            .map_err(|e| anyhow!("Failed to write ttyrec frame: {}", e))?;
+        Ok(self.writer)
+    }
+}
+
+impl<W: Write> EventWriter for TtyrecWriter<W> {
+    fn write_event(&mut self, event: &Event) -> Result<()> {
+        TtyrecWriter::write_event(self, event)
+    }
+
+    fn finish(self: Box<Self>) -> Result<()> {
+        TtyrecWriter::finish(*self).map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(sec: u32, usec: u32, data: &[u8]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend(sec.to_le_bytes());
+        bytes.extend(usec.to_le_bytes());
+        bytes.extend((data.len() as u32).to_le_bytes());
+        bytes.extend(data);
+        bytes
+    }
+
+    #[test]
+    fn test_read_ttyrec() -> Result<()> {
+        let mut bytes = frame(1_700_000_000, 500_000, b"Hello ");
+        bytes.extend(frame(1_700_000_001, 250_000, b"World\r\n"));
+
+        let session = read(bytes.as_slice())?;
+        assert_eq!(session.metadata.timestamp, Some(1_700_000_000));
+        assert_eq!(
+            session.events,
+            vec![
+                Event::output(Duration::ZERO, "Hello "),
+                Event::output(Duration::from_millis(750), "World\r\n"),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_ttyrec_round_trip() -> Result<()> {
+        let mut session = Session::new();
+        session.metadata.timestamp = Some(1_700_000_000);
+        session.events = vec![
+            Event::output(Duration::from_micros(1_500), "one"),
+            Event {
+                time: Duration::from_millis(2),
+                kind: EventKind::Marker("skipped".to_string()),
+            },
+            Event::output(Duration::from_millis(1_250), "two"),
+        ];
+
+        let mut bytes = Vec::new();
+        write(&session, &mut bytes)?;
+        assert_eq!(&bytes[..12], &frame(1_700_000_000, 1_500, b"one")[..12]);
+
+        let read_back = read(bytes.as_slice())?;
+        let output: Vec<_> = read_back.output().collect();
+        assert_eq!(
+            output,
+            vec![
+                (Duration::ZERO, b"one".as_slice()),
+                (Duration::from_micros(1_248_500), b"two".as_slice()),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_truncated_and_invalid_frames() {
+        let mut bytes = frame(10, 0, b"complete");
+        bytes.extend(&frame(11, 0, b"cut off")[..15]);
+        let session = read(bytes.as_slice()).unwrap();
+        assert_eq!(session.events.len(), 1);
+
+        let invalid = frame(10, 2_000_000, b"bad");
+        assert!(read(invalid.as_slice())
+            .unwrap_err()
+            .to_string()
+            .contains("usec"));
+    }
+}
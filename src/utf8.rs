@@ -0,0 +1,144 @@
+//! Incremental UTF-8 decoding
+//!
+//! Terminal output arrives in arbitrarily sized chunks, so a multibyte
+//! character can easily be split across two reads. Decoding each chunk on its
+//! own with [`String::from_utf8_lossy`] turns such a character into replacement
+//! characters. [`Utf8Decoder`] keeps the incomplete tail of a chunk and
+//! prepends it to the next one instead.
+
+/// A stateful UTF-8 decoder that carries incomplete sequences across chunks
+///
+/// Invalid bytes are replaced with U+FFFD exactly like
+/// [`String::from_utf8_lossy`]; only a truncated sequence at the very end of a
+/// chunk is held back until more input arrives or [`Utf8Decoder::finish`] is
+/// called.
+///
+/// # Example
+///
+/// ```rust
+/// use replay_rs::Utf8Decoder;
+///
+/// let mut decoder = Utf8Decoder::new();
+/// let bytes = "中".as_bytes();
+/// assert_eq!(decoder.decode(&bytes[..2]), "");
+/// assert_eq!(decoder.decode(&bytes[2..]), "中");
+/// assert_eq!(decoder.finish(), "");
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct Utf8Decoder {
+    pending: Vec<u8>,
+}
+
+impl Utf8Decoder {
+    /// Create a decoder with no pending input
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Decode the next chunk of bytes
+    ///
+    /// Returns all text that could be decoded so far. A trailing incomplete
+    /// sequence is kept and completed by the next call.
+    pub fn decode(&mut self, chunk: &[u8]) -> String {
+        let mut input = std::mem::take(&mut self.pending);
+        input.extend_from_slice(chunk);
+
+        let mut output = String::with_capacity(input.len());
+        let mut rest = input.as_slice();
+
+        loop {
+            match std::str::from_utf8(rest) {
+                Ok(valid) => {
+                    output.push_str(valid);
+                    break;
+                }
+                Err(e) => {
+                    let (valid, after) = rest.split_at(e.valid_up_to());
+                    // `valid_up_to` guarantees this prefix is valid UTF-8
+                    output.push_str(std::str::from_utf8(valid).unwrap_or_default());
+
+                    match e.error_len() {
+                        Some(len) => {
+                            output.push(char::REPLACEMENT_CHARACTER);
+                            rest = &after[len..];
+                        }
+                        None => {
+                            // Incomplete sequence at the end, wait for more input
+                            self.pending.extend_from_slice(after);
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+
+        output
+    }
+
+    /// Flush any pending bytes at end of input
+    ///
+    /// A sequence that was never completed is emitted as U+FFFD.
+    pub fn finish(&mut self) -> String {
+        if self.pending.is_empty() {
+            return String::new();
+        }
+        self.pending.clear();
+        char::REPLACEMENT_CHARACTER.to_string()
+    }
+
+    /// Returns true if an incomplete sequence is waiting for more input
+    pub fn has_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_ascii_passthrough() {
+        let mut decoder = Utf8Decoder::new();
+        assert_eq!(decoder.decode(b"Hello"), "Hello");
+        assert_eq!(decoder.decode(b", World"), ", World");
+        assert!(!decoder.has_pending());
+        assert_eq!(decoder.finish(), "");
+    }
+
+    #[test]
+    fn test_decode_split_multibyte_characters() {
+        let text = "中文 🎬 ñ";
+        let bytes = text.as_bytes();
+
+        // Feed one byte at a time, the worst possible split
+        let mut decoder = Utf8Decoder::new();
+        let mut output = String::new();
+        for byte in bytes {
+            output.push_str(&decoder.decode(std::slice::from_ref(byte)));
+        }
+        output.push_str(&decoder.finish());
+
+        assert_eq!(output, text);
+        assert!(!output.contains(char::REPLACEMENT_CHARACTER));
+    }
+
+    #[test]
+    fn test_decode_invalid_bytes_are_replaced() {
+        let mut decoder = Utf8Decoder::new();
+        assert_eq!(decoder.decode(b"a\xffb"), "a\u{FFFD}b");
+        assert_eq!(
+            decoder.decode(b"\xc3\x28"),
+            String::from_utf8_lossy(b"\xc3\x28")
+        );
+    }
+
+    #[test]
+    fn test_finish_flushes_truncated_sequence() {
+        let mut decoder = Utf8Decoder::new();
+        assert_eq!(decoder.decode(&"🎬".as_bytes()[..3]), "");
+        assert!(decoder.has_pending());
+        assert_eq!(decoder.finish(), "\u{FFFD}");
+        assert!(!decoder.has_pending());
+        assert_eq!(decoder.finish(), "");
+    }
+}
@@ -25,6 +25,13 @@ fn cleanup_files(files: &[&str]) {
     }
 }
 
+/// Helper function to create a unique temporary directory for a test's files
+fn test_dir(base: &str) -> String {
+    let dir = std::env::temp_dir().join(test_file_name(base));
+    fs::create_dir_all(&dir).unwrap();
+    dir.to_string_lossy().into_owned()
+}
+
 /// Get the path to a compiled binary
 fn binary_path(name: &str) -> String {
     let mut path = std::env::current_exe().unwrap();
@@ -70,7 +77,7 @@ fn test_recorder_basic_recording() {
     let timing_file = format!("{}.timing", output_file);
 
     let output = Command::new(binary_path("recorder"))
-        .args(&[
+        .args([
             "echo",
             "Binary recorder test",
             "--output",
@@ -109,7 +116,7 @@ fn test_recorder_with_verbose_flag() {
     let output_file = test_file_name("binary_recorder_verbose.log");
 
     let output = Command::new(binary_path("recorder"))
-        .args(&[
+        .args([
             "echo",
             "Verbose test",
             "--output",
@@ -133,7 +140,7 @@ fn test_recorder_plain_text_flag() {
     let output_file = test_file_name("binary_recorder_plain.log");
 
     let output = Command::new(binary_path("recorder"))
-        .args(&[
+        .args([
             "echo",
             "Plain text test",
             "--output",
@@ -156,7 +163,7 @@ fn test_recorder_multiple_arguments() {
     let output_file = test_file_name("binary_recorder_args.log");
 
     let output = Command::new(binary_path("recorder"))
-        .args(&[
+        .args([
             "printf",
             "Line 1\\nLine 2\\nLine 3",
             "--output",
@@ -216,6 +223,25 @@ fn test_player_missing_files() {
     assert!(stderr.contains("not found") || stderr.contains("No such file"));
 }
 
+#[test]
+fn test_player_unknown_format() {
+    let notes_file = test_file_name("binary_notes.txt");
+    fs::write(&notes_file, "Not a recording\n").unwrap();
+
+    let output = Command::new(binary_path("player"))
+        .args([&notes_file, "--dump"])
+        .output()
+        .expect("Failed to execute player");
+
+    // The error explains what was looked for
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Unrecognized session format"));
+    assert!(stderr.contains(&format!("{}.timing", notes_file)));
+
+    cleanup_files(&[&notes_file]);
+}
+
 #[test]
 fn test_recorder_and_player_integration() {
     let output_file = test_file_name("binary_integration.log");
@@ -223,7 +249,7 @@ fn test_recorder_and_player_integration() {
 
     // First, record a session
     let record_output = Command::new(binary_path("recorder"))
-        .args(&[
+        .args([
             "echo",
             "Integration test between recorder and player",
             "--output",
@@ -240,7 +266,7 @@ fn test_recorder_and_player_integration() {
 
     // Then, play it back with dump mode (faster for testing)
     let play_output = Command::new(binary_path("player"))
-        .args(&[&output_file, "--timing", &timing_file, "--dump"])
+        .args([&output_file, "--timing", &timing_file, "--dump"])
         .output()
         .expect("Failed to execute player");
 
@@ -258,7 +284,7 @@ fn test_player_speed_parameter() {
 
     // Record a session
     let _record_output = Command::new(binary_path("recorder"))
-        .args(&[
+        .args([
             "echo",
             "Speed test",
             "--output",
@@ -271,7 +297,7 @@ fn test_player_speed_parameter() {
 
     // Play back at different speeds
     let play_output = Command::new(binary_path("player"))
-        .args(&[
+        .args([
             &output_file,
             "--timing",
             &timing_file,
@@ -309,7 +335,7 @@ fn test_replay_record_subcommand() {
     let timing_file = format!("{}.timing", output_file);
 
     let output = Command::new(binary_path("replay"))
-        .args(&[
+        .args([
             "record",
             "echo",
             "Replay record test",
@@ -338,7 +364,7 @@ fn test_replay_play_subcommand() {
 
     // First record with replay
     let _record_output = Command::new(binary_path("replay"))
-        .args(&[
+        .args([
             "record",
             "echo",
             "Replay play test",
@@ -352,7 +378,7 @@ fn test_replay_play_subcommand() {
 
     // Then play back with replay
     let play_output = Command::new(binary_path("replay"))
-        .args(&["play", &output_file, "--timing", &timing_file, "--dump"])
+        .args(["play", &output_file, "--timing", &timing_file, "--dump"])
         .output()
         .expect("Failed to execute replay play");
 
@@ -368,7 +394,7 @@ fn test_replay_record_with_verbose() {
     let output_file = test_file_name("binary_replay_verbose.log");
 
     let output = Command::new(binary_path("replay"))
-        .args(&[
+        .args([
             "record",
             "echo",
             "Verbose replay test",
@@ -395,7 +421,7 @@ fn test_replay_play_with_verbose() {
 
     // Record first
     let _record_output = Command::new(binary_path("replay"))
-        .args(&[
+        .args([
             "record",
             "echo",
             "Verbose play test",
@@ -409,7 +435,7 @@ fn test_replay_play_with_verbose() {
 
     // Play with verbose
     let play_output = Command::new(binary_path("replay"))
-        .args(&[
+        .args([
             "play",
             &output_file,
             "--timing",
@@ -459,7 +485,7 @@ fn test_recorder_default_timing_file() {
     let expected_timing_file = format!("{}.timing", output_file);
 
     let output = Command::new(binary_path("recorder"))
-        .args(&["echo", "Default timing test", "--output", &output_file])
+        .args(["echo", "Default timing test", "--output", &output_file])
         .output()
         .expect("Failed to execute recorder");
 
@@ -477,7 +503,7 @@ fn test_player_default_timing_file() {
 
     // Record first
     let _record_output = Command::new(binary_path("recorder"))
-        .args(&[
+        .args([
             "echo",
             "Player default timing test",
             "--output",
@@ -490,7 +516,7 @@ fn test_player_default_timing_file() {
 
     // Play without specifying timing file (should use default)
     let play_output = Command::new(binary_path("player"))
-        .args(&[&output_file, "--dump"])
+        .args([&output_file, "--dump"])
         .output()
         .expect("Failed to execute player with default timing");
 
@@ -504,7 +530,7 @@ fn test_error_handling_nonexistent_command() {
     let output_file = test_file_name("binary_nonexistent.log");
 
     let output = Command::new(binary_path("recorder"))
-        .args(&[
+        .args([
             "nonexistent_command_should_fail_12345",
             "--output",
             &output_file,
@@ -525,7 +551,7 @@ fn test_complex_command_with_pipes_and_redirects() {
 
     // Test recording a command with multiple arguments
     let output = Command::new(binary_path("recorder"))
-        .args(&[
+        .args([
             "sh",
             "-c",
             "echo 'First line' && echo 'Second line'",
@@ -543,3 +569,640 @@ fn test_complex_command_with_pipes_and_redirects() {
 
     cleanup_files(&[&output_file, &format!("{}.timing", output_file)]);
 }
+
+#[test]
+fn test_replay_record_and_play_asciicast() {
+    let cast_file = format!("{}.cast", test_file_name("binary_asciicast"));
+
+    // The .cast extension selects the asciicast format
+    let output = Command::new(binary_path("replay"))
+        .args(["record", "echo", "Asciicast test", "--output", &cast_file])
+        .output()
+        .expect("Failed to execute replay record");
+
+    assert!(output.status.success());
+    assert!(!Path::new(&format!("{}.timing", cast_file)).exists());
+
+    let recorded_content = fs::read_to_string(&cast_file).unwrap();
+    assert!(recorded_content.starts_with("{\"version\": 2"));
+    assert!(recorded_content.contains("Asciicast test"));
+
+    let output = Command::new(binary_path("player"))
+        .args([&cast_file, "--dump"])
+        .output()
+        .expect("Failed to execute player");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Asciicast test"));
+
+    cleanup_files(&[&cast_file]);
+}
+
+#[test]
+fn test_replay_record_and_play_container() {
+    let container_file = format!("{}.replay", test_file_name("binary_container"));
+
+    // The .replay extension selects the single-file container
+    let output = Command::new(binary_path("replay"))
+        .args([
+            "record",
+            "echo",
+            "Container test",
+            "--output",
+            &container_file,
+        ])
+        .output()
+        .expect("Failed to execute replay record");
+
+    assert!(output.status.success());
+    assert!(!Path::new(&format!("{}.timing", container_file)).exists());
+
+    let output = Command::new(binary_path("replay"))
+        .args(["play", &container_file, "--dump", "--verbose"])
+        .output()
+        .expect("Failed to execute replay play");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("File format: container"));
+    assert!(stdout.contains("Container test"));
+
+    cleanup_files(&[&container_file]);
+}
+
+#[test]
+fn test_replay_convert_ttyrec_and_typescript() {
+    let output_file = test_file_name("binary_convert.log");
+    let timing_file = format!("{}.timing", output_file);
+    let ttyrec_file = format!("{}.ttyrec", output_file);
+    let back_file = format!("{}.back", output_file);
+    let back_timing = format!("{}.timing", back_file);
+
+    let output = Command::new(binary_path("recorder"))
+        .args(["echo", "Convert test", "--output", &output_file])
+        .output()
+        .expect("Failed to execute recorder");
+    assert!(output.status.success());
+
+    // typescript/timing -> ttyrec
+    let output = Command::new(binary_path("replay"))
+        .args(["convert", &output_file, &ttyrec_file])
+        .output()
+        .expect("Failed to execute replay convert");
+    assert!(output.status.success());
+    assert!(Path::new(&ttyrec_file).exists());
+
+    // ttyrec -> typescript/timing
+    let output = Command::new(binary_path("replay"))
+        .args([
+            "convert",
+            &ttyrec_file,
+            &back_file,
+            "--format",
+            "scriptreplay",
+        ])
+        .output()
+        .expect("Failed to execute replay convert");
+    assert!(output.status.success());
+
+    assert_eq!(
+        fs::read(&output_file).unwrap(),
+        fs::read(&back_file).unwrap()
+    );
+    assert!(Path::new(&back_timing).exists());
+
+    cleanup_files(&[
+        &output_file,
+        &timing_file,
+        &ttyrec_file,
+        &back_file,
+        &back_timing,
+    ]);
+}
+
+#[test]
+fn test_replay_convert_directory() {
+    let input_dir = test_file_name("binary_convert_dir_in");
+    let output_dir = test_file_name("binary_convert_dir_out");
+    fs::create_dir_all(&input_dir).unwrap();
+
+    for name in ["first", "second"] {
+        let cast_file = format!("{}/{}.cast", input_dir, name);
+        let output = Command::new(binary_path("replay"))
+            .args(["record", "echo", name, "--output", &cast_file])
+            .output()
+            .expect("Failed to execute replay record");
+        assert!(output.status.success());
+    }
+    // Files that aren't sessions are skipped
+    fs::write(format!("{}/notes.txt", input_dir), "not a session\n").unwrap();
+
+    // A directory needs an explicit output format
+    let output = Command::new(binary_path("replay"))
+        .args(["convert", &input_dir, &output_dir])
+        .output()
+        .expect("Failed to execute replay convert");
+    assert!(!output.status.success());
+
+    let output = Command::new(binary_path("replay"))
+        .args(["convert", &input_dir, &output_dir, "--to", "ttyrec"])
+        .output()
+        .expect("Failed to execute replay convert");
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Converted 2 sessions"));
+
+    for name in ["first", "second"] {
+        let ttyrec_file = format!("{}/{}.ttyrec", output_dir, name);
+        let output = Command::new(binary_path("replay"))
+            .args(["play", &ttyrec_file, "--dump"])
+            .output()
+            .expect("Failed to execute replay play");
+        assert!(output.status.success());
+        assert!(String::from_utf8_lossy(&output.stdout).contains(name));
+    }
+    assert!(!Path::new(&format!("{}/notes.ttyrec", output_dir)).exists());
+
+    // With --from every file is read as that format
+    let back_dir = test_file_name("binary_convert_dir_back");
+    let output = Command::new(binary_path("replay"))
+        .args([
+            "convert",
+            &output_dir,
+            &back_dir,
+            "--from",
+            "ttyrec",
+            "--to",
+            "asciicast",
+        ])
+        .output()
+        .expect("Failed to execute replay convert");
+    assert!(output.status.success());
+    assert!(Path::new(&format!("{}/first.cast", back_dir)).exists());
+    assert!(Path::new(&format!("{}/second.cast", back_dir)).exists());
+
+    for dir in [&input_dir, &output_dir, &back_dir] {
+        fs::remove_dir_all(dir).unwrap_or(());
+    }
+}
+
+#[test]
+fn test_replay_export_gif() {
+    let dir = test_dir("binary_export");
+    let cast_file = format!("{}/session.cast", dir);
+    let gif_file = format!("{}/session.gif", dir);
+
+    let output = Command::new(binary_path("replay"))
+        .args(["record", "echo", "Export test", "--output", &cast_file])
+        .output()
+        .expect("Failed to execute replay record");
+    assert!(output.status.success());
+
+    let output = Command::new(binary_path("replay"))
+        .args([
+            "export", &cast_file, "--gif", &gif_file, "--theme", "monokai",
+        ])
+        .output()
+        .expect("Failed to execute replay export");
+    assert!(output.status.success());
+    let gif = fs::read(&gif_file).unwrap();
+    assert!(gif.starts_with(b"GIF89a"));
+
+    // An export needs an output format and a known theme
+    let output = Command::new(binary_path("replay"))
+        .args(["export", &cast_file])
+        .output()
+        .expect("Failed to execute replay export");
+    assert!(!output.status.success());
+    let output = Command::new(binary_path("replay"))
+        .args(["export", &cast_file, "--gif", &gif_file, "--theme", "nope"])
+        .output()
+        .expect("Failed to execute replay export");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("invalid value 'nope'"));
+
+    fs::remove_dir_all(&dir).unwrap_or(());
+}
+
+#[test]
+fn test_replay_export_svg() {
+    let dir = test_dir("binary_export_svg");
+    let cast_file = format!("{}/session.cast", dir);
+    let svg_file = format!("{}/session.svg", dir);
+
+    let output = Command::new(binary_path("replay"))
+        .args(["record", "echo", "SVG export", "--output", &cast_file])
+        .output()
+        .expect("Failed to execute replay record");
+    assert!(output.status.success());
+
+    let output = Command::new(binary_path("replay"))
+        .args([
+            "export", &cast_file, "--svg", &svg_file, "--window", "--from", "0", "--to", "10",
+        ])
+        .output()
+        .expect("Failed to execute replay export");
+    assert!(output.status.success());
+    let svg = fs::read_to_string(&svg_file).unwrap();
+    assert!(svg.contains("SVG export"));
+
+    let output = Command::new(binary_path("replay"))
+        .args(["export", &cast_file, "--svg", &svg_file, "--from", "-1"])
+        .output()
+        .expect("Failed to execute replay export");
+    assert!(!output.status.success());
+
+    fs::remove_dir_all(&dir).unwrap_or(());
+}
+
+#[test]
+fn test_replay_export_html() {
+    let dir = test_dir("binary_export_html");
+    let cast_file = format!("{}/session.cast", dir);
+    let html_file = format!("{}/session.html", dir);
+
+    let output = Command::new(binary_path("replay"))
+        .args(["record", "echo", "HTML export", "--output", &cast_file])
+        .output()
+        .expect("Failed to execute replay record");
+    assert!(output.status.success());
+
+    let output = Command::new(binary_path("replay"))
+        .args(["export", &cast_file, "--html", &html_file])
+        .output()
+        .expect("Failed to execute replay export");
+    assert!(output.status.success());
+    let html = fs::read_to_string(&html_file).unwrap();
+    assert!(html.contains("HTML export"));
+    assert!(html.contains("replay-play"));
+
+    fs::remove_dir_all(&dir).unwrap_or(());
+}
+
+#[test]
+fn test_replay_export_text() {
+    let dir = test_dir("binary_export_text");
+    let cast_file = format!("{}/session.cast", dir);
+    let text_file = format!("{}/session.txt", dir);
+
+    let output = Command::new(binary_path("replay"))
+        .args([
+            "record",
+            "printf",
+            "10%%\\r100%%\\ndone\\n",
+            "--output",
+            &cast_file,
+        ])
+        .output()
+        .expect("Failed to execute replay record");
+    assert!(output.status.success());
+
+    // "-" writes the text to stdout
+    let output = Command::new(binary_path("replay"))
+        .args(["export", &cast_file, "--text", "-", "--transcript"])
+        .output()
+        .expect("Failed to execute replay export");
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "100%\ndone\n");
+
+    let output = Command::new(binary_path("replay"))
+        .args(["export", &cast_file, "--text", &text_file])
+        .output()
+        .expect("Failed to execute replay export");
+    assert!(output.status.success());
+    assert_eq!(fs::read_to_string(&text_file).unwrap(), "100%\ndone\n");
+
+    fs::remove_dir_all(&dir).unwrap_or(());
+}
+
+#[test]
+fn test_replay_export_markdown() {
+    let dir = test_dir("binary_export_markdown");
+    let cast_file = format!("{}/session.cast", dir);
+    let markdown_file = format!("{}/session.md", dir);
+
+    let output = Command::new(binary_path("replay"))
+        .args([
+            "record",
+            "printf",
+            "$ make\\nok\\n$ make test\\nall passed\\n",
+            "--output",
+            &cast_file,
+        ])
+        .output()
+        .expect("Failed to execute replay record");
+    assert!(output.status.success());
+
+    let output = Command::new(binary_path("replay"))
+        .args(["export", &cast_file, "--markdown", &markdown_file])
+        .output()
+        .expect("Failed to execute replay export");
+    assert!(output.status.success());
+    assert_eq!(
+        fs::read_to_string(&markdown_file).unwrap(),
+        "```sh\nmake\n```\n\n```text\nok\n```\n\n\
+         ```sh\nmake test\n```\n\n```text\nall passed\n```\n\n"
+    );
+
+    fs::remove_dir_all(&dir).unwrap_or(());
+}
+
+#[test]
+fn test_replay_play_without_terminal() {
+    let cast_file = format!("{}.cast", test_file_name("binary_play_controls"));
+
+    let output = Command::new(binary_path("replay"))
+        .args(["record", "echo", "No terminal", "--output", &cast_file])
+        .output()
+        .expect("Failed to execute replay record");
+    assert!(output.status.success());
+
+    // Without a terminal on stdin playback falls back to a timed replay
+    for args in [
+        vec!["play", &cast_file],
+        vec!["play", &cast_file, "--no-controls"],
+    ] {
+        let output = Command::new(binary_path("replay"))
+            .args(&args)
+            .stdin(std::process::Stdio::null())
+            .output()
+            .expect("Failed to execute replay play");
+        assert!(output.status.success());
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(stdout.contains("No terminal"));
+        assert!(!stdout.contains("\x1b[1;"));
+    }
+
+    let output = Command::new(binary_path("player"))
+        .args([&cast_file, "--no-controls", "--verbose"])
+        .output()
+        .expect("Failed to execute player");
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Mode: Timed replay"));
+
+    cleanup_files(&[&cast_file]);
+}
+
+#[test]
+fn test_replay_play_time_range() {
+    let cast_file = format!("{}.cast", test_file_name("binary_play_range"));
+    fs::write(
+        &cast_file,
+        concat!(
+            "{\"version\": 2, \"width\": 20, \"height\": 4}\n",
+            "[0.1, \"o\", \"one\\r\\n\"]\n",
+            "[0.2, \"o\", \"\\u001b[2Jtwo\\r\\n\"]\n",
+            "[0.3, \"m\", \"deploy\"]\n",
+            "[0.4, \"o\", \"three\\r\\n\"]\n",
+            "[0.5, \"o\", \"four\\r\\n\"]\n",
+        ),
+    )
+    .unwrap();
+
+    let play = |args: &[&str]| {
+        Command::new(binary_path("replay"))
+            .args(["play", &cast_file, "--no-controls"])
+            .args(args)
+            .output()
+            .expect("Failed to execute replay play")
+    };
+
+    // The skipped output is redrawn as it ended up on screen
+    let output = play(&["--from", "0.25", "--to", "0.45"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!stdout.contains("one"));
+    assert!(stdout.contains("two"));
+    assert!(stdout.contains("three"));
+    assert!(!stdout.contains("four"));
+
+    let output = play(&["--from-marker", "deploy"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("three") && stdout.contains("four"));
+
+    let output = play(&["--from-marker", "missing"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Marker not found"));
+
+    let output = play(&["--from", "soon"]);
+    assert!(!output.status.success());
+
+    cleanup_files(&[&cast_file]);
+}
+
+#[test]
+fn test_replay_play_max_delay() {
+    let cast_file = format!("{}.cast", test_file_name("binary_play_max_delay"));
+    let limited_file = format!("{}.cast", test_file_name("binary_play_idle_limit"));
+    let events = "[0.1, \"o\", \"before\\r\\n\"]\n[30.1, \"o\", \"after\\r\\n\"]\n";
+    fs::write(
+        &cast_file,
+        format!(
+            "{{\"version\": 2, \"width\": 20, \"height\": 4}}\n{}",
+            events
+        ),
+    )
+    .unwrap();
+    fs::write(
+        &limited_file,
+        format!(
+            "{{\"version\": 2, \"width\": 20, \"height\": 4, \"idle_time_limit\": 0.2}}\n{}",
+            events
+        ),
+    )
+    .unwrap();
+
+    // The 30 second pause is cut short, by the flag or by the header
+    for args in [
+        vec!["play", &cast_file, "--max-delay", "0.2"],
+        vec!["play", &limited_file],
+    ] {
+        let started = SystemTime::now();
+        let output = Command::new(binary_path("replay"))
+            .args(&args)
+            .output()
+            .expect("Failed to execute replay play");
+        assert!(output.status.success());
+        assert!(started.elapsed().unwrap() < std::time::Duration::from_secs(10));
+        assert!(String::from_utf8_lossy(&output.stdout).contains("after"));
+    }
+
+    cleanup_files(&[&cast_file, &limited_file]);
+}
+
+#[test]
+fn test_replay_dump_sanitize_levels() {
+    let output_file = test_file_name("binary_dump_levels.log");
+    let timing_file = format!("{}.timing", output_file);
+    let recorded: &[u8] = b"\x1b[?2004h\x1b[31mred\xff\xfe\x1b[0m\x07\r\nnext\r\n";
+    fs::write(&output_file, recorded).unwrap();
+    fs::write(&timing_file, format!("0.01 {}\n", recorded.len())).unwrap();
+
+    let dump = |level: Option<&str>| {
+        let mut command = Command::new(binary_path("replay"));
+        command.args(["play", &output_file, "--timing", &timing_file, "--dump"]);
+        if let Some(level) = level {
+            command.args(["--sanitize", level]);
+        }
+        let output = command.output().expect("Failed to execute replay play");
+        assert!(output.status.success(), "{:?}", level);
+        output.stdout
+    };
+
+    // Invalid UTF-8 is written unchanged by default
+    assert_eq!(dump(None), recorded);
+    assert_eq!(
+        dump(Some("strip-dangerous")),
+        b"\x1b[31mred\xff\xfe\x1b[0m\r\nnext\r\n"
+    );
+    assert_eq!(dump(Some("strip-ansi")), b"red\xff\xfe\r\nnext\r\n");
+    let text = String::from_utf8_lossy(&dump(Some("text"))).into_owned();
+    assert!(text.starts_with("red"));
+    assert!(text.contains("\nnext"));
+
+    let output = Command::new(binary_path("replay"))
+        .args(["play", &output_file, "--dump", "--sanitize", "everything"])
+        .output()
+        .expect("Failed to execute replay play");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("strip-ansi"));
+
+    cleanup_files(&[&output_file, &timing_file]);
+}
+
+#[test]
+fn test_replay_play_progress() {
+    let cast_file = format!("{}.cast", test_file_name("binary_play_progress"));
+    fs::write(
+        &cast_file,
+        concat!(
+            "{\"version\": 2, \"width\": 20, \"height\": 4}\n",
+            "[0.1, \"o\", \"one\\r\\n\"]\n",
+            "[0.2, \"m\", \"deploy\"]\n",
+            "[0.3, \"o\", \"two\\r\\n\"]\n",
+        ),
+    )
+    .unwrap();
+
+    let output = Command::new(binary_path("replay"))
+        .args(["play", &cast_file, "--progress"])
+        .output()
+        .expect("Failed to execute replay play");
+    assert!(output.status.success());
+
+    // The replayed output stays clean, the progress goes to stderr
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(stdout, "one\r\ntwo\r\n");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("next: deploy at 00:00"));
+    assert!(stderr.contains("00:00 / 00:00"));
+    assert!(stderr.trim_end().ends_with("100%  1x"));
+
+    cleanup_files(&[&cast_file]);
+}
+
+#[test]
+fn test_replay_play_loop() {
+    let cast_file = format!("{}.cast", test_file_name("binary_play_loop"));
+    fs::write(
+        &cast_file,
+        "{\"version\": 2, \"width\": 20, \"height\": 4}\n[0.05, \"o\", \"round\\r\\n\"]\n",
+    )
+    .unwrap();
+
+    let output = Command::new(binary_path("replay"))
+        .args(["play", &cast_file, "--loop", "3", "--loop-pause", "0.05"])
+        .output()
+        .expect("Failed to execute replay play");
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout)
+            .matches("round")
+            .count(),
+        3
+    );
+
+    let output = Command::new(binary_path("replay"))
+        .args(["play", &cast_file, "--loop", "--dump"])
+        .output()
+        .expect("Failed to execute replay play");
+    assert!(!output.status.success());
+
+    cleanup_files(&[&cast_file]);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_replay_play_loop_stops_on_signal() {
+    let cast_file = format!("{}.cast", test_file_name("binary_play_loop_signal"));
+    fs::write(
+        &cast_file,
+        "{\"version\": 2, \"width\": 20, \"height\": 4}\n[0.05, \"o\", \"round\\r\\n\"]\n[60, \"o\", \"late\\r\\n\"]\n",
+    )
+    .unwrap();
+
+    // Looping forever, and in the middle of a minute-long pause
+    let child = Command::new(binary_path("replay"))
+        .args(["play", &cast_file, "--loop", "--progress"])
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .expect("Failed to execute replay play");
+    std::thread::sleep(std::time::Duration::from_millis(500));
+    let status = Command::new("kill")
+        .args(["-TERM", &child.id().to_string()])
+        .status()
+        .expect("Failed to execute kill");
+    assert!(status.success());
+
+    let started = SystemTime::now();
+    let output = child.wait_with_output().unwrap();
+    assert!(started.elapsed().unwrap() < std::time::Duration::from_secs(5));
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("round"));
+    assert!(String::from_utf8_lossy(&output.stderr).ends_with('\n'));
+
+    cleanup_files(&[&cast_file]);
+}
+
+#[test]
+fn test_replay_search() {
+    let cast_file = format!("{}.cast", test_file_name("binary_search"));
+    fs::write(
+        &cast_file,
+        "{\"version\": 2, \"width\": 40, \"height\": 4}\n\
+         [0.5, \"o\", \"$ cargo build\\r\\n\"]\n\
+         [83.25, \"o\", \"\\u001b[1;31mERR\"]\n\
+         [83.5, \"o\", \"OR\\u001b[0m: linker failed\\r\\n\"]\n",
+    )
+    .unwrap();
+
+    // The match is split across chunks and colors
+    let output = Command::new(binary_path("replay"))
+        .args(["search", &cast_file, "error: \\w+", "--ignore-case"])
+        .output()
+        .expect("Failed to execute replay search");
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "00:01:23.500  ERROR: linker failed\n"
+    );
+
+    // Nothing found is a failure, like grep
+    let output = Command::new(binary_path("replay"))
+        .args(["search", &cast_file, "warning"])
+        .output()
+        .expect("Failed to execute replay search");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("No matches for 'warning'"));
+
+    let output = Command::new(binary_path("replay"))
+        .args(["search", &cast_file, "("])
+        .output()
+        .expect("Failed to execute replay search");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Invalid search pattern"));
+
+    cleanup_files(&[&cast_file]);
+}
@@ -153,7 +153,7 @@ fn test_replay_corrupted_timing_file() -> Result<()> {
     writeln!(timing, "missing_size")?;
     writeln!(timing, "-1.0 10")?; // Negative delay
     writeln!(timing, "0.1 -5")?; // Negative size
-    writeln!(timing, "")?; // Empty line
+    writeln!(timing)?; // Empty line
     writeln!(timing, "0.1 999999999999999")?; // Huge size
 
     let player = Player::new(&timing_file, &output_file)?;
@@ -302,10 +302,7 @@ fn test_concurrent_access_to_files() -> Result<()> {
 
     // Try to open the files while recorder might have them open
     // This tests file locking/sharing behavior
-    let result = OpenOptions::new()
-        .write(true)
-        .append(true)
-        .open(&output_file);
+    let result = OpenOptions::new().append(true).open(&output_file);
 
     // Behavior is platform-dependent, but should not crash
     let _ = result;
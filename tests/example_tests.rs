@@ -100,7 +100,7 @@ fn test_simple_record_replay_no_leftover_files() {
 fn test_example_compilation() {
     // Test that examples can be compiled (this is more of a build system test)
     let output = Command::new("cargo")
-        .args(&["build", "--examples"])
+        .args(["build", "--examples"])
         .current_dir(".")
         .output()
         .expect("Failed to run cargo build --examples");
@@ -120,7 +120,7 @@ fn test_example_runs_multiple_times() {
     for i in 0..3 {
         let output = Command::new(example_path("simple_record_replay"))
             .output()
-            .expect(&format!("Failed to execute example on iteration {}", i));
+            .unwrap_or_else(|_| panic!("Failed to execute example on iteration {}", i));
 
         assert!(
             output.status.success(),
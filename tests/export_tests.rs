@@ -0,0 +1,228 @@
+//! Export tests for replay-rs
+//!
+//! These tests verify rendering recorded sessions to animations and
+//! documents through the built-in terminal emulator.
+
+use anyhow::Result;
+use replay_rs::gif::{self, GifOptions};
+use replay_rs::html::{self, HtmlOptions};
+use replay_rs::svg::{self, SvgOptions};
+use replay_rs::terminal::Terminal;
+use replay_rs::text::{self, TextMode, TextOptions};
+use replay_rs::{BuiltinTheme, Event, Player, Recorder, Session, Theme};
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::process::Command;
+use std::time::Duration;
+
+/// Helper function to create a unique test file name
+fn test_file_name(base: &str) -> String {
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    format!("{}_{}", base, timestamp)
+}
+
+/// Helper function to clean up test files
+fn cleanup_files(files: &[&str]) {
+    for file in files {
+        fs::remove_file(file).unwrap_or(());
+    }
+}
+
+/// Count the image descriptors in a GIF written by the exporter
+fn gif_frames(data: &[u8]) -> usize {
+    data.windows(4)
+        .filter(|window| window == &[0x21, 0xf9, 0x04, 0x04])
+        .count()
+}
+
+#[test]
+fn test_terminal_screen_from_session() {
+    let mut session = Session::new();
+    session.events = vec![
+        Event::output(Duration::ZERO, "\x1b[1mprogress: 10%"),
+        Event::output(
+            Duration::from_millis(10),
+            "\r\x1b[Kprogress: 100%\x1b[0m\r\n",
+        ),
+        Event::output(
+            Duration::from_millis(20),
+            "\x1b[?1049hfull screen\x1b[?1049l",
+        ),
+    ];
+
+    let mut terminal = Terminal::new(20, 3);
+    for event in &session.events {
+        if let replay_rs::EventKind::Output(data) = &event.kind {
+            terminal.feed(data);
+        }
+    }
+
+    assert_eq!(terminal.screen_text(), "progress: 100%");
+    assert!(terminal.lines()[0].cells[0].style.bold);
+    assert_eq!(terminal.cursor(), (0, 1));
+}
+
+#[test]
+fn test_export_recorded_session_to_gif() -> Result<()> {
+    let output_file = test_file_name("export_gif.log");
+    let timing_file = format!("{}.timing", output_file);
+    let gif_file = format!("{}.gif", output_file);
+
+    let recorder = Recorder::new(&output_file, &timing_file)?;
+    let mut cmd = Command::new("sh");
+    cmd.args([
+        "-c",
+        "printf 'one\\n'; sleep 0.1; printf '\\033[32mtwo\\033[0m\\n'",
+    ]);
+    recorder.record_command(cmd, false)?;
+
+    let session = Player::new(&timing_file, &output_file)?.session()?;
+    let options = GifOptions {
+        theme: Theme::builtin(BuiltinTheme::Dracula),
+        scale: 1,
+        cols: Some(20),
+        rows: Some(4),
+        ..GifOptions::default()
+    };
+    gif::export(&session, BufWriter::new(File::create(&gif_file)?), &options)?;
+
+    let data = fs::read(&gif_file)?;
+    assert!(data.starts_with(b"GIF89a"));
+    // 20x4 cells of 8x10 pixels plus one cell of padding on each side
+    assert_eq!(&data[6..10], &[176, 0, 56, 0]);
+    assert_eq!(data.last(), Some(&0x3b));
+    assert!(gif_frames(&data) >= 2);
+
+    cleanup_files(&[&output_file, &timing_file, &gif_file]);
+    Ok(())
+}
+
+#[test]
+fn test_gif_skips_unchanged_frames() -> Result<()> {
+    let mut session = Session::new();
+    session.metadata.width = Some(10);
+    session.metadata.height = Some(2);
+    session.events = vec![
+        Event::output(Duration::ZERO, "same"),
+        // Output that leaves the screen as it was
+        Event::output(Duration::from_secs(1), "\x1b[s\x1b[u"),
+        Event::output(Duration::from_secs(2), "\x1b[31m\x1b[0m"),
+    ];
+
+    let mut data = Vec::new();
+    gif::export(&session, &mut data, &GifOptions::default())?;
+    assert_eq!(gif_frames(&data), 1);
+    Ok(())
+}
+
+#[test]
+fn test_export_svg_with_window_and_time_range() -> Result<()> {
+    let mut session = Session::new();
+    session.metadata.width = Some(30);
+    session.metadata.height = Some(3);
+    session.metadata.title = Some("Demo <1>".to_string());
+    session.events = vec![
+        Event::output(Duration::from_secs(1), "before\r\n"),
+        Event::output(
+            Duration::from_secs(2),
+            "\x1b[1;4;35mstyled\x1b[0m & text\r\n",
+        ),
+        Event::output(Duration::from_secs(3), "\x1b[44m \x1b[0m日本\r\n"),
+        Event::output(Duration::from_secs(9), "after\r\n"),
+    ];
+    let options = SvgOptions {
+        theme: Theme::builtin(BuiltinTheme::Light),
+        window: true,
+        start: Some(Duration::from_millis(1500)),
+        end: Some(Duration::from_secs(5)),
+        ..SvgOptions::default()
+    };
+
+    let mut data = Vec::new();
+    svg::export(&session, &mut data, &options)?;
+    let svg = String::from_utf8(data)?;
+
+    assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+    assert!(svg.contains(">Demo &lt;1&gt;</text>"));
+    assert!(svg.contains("font-weight=\"bold\""));
+    assert!(svg.contains("text-decoration=\"underline\">styled</tspan>"));
+    assert!(svg.contains("> &amp; text</tspan>"));
+    assert!(svg.contains("fill=\"#0451a5\""));
+    assert!(svg.contains(">日</tspan>"));
+    // Output before the range is fast-forwarded, output after it is dropped
+    assert!(svg.contains(">before</tspan>"));
+    assert!(!svg.contains("after"));
+    assert!(svg.contains("@keyframes"));
+    Ok(())
+}
+
+#[test]
+fn test_export_recorded_session_to_html() -> Result<()> {
+    let cast_file = test_file_name("export_html.cast");
+    let html_file = format!("{}.html", cast_file);
+
+    let recorder = Recorder::new_asciicast(&cast_file)?;
+    let mut cmd = Command::new("printf");
+    cmd.arg("\\033[32mgreen\\033[0m \\033]8;;https://example.com/docs\\033\\\\docs\\033]8;;\\033\\\\\\n");
+    recorder.record_command(cmd, false)?;
+
+    let session = Player::new("nonexistent.timing", &cast_file)?.session()?;
+    let options = HtmlOptions {
+        cols: Some(40),
+        rows: Some(5),
+        ..HtmlOptions::default()
+    };
+    html::export(
+        &session,
+        BufWriter::new(File::create(&html_file)?),
+        &options,
+    )?;
+
+    let page = fs::read_to_string(&html_file)?;
+    assert!(page.starts_with("<!DOCTYPE html>"));
+    assert!(page.contains("width: 40ch"));
+    assert!(page.contains("calc(5 * 1.25em)"));
+    assert!(page.contains(r#"<span style=\"color:#4ebf22\">green<\/span>"#));
+    assert!(page.contains(r#"<a href=\"https://example.com/docs\""#));
+    // Nothing is loaded from the network
+    assert!(!page.contains("src=\""));
+    assert!(!page.contains("<link"));
+
+    cleanup_files(&[&cast_file, &html_file]);
+    Ok(())
+}
+
+#[test]
+fn test_export_recorded_session_to_text() -> Result<()> {
+    let output_file = test_file_name("export_text.log");
+    let timing_file = format!("{}.timing", output_file);
+
+    let recorder = Recorder::new(&output_file, &timing_file)?;
+    let mut cmd = Command::new("sh");
+    cmd.args([
+        "-c",
+        "printf 'Building\\n'; printf '[==  ] 50%%\\r[====] 100%%\\n'; \
+         printf '\\033[1mdone\\033[0m\\n'",
+    ]);
+    recorder.record_command(cmd, false)?;
+    let session = Player::new(&timing_file, &output_file)?.session()?;
+
+    for mode in [TextMode::Screen, TextMode::Transcript] {
+        let mut data = Vec::new();
+        let options = TextOptions {
+            mode,
+            cols: Some(40),
+            rows: Some(2),
+            ..TextOptions::default()
+        };
+        text::export(&session, &mut data, &options)?;
+        let text = String::from_utf8(data)?;
+        assert_eq!(text, "Building\n[====] 100%\ndone\n", "{:?}", mode);
+    }
+
+    cleanup_files(&[&output_file, &timing_file]);
+    Ok(())
+}
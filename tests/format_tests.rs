@@ -0,0 +1,653 @@
+//! Session format tests for replay-rs
+//!
+//! These tests verify reading, writing and converting between the session
+//! file formats supported by the library.
+
+use anyhow::Result;
+use replay_rs::{Event, EventKind, Format, Player, Recorder, Session, SessionFiles};
+use std::fs::{self, File};
+use std::io::{BufReader, Write};
+use std::process::Command;
+use std::time::Duration;
+
+/// Helper function to create a unique test file name
+fn test_file_name(base: &str) -> String {
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    format!("{}_{}", base, timestamp)
+}
+
+/// Helper function to clean up test files
+fn cleanup_files(files: &[&str]) {
+    for file in files {
+        fs::remove_file(file).unwrap_or(());
+    }
+}
+
+#[test]
+fn test_record_asciicast() -> Result<()> {
+    let cast_file = test_file_name("format_record.cast");
+
+    let recorder = Recorder::new_asciicast(&cast_file)?;
+    let mut cmd = Command::new("printf");
+    cmd.arg("Hello asciicast\\n");
+    recorder.record_command(cmd, false)?;
+
+    let content = fs::read_to_string(&cast_file)?;
+    let mut lines = content.lines();
+    let header = lines.next().unwrap();
+    assert!(header.starts_with("{\"version\": 2"));
+    assert!(header.contains("\"command\": \"printf Hello asciicast\\\\n\""));
+    assert!(lines.any(|line| line.contains("\"o\", \"Hello asciicast\\n\"")));
+
+    let session = Session::read_asciicast(BufReader::new(File::open(&cast_file)?))?;
+    assert!(session.metadata.width.is_some());
+    assert!(session.metadata.timestamp.is_some());
+    let output: Vec<u8> = session.output().flat_map(|(_, d)| d.to_vec()).collect();
+    assert_eq!(output, b"Hello asciicast\n");
+
+    cleanup_files(&[&cast_file]);
+    Ok(())
+}
+
+#[test]
+fn test_player_plays_asciicast_directly() -> Result<()> {
+    let cast_file = test_file_name("format_play.cast");
+
+    let mut file = File::create(&cast_file)?;
+    writeln!(file, "{{\"version\": 2, \"width\": 80, \"height\": 24}}")?;
+    writeln!(file, "[0.001, \"o\", \"Hello \"]")?;
+    writeln!(file, "[0.002, \"o\", \"World\\r\\n\"]")?;
+    drop(file);
+
+    // The timing file is not needed for asciicast recordings
+    let player = Player::new("nonexistent.timing", &cast_file)?;
+    assert_eq!(player.format(), Format::Asciicast);
+    player.replay(100.0)?;
+    player.dump()?;
+
+    let session = player.session()?;
+    assert_eq!(session.events.len(), 2);
+
+    cleanup_files(&[&cast_file]);
+    Ok(())
+}
+
+#[test]
+fn test_player_plays_asciicast_v1_and_v3() -> Result<()> {
+    let v1_file = test_file_name("format_v1.json");
+    let v3_file = test_file_name("format_v3.cast");
+
+    fs::write(
+        &v1_file,
+        "{\n  \"version\": 1,\n  \"width\": 80,\n  \"height\": 24,\n  \"stdout\": [\n    [0.001, \"v1 output\\r\\n\"]\n  ]\n}\n",
+    )?;
+    fs::write(
+        &v3_file,
+        "{\"version\": 3, \"term\": {\"cols\": 80, \"rows\": 24}}\n[0.001, \"o\", \"v3 output\\r\\n\"]\n[0.001, \"x\", \"0\"]\n",
+    )?;
+
+    for (file, expected) in [(&v1_file, "v1 output"), (&v3_file, "v3 output")] {
+        let player = Player::new("nonexistent.timing", file)?;
+        assert_eq!(player.format(), Format::Asciicast);
+        player.replay(100.0)?;
+
+        let output: Vec<u8> = player
+            .session()?
+            .output()
+            .flat_map(|(_, data)| data.to_vec())
+            .collect();
+        assert_eq!(String::from_utf8(output)?, format!("{}\r\n", expected));
+    }
+
+    cleanup_files(&[&v1_file, &v3_file]);
+    Ok(())
+}
+
+#[test]
+fn test_scriptreplay_asciicast_round_trip() -> Result<()> {
+    let output_file = test_file_name("format_round_trip.log");
+    let timing_file = format!("{}.timing", output_file);
+    let cast_file = format!("{}.cast", output_file);
+    let back_output = format!("{}.back", output_file);
+    let back_timing = format!("{}.back.timing", output_file);
+
+    let recorder = Recorder::new(&output_file, &timing_file)?;
+    let mut cmd = Command::new("printf");
+    cmd.arg("\\033[32mgreen\\033[0m 中文\\n");
+    recorder.record_command(cmd, false)?;
+
+    // scriptreplay -> asciicast
+    let session = Session::read_scriptreplay(&timing_file, &output_file)?;
+    session.write_asciicast(File::create(&cast_file)?)?;
+
+    // asciicast -> scriptreplay
+    let cast = Session::read_asciicast(BufReader::new(File::open(&cast_file)?))?;
+    cast.write_scriptreplay(&back_timing, &back_output)?;
+
+    assert_eq!(fs::read(&output_file)?, fs::read(&back_output)?);
+    assert_eq!(
+        Session::read_scriptreplay(&back_timing, &back_output)?.duration(),
+        session.duration()
+    );
+
+    cleanup_files(&[
+        &output_file,
+        &timing_file,
+        &cast_file,
+        &back_output,
+        &back_timing,
+    ]);
+    Ok(())
+}
+
+#[test]
+fn test_format_from_path() {
+    assert_eq!(Format::from_path("demo.cast"), Format::Asciicast);
+    assert_eq!(Format::from_path("session.log"), Format::Scriptreplay);
+    assert_eq!(Format::from_path("nethack.ttyrec"), Format::Ttyrec);
+    assert_eq!(Format::from_path("demo.replay"), Format::Container);
+}
+
+#[test]
+fn test_record_advanced_timing() -> Result<()> {
+    let output_file = test_file_name("format_advanced.log");
+    let timing_file = format!("{}.timing", output_file);
+
+    let recorder = Recorder::new_advanced(&output_file, &timing_file)?;
+    let mut cmd = Command::new("printf");
+    cmd.arg("Hello advanced\\n");
+    recorder.record_command(cmd, false)?;
+
+    let timing = fs::read_to_string(&timing_file)?;
+    assert!(timing.contains("H 0.000000 COMMAND printf Hello advanced\\n"));
+    assert!(timing.contains(&format!("H 0.000000 OUTPUT_LOG {}", output_file)));
+    assert!(timing.lines().any(|line| line.starts_with("O ")));
+    assert!(timing.contains(" EXIT_CODE 0\n"));
+    assert!(fs::read_to_string(&output_file)?.starts_with("Script started on "));
+
+    let player = Player::new(&timing_file, &output_file)?;
+    assert_eq!(player.format(), Format::Advanced);
+    player.replay(100.0)?;
+    player.dump()?;
+
+    let session = player.session()?;
+    assert!(session.metadata.timestamp.is_some());
+    let output: Vec<u8> = session.output().flat_map(|(_, d)| d.to_vec()).collect();
+    assert_eq!(output, b"Hello advanced\n");
+    assert_eq!(
+        session.events.last().map(|e| &e.kind),
+        Some(&EventKind::Exit(0))
+    );
+
+    cleanup_files(&[&output_file, &timing_file]);
+    Ok(())
+}
+
+#[test]
+fn test_advanced_round_trip_with_input() -> Result<()> {
+    let output_file = test_file_name("format_advanced_io.out");
+    let input_file = format!("{}.in", output_file);
+    let timing_file = format!("{}.timing", output_file);
+
+    let mut session = Session::new();
+    session.metadata.width = Some(120);
+    session.metadata.height = Some(40);
+    session.metadata.command = Some("bash".to_string());
+    session.events = vec![
+        Event::output(Duration::from_millis(100), "$ "),
+        Event {
+            time: Duration::from_millis(600),
+            kind: EventKind::Input(b"ls\r".to_vec()),
+        },
+        Event {
+            time: Duration::from_millis(700),
+            kind: EventKind::Resize {
+                cols: 100,
+                rows: 30,
+            },
+        },
+        Event::output(Duration::from_millis(750), "file\r\n"),
+        Event {
+            time: Duration::from_millis(800),
+            kind: EventKind::Exit(0),
+        },
+    ];
+    session.write_advanced(&timing_file, &output_file, Some(&input_file))?;
+
+    // The input log is found through the INPUT_LOG header entry
+    let read_back = Session::read_advanced(&timing_file, &output_file, None)?;
+    assert_eq!(read_back.events, session.events);
+    assert_eq!(read_back.metadata.width, Some(120));
+    assert_eq!(read_back.metadata.command.as_deref(), Some("bash"));
+
+    cleanup_files(&[&output_file, &input_file, &timing_file]);
+    Ok(())
+}
+
+#[test]
+fn test_advanced_interop_with_util_linux() -> Result<()> {
+    let available = |tool: &str| {
+        Command::new(tool)
+            .arg("--version")
+            .output()
+            .map(|output| output.status.success())
+            .unwrap_or(false)
+    };
+    if !available("script") || !available("scriptreplay") {
+        return Ok(());
+    }
+
+    let output_file = test_file_name("format_util_linux.out");
+    let input_file = format!("{}.in", output_file);
+    let timing_file = format!("{}.timing", output_file);
+
+    // Read a recording made by util-linux script
+    let status = Command::new("script")
+        .args(["-q", "-c", "printf 'from script\\n'", "-T", &timing_file])
+        .args(["-O", &output_file, "-I", &input_file])
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::null())
+        .status()?;
+    assert!(status.success());
+
+    let player = Player::new(&timing_file, &output_file)?;
+    assert_eq!(player.format(), Format::Advanced);
+    let session = player.session()?;
+    let output: Vec<u8> = session.output().flat_map(|(_, d)| d.to_vec()).collect();
+    assert!(String::from_utf8_lossy(&output).contains("from script"));
+    assert!(session.metadata.command.is_some());
+
+    // And replay a recording written by replay-rs with util-linux scriptreplay
+    let mut session = Session::new();
+    session.events = vec![
+        Event::output(Duration::from_millis(1), "first line\n"),
+        Event::output(Duration::from_millis(2), "second line\n"),
+    ];
+    session.write_advanced(&timing_file, &output_file, None)?;
+    let replayed = Command::new("scriptreplay")
+        .args(["--log-timing", &timing_file, "--log-out", &output_file])
+        .output()?;
+    assert!(replayed.status.success());
+    // scriptreplay skips the header line and ends with a newline of its own
+    assert!(String::from_utf8_lossy(&replayed.stdout).starts_with("first line\nsecond line\n"));
+
+    cleanup_files(&[&output_file, &input_file, &timing_file]);
+    Ok(())
+}
+
+#[test]
+fn test_record_and_play_ttyrec() -> Result<()> {
+    let ttyrec_file = format!("{}.ttyrec", test_file_name("format_record"));
+
+    let recorder = Recorder::new_ttyrec(&ttyrec_file)?;
+    let mut cmd = Command::new("printf");
+    cmd.arg("Hello ttyrec\\n");
+    recorder.record_command(cmd, false)?;
+
+    let bytes = fs::read(&ttyrec_file)?;
+    let len = u32::from_le_bytes([bytes[8], bytes[9], bytes[10], bytes[11]]) as usize;
+    assert_eq!(&bytes[12..12 + len], b"Hello ttyrec\n");
+
+    // ttyrec files are recognized without a timing file
+    let player = Player::new("nonexistent.timing", &ttyrec_file)?;
+    assert_eq!(player.format(), Format::Ttyrec);
+    player.replay(100.0)?;
+    player.dump()?;
+
+    let session = player.session()?;
+    assert!(session.metadata.timestamp.is_some());
+    let output: Vec<u8> = session.output().flat_map(|(_, d)| d.to_vec()).collect();
+    assert_eq!(output, b"Hello ttyrec\n");
+
+    cleanup_files(&[&ttyrec_file]);
+    Ok(())
+}
+
+#[test]
+fn test_ttyrec_detected_without_extension() -> Result<()> {
+    let ttyrec_file = test_file_name("format_ttyrec_capture");
+
+    let mut session = Session::new();
+    session.metadata.timestamp = Some(1_700_000_000);
+    session.events = vec![
+        Event::output(Duration::from_millis(10), "first\r\n"),
+        Event::output(Duration::from_millis(20), "second\r\n"),
+    ];
+    session.write_ttyrec(File::create(&ttyrec_file)?)?;
+
+    let player = Player::open(&ttyrec_file)?;
+    assert_eq!(player.format(), Format::Ttyrec);
+    let read_back = player.session()?;
+    assert_eq!(read_back.metadata.timestamp, Some(1_700_000_000));
+    assert_eq!(read_back.duration(), Duration::from_millis(10));
+
+    // Plain text is not mistaken for ttyrec
+    let text_file = test_file_name("format_not_ttyrec.log");
+    fs::write(&text_file, "Hello World\r\nThis is a typescript\r\n")?;
+    assert!(Player::new("nonexistent.timing", &text_file).is_err());
+
+    // A timing file given for it has to exist unless the name says ttyrec
+    let error = Player::new("nonexistent.timing", &ttyrec_file).unwrap_err();
+    assert!(error.to_string().contains("Timing file not found"));
+
+    cleanup_files(&[&ttyrec_file, &text_file]);
+    Ok(())
+}
+
+#[test]
+fn test_record_and_play_container() -> Result<()> {
+    let container_file = format!("{}.replay", test_file_name("format_container"));
+
+    let recorder = Recorder::new_container(&container_file)?;
+    let mut cmd = Command::new("printf");
+    cmd.arg("Hello container\\n");
+    recorder.record_command(cmd, false)?;
+
+    // The whole session lives in one file
+    assert!(!std::path::Path::new(&format!("{}.timing", container_file)).exists());
+
+    let player = Player::new("nonexistent.timing", &container_file)?;
+    assert_eq!(player.format(), Format::Container);
+    player.replay(100.0)?;
+    player.dump()?;
+
+    let session = player.session()?;
+    assert_eq!(
+        session.metadata.command.as_deref(),
+        Some("printf Hello container\\n")
+    );
+    let output: Vec<u8> = session.output().flat_map(|(_, d)| d.to_vec()).collect();
+    assert_eq!(output, b"Hello container\n");
+    assert!(session
+        .events
+        .iter()
+        .any(|event| matches!(event.kind, EventKind::Exit(0))));
+
+    // The archive is a plain tar whose members scriptreplay understands
+    let dir = test_file_name("format_container_extract");
+    fs::create_dir(&dir)?;
+    let tar = Command::new("tar")
+        .args(["-xf", &container_file, "-C", &dir])
+        .status();
+    if matches!(tar, Ok(status) if status.success()) {
+        let player = Player::new(&format!("{}/timing", dir), &format!("{}/typescript", dir))?;
+        assert_eq!(player.format(), Format::Scriptreplay);
+        let output: Vec<u8> = player
+            .session()?
+            .output()
+            .flat_map(|(_, d)| d.to_vec())
+            .collect();
+        assert_eq!(output, b"Hello container\n");
+        assert!(fs::read_to_string(format!("{}/replay.json", dir))?.contains("\"version\": 1"));
+    }
+
+    fs::remove_dir_all(&dir).unwrap_or(());
+    cleanup_files(&[&container_file]);
+    Ok(())
+}
+
+#[test]
+fn test_session_format_round_trip() -> Result<()> {
+    let mut session = Session::new();
+    session.metadata.timestamp = Some(1_700_000_000);
+    session.events = vec![
+        Event::output(Duration::from_millis(100), "one\r\n"),
+        Event::output(Duration::from_millis(350), "two\r\n"),
+    ];
+
+    for format in [
+        Format::Scriptreplay,
+        Format::Asciicast,
+        Format::Advanced,
+        Format::Ttyrec,
+        Format::Container,
+    ] {
+        let handler = format.session_format();
+        let data_file = format!(
+            "{}.{}",
+            test_file_name(&format!("format_trait_{}", handler.name())),
+            format.extension()
+        );
+        let files = SessionFiles::new(&data_file);
+        handler.write(&session, &files)?;
+
+        let read_back = handler.read(&files)?;
+        let output: Vec<(Duration, Vec<u8>)> = read_back
+            .output()
+            .map(|(time, data)| (time, data.to_vec()))
+            .collect();
+        let expected: Vec<(Duration, Vec<u8>)> = session
+            .output()
+            .map(|(time, data)| (time, data.to_vec()))
+            .collect();
+        // ttyrec times start at the first frame
+        if format == Format::Ttyrec {
+            assert_eq!(output[1].0 - output[0].0, Duration::from_millis(250));
+        } else {
+            assert_eq!(output, expected, "{}", handler.name());
+        }
+
+        // The player sees the same session through the same files
+        let player = Player::new(&files.timing_file(), &data_file)?;
+        assert_eq!(player.format(), format);
+        assert_eq!(player.session()?, read_back);
+
+        cleanup_files(&[&data_file, &files.timing_file()]);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_player_open_detects_formats() -> Result<()> {
+    let mut session = Session::new();
+    session.metadata.timestamp = Some(1_700_000_000);
+    session.events = vec![
+        Event::output(Duration::from_millis(100), "one\r\n"),
+        Event::output(Duration::from_millis(350), "two\r\n"),
+    ];
+
+    // Names that don't hint at the format
+    let cast_file = test_file_name("format_open_cast.json");
+    session.write_asciicast(File::create(&cast_file)?)?;
+    let ttyrec_file = test_file_name("format_open_ttyrec.dat");
+    session.write_ttyrec(File::create(&ttyrec_file)?)?;
+    let container_file = test_file_name("format_open_container.bin");
+    Format::Container
+        .session_format()
+        .write(&session, &SessionFiles::new(&container_file))?;
+
+    // Typescripts are found through a timing file named after their stem
+    let typescript_file = format!("{}.log", test_file_name("format_open_typescript"));
+    let stem_timing = typescript_file.replace(".log", ".timing");
+    session.write_scriptreplay(&stem_timing, &typescript_file)?;
+    let advanced_file = test_file_name("format_open_advanced.out");
+    let advanced_timing = format!("{}.timing", advanced_file);
+    session.write_advanced(&advanced_timing, &advanced_file, None)?;
+
+    for (file, format) in [
+        (&cast_file, Format::Asciicast),
+        (&ttyrec_file, Format::Ttyrec),
+        (&container_file, Format::Container),
+        (&typescript_file, Format::Scriptreplay),
+        (&advanced_file, Format::Advanced),
+    ] {
+        let player = Player::open(file)?;
+        assert_eq!(player.format(), format, "{}", file);
+        let output: Vec<u8> = player
+            .session()?
+            .output()
+            .flat_map(|(_, d)| d.to_vec())
+            .collect();
+        assert_eq!(output, b"one\r\ntwo\r\n", "{}", file);
+    }
+    assert_eq!(
+        Player::open(&typescript_file)?.files().timing_file(),
+        stem_timing
+    );
+
+    cleanup_files(&[
+        &cast_file,
+        &ttyrec_file,
+        &container_file,
+        &typescript_file,
+        &stem_timing,
+        &advanced_file,
+        &advanced_timing,
+    ]);
+    Ok(())
+}
+
+#[test]
+fn test_player_open_compressed() -> Result<()> {
+    let mut session = Session::new();
+    session.events = vec![
+        Event::output(Duration::from_millis(100), "compressed\r\n"),
+        Event::output(Duration::from_millis(300), "session\r\n"),
+    ];
+
+    let cast_file = format!("{}.cast", test_file_name("format_gzip"));
+    session.write_asciicast(File::create(&cast_file)?)?;
+    let ttyrec_file = format!("{}.ttyrec", test_file_name("format_gzip"));
+    session.write_ttyrec(File::create(&ttyrec_file)?)?;
+    let typescript_file = format!("{}.log", test_file_name("format_gzip"));
+    let timing_file = format!("{}.timing", typescript_file);
+    session.write_scriptreplay(&timing_file, &typescript_file)?;
+    let advanced_file = format!("{}.log", test_file_name("format_gzip_advanced"));
+    let advanced_timing = format!("{}.timing", advanced_file);
+
+    // Skip when there is no gzip to compress with
+    let gzip = |file: &str| {
+        Command::new("gzip")
+            .args(["-f", file])
+            .status()
+            .is_ok_and(|status| status.success())
+    };
+    if gzip(&cast_file) && gzip(&ttyrec_file) && gzip(&typescript_file) {
+        for (file, format) in [
+            (&cast_file, Format::Asciicast),
+            (&ttyrec_file, Format::Ttyrec),
+            (&typescript_file, Format::Scriptreplay),
+        ] {
+            let player = Player::open(&format!("{}.gz", file))?;
+            assert_eq!(player.format(), format);
+            player.dump()?;
+            let output: Vec<u8> = player
+                .session()?
+                .output()
+                .flat_map(|(_, d)| d.to_vec())
+                .collect();
+            assert_eq!(output, b"compressed\r\nsession\r\n");
+        }
+
+        // The timing file may be compressed along with the typescript
+        assert!(gzip(&timing_file));
+        let player = Player::open(&format!("{}.gz", typescript_file))?;
+        assert_eq!(player.files().timing_file(), format!("{}.gz", timing_file));
+        assert_eq!(player.session()?.duration(), Duration::from_millis(300));
+
+        // Advanced timing logs are read through the decompressor as well
+        session.write_advanced(&advanced_timing, &advanced_file, None)?;
+        assert!(gzip(&advanced_file) && gzip(&advanced_timing));
+        let player = Player::open(&format!("{}.gz", advanced_file))?;
+        assert_eq!(player.format(), Format::Advanced);
+        assert_eq!(player.session()?.output().count(), 2);
+    }
+
+    for file in [
+        &cast_file,
+        &ttyrec_file,
+        &typescript_file,
+        &timing_file,
+        &advanced_file,
+        &advanced_timing,
+    ] {
+        cleanup_files(&[file, &format!("{}.gz", file)]);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_player_open_unknown_format() -> Result<()> {
+    let text_file = test_file_name("format_unknown.txt");
+    fs::write(&text_file, "Just some notes\nnot a recording\n")?;
+    let error = Player::open(&text_file).unwrap_err().to_string();
+    assert!(error.contains("Unrecognized session format"), "{}", error);
+    assert!(
+        error.contains(&format!("{}.timing", text_file)),
+        "{}",
+        error
+    );
+
+    let xz_file = test_file_name("format_unknown.xz");
+    fs::write(&xz_file, b"\xfd7zXZ\x00\x00\x04")?;
+    let error = Player::open(&xz_file).unwrap_err().to_string();
+    assert!(error.contains("compressed with xz"), "{}", error);
+
+    let empty_file = test_file_name("format_unknown_empty");
+    fs::write(&empty_file, "")?;
+    let error = Player::open(&empty_file).unwrap_err().to_string();
+    assert!(error.contains("is empty"), "{}", error);
+
+    assert!(Player::open("format_missing_file.cast").is_err());
+
+    cleanup_files(&[&text_file, &xz_file, &empty_file]);
+    Ok(())
+}
+
+#[test]
+fn test_player_convert_between_formats() -> Result<()> {
+    let mut session = Session::new();
+    session.metadata.timestamp = Some(1_700_000_000);
+    session.events = vec![
+        Event::output(Duration::from_millis(100), "one\r\n"),
+        Event {
+            time: Duration::from_millis(200),
+            kind: EventKind::Resize {
+                cols: 100,
+                rows: 30,
+            },
+        },
+        Event::output(Duration::from_millis(350), "two\r\n"),
+    ];
+
+    let formats = [
+        Format::Scriptreplay,
+        Format::Asciicast,
+        Format::Advanced,
+        Format::Ttyrec,
+        Format::Container,
+    ];
+    for from in formats {
+        let source = SessionFiles::new(&test_file_name(&format!(
+            "format_convert_{}",
+            from.session_format().name()
+        )));
+        from.session_format().write(&session, &source)?;
+        let player = Player::with_format(from, &source);
+
+        for to in formats {
+            let target = SessionFiles::new(&test_file_name(&format!(
+                "format_convert_{}_{}",
+                from.session_format().name(),
+                to.session_format().name()
+            )));
+            player.convert(to, &target)?;
+
+            let converted = to.session_format().read(&target)?;
+            let output: Vec<u8> = converted.output().flat_map(|(_, d)| d.to_vec()).collect();
+            assert_eq!(
+                output,
+                b"one\r\ntwo\r\n",
+                "{} -> {}",
+                from.session_format().name(),
+                to.session_format().name()
+            );
+            cleanup_files(&[&target.data, &target.timing_file()]);
+        }
+        cleanup_files(&[&source.data, &source.timing_file()]);
+    }
+    Ok(())
+}
@@ -4,12 +4,13 @@
 //! terminal sessions, including real command execution and file I/O operations.
 
 use anyhow::Result;
-use replay_rs::{clean_for_display, Player, Recorder};
+use replay_rs::{clean_for_display, Player, Recorder, ReplayOptions, Sanitize};
 use std::fs::{self, File};
 use std::io::{Read, Write};
 use std::path::Path;
 use std::process::Command;
 use std::thread;
+use std::time::{Duration, Instant};
 
 /// Helper function to create a unique test file name
 fn test_file_name(base: &str) -> String {
@@ -115,7 +116,7 @@ fn test_record_command_with_arguments() -> Result<()> {
     // Record printf command with multiple arguments
     let recorder = Recorder::new(&output_file, &timing_file)?;
     let mut cmd = Command::new("printf");
-    cmd.args(&[
+    cmd.args([
         "Arg1: %s\\nArg2: %s\\nArg3: %s\\n",
         "first",
         "second",
@@ -161,6 +162,123 @@ fn test_replay_speed_variations() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_replay_to_writer() -> Result<()> {
+    let output_file = test_file_name("integration_writer.log");
+    let timing_file = format!("{}.timing", output_file);
+
+    // Output split in the middle of a multibyte character and a sequence
+    fs::write(&output_file, "résumé \x1b[31mred\x1b[0m\n")?;
+    fs::write(&timing_file, "0.01 4\n0.01 10\n0.01 8\n")?;
+    let recorded = fs::read(&output_file)?;
+
+    let player = Player::new(&timing_file, &output_file)?;
+
+    // Without the banner the replayed bytes are exactly the recorded ones
+    let mut replayed = Vec::new();
+    let options = ReplayOptions {
+        speed: 100.0,
+        banner: false,
+        ..ReplayOptions::default()
+    };
+    player.replay_to(&mut replayed, &options)?;
+    assert_eq!(replayed, recorded);
+
+    let mut replayed = Vec::new();
+    player.replay_to(&mut replayed, &ReplayOptions::default())?;
+    let replayed = String::from_utf8(replayed)?;
+    assert!(replayed.starts_with("🎬 Playing back session"));
+    assert!(replayed.ends_with("\x1b[0m\n\n"));
+
+    let mut dumped = Vec::new();
+    player.dump_to(&mut dumped, Sanitize::Raw)?;
+    assert_eq!(dumped, recorded);
+
+    // Keyboard controls need the terminal
+    let options = ReplayOptions {
+        interactive: true,
+        ..ReplayOptions::default()
+    };
+    assert!(player.replay_to(&mut Vec::new(), &options).is_err());
+
+    cleanup_files(&[&output_file, &timing_file]);
+    Ok(())
+}
+
+#[test]
+fn test_replay_restores_terminal() -> Result<()> {
+    let output_file = test_file_name("integration_restore.log");
+    let timing_file = format!("{}.timing", output_file);
+
+    // A full-screen program cut off before switching its modes back
+    fs::write(&output_file, "\x1b[?1049h\x1b[?25l\x1b[?1000h\x1b[1mtop\n")?;
+    fs::write(&timing_file, "0.01 15\n0.01 15\n")?;
+    let recorded = fs::read(&output_file)?;
+
+    let player = Player::new(&timing_file, &output_file)?;
+    let mut options = ReplayOptions {
+        speed: 100.0,
+        banner: false,
+        ..ReplayOptions::default()
+    };
+
+    let mut replayed = Vec::new();
+    player.replay_to(&mut replayed, &options)?;
+    let (played, reset) = replayed.split_at(recorded.len());
+    assert_eq!(played, &recorded[..]);
+    assert_eq!(reset, b"\x1b[?1049l\x1b[?25h\x1b[?1000l\x1b[0m");
+
+    options.restore_terminal = false;
+    let mut replayed = Vec::new();
+    player.replay_to(&mut replayed, &options)?;
+    assert_eq!(replayed, recorded);
+
+    cleanup_files(&[&output_file, &timing_file]);
+    Ok(())
+}
+
+#[test]
+fn test_player_events_iterator() -> Result<()> {
+    let output_file = test_file_name("integration_events.log");
+    let timing_file = format!("{}.timing", output_file);
+
+    fs::write(&output_file, "firstsecond")?;
+    fs::write(&timing_file, "0.5 5\n0.25 6\nbroken line\n")?;
+
+    let player = Player::new(&timing_file, &output_file)?;
+    let mut events = player.events()?;
+
+    let first = events.next().unwrap()?;
+    assert_eq!(first.time, Duration::from_millis(500));
+    assert_eq!(first.delay, Duration::from_millis(500));
+    assert_eq!(first.data, b"first");
+
+    let second = events.next().unwrap()?;
+    assert_eq!(second.time, Duration::from_millis(750));
+    assert_eq!(second.delay, Duration::from_millis(250));
+    assert_eq!(second.data, b"second");
+
+    // A bad timing line comes out as an error item, not a failed call
+    assert!(events.next().unwrap().is_err());
+
+    // The scheduled iterator waits for each block
+    let options = ReplayOptions {
+        speed: 5.0,
+        end: Some(Duration::from_secs(1)),
+        ..ReplayOptions::default()
+    };
+    let started = Instant::now();
+    let scheduled: Vec<_> = player
+        .scheduled_events(&options)?
+        .take(2)
+        .collect::<Result<_>>()?;
+    assert_eq!(scheduled.len(), 2);
+    assert!(started.elapsed() >= Duration::from_millis(150));
+
+    cleanup_files(&[&output_file, &timing_file]);
+    Ok(())
+}
+
 #[test]
 fn test_record_failing_command() {
     let output_file = test_file_name("integration_fail.log");
@@ -186,7 +304,7 @@ fn test_large_output_recording() -> Result<()> {
     // Create a command that generates substantial output
     let recorder = Recorder::new(&output_file, &timing_file)?;
     let mut cmd = Command::new("seq");
-    cmd.args(&["1", "100"]); // Generate numbers 1-100
+    cmd.args(["1", "100"]); // Generate numbers 1-100
     recorder.record_command(cmd, false)?;
 
     // Verify substantial content was recorded
@@ -249,7 +367,7 @@ fn test_concurrent_recording() -> Result<()> {
 
                 let recorder = Recorder::new(&output_file, &timing_file)?;
                 let mut cmd = Command::new("echo");
-                cmd.arg(&format!("Concurrent test {}", i));
+                cmd.arg(format!("Concurrent test {}", i));
                 recorder.record_command(cmd, false)?;
 
                 // Verify files exist
@@ -422,7 +540,7 @@ fn test_stress_multiple_sessions() -> Result<()> {
         // Record session
         let recorder = Recorder::new(&output_file, &timing_file)?;
         let mut cmd = Command::new("echo");
-        cmd.arg(&format!("Stress test session {}", i));
+        cmd.arg(format!("Stress test session {}", i));
         recorder.record_command(cmd, false)?;
 
         // Verify and replay
@@ -145,7 +145,7 @@ fn test_locale_and_encoding() -> Result<()> {
         cmd.env("LANG", locale);
         cmd.env("LC_ALL", locale);
         cmd.arg("-c");
-        cmd.arg(&format!("echo '{}'", test_text));
+        cmd.arg(format!("echo '{}'", test_text));
 
         let result = recorder.record_command(cmd, false);
 
@@ -336,7 +336,7 @@ fn test_path_separators() -> Result<()> {
 
     // Use appropriate path separator for the platform
     let path_sep = if cfg!(windows) { "\\" } else { "/" };
-    cmd.arg(&format!("echo 'Path separator: {}'", path_sep));
+    cmd.arg(format!("echo 'Path separator: {}'", path_sep));
 
     recorder.record_command(cmd, false)?;
 
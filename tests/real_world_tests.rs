@@ -47,7 +47,7 @@ fn test_record_git_operations() -> Result<()> {
 
     let recorder = Recorder::new(&output_file, &timing_file)?;
     let mut cmd = Command::new("git");
-    cmd.args(&["--version"]);
+    cmd.args(["--version"]);
 
     recorder.record_command(cmd, false)?;
 
@@ -93,7 +93,7 @@ fn test_record_cargo_build_output() -> Result<()> {
     let recorder = Recorder::new(&output_file, &timing_file)?;
     let mut cmd = Command::new("cargo");
     cmd.current_dir(&temp_dir);
-    cmd.args(&["check", "--color=always"]);
+    cmd.args(["check", "--color=always"]);
 
     let _result = recorder.record_command(cmd, false);
 
@@ -193,7 +193,7 @@ fn test_record_interactive_python() -> Result<()> {
     } else {
         "python"
     };
-    cmd.arg(&format!(
+    cmd.arg(format!(
         "echo 'print(\"Hello from Python\")
 print(2 + 2)
 exit()' | {}",
@@ -224,7 +224,7 @@ fn test_record_curl_download() -> Result<()> {
     let recorder = Recorder::new(&output_file, &timing_file)?;
     let mut cmd = Command::new("curl");
     // Use a small, reliable URL
-    cmd.args(&["-I", "https://example.com"]);
+    cmd.args(["-I", "https://example.com"]);
 
     let result = recorder.record_command(cmd, false);
 
@@ -281,7 +281,7 @@ fn test_record_npm_output() -> Result<()> {
 
     let recorder = Recorder::new(&output_file, &timing_file)?;
     let mut cmd = Command::new("npm");
-    cmd.args(&["--version"]);
+    cmd.args(["--version"]);
 
     recorder.record_command(cmd, false)?;
 
@@ -305,7 +305,7 @@ fn test_record_docker_output() -> Result<()> {
 
     let recorder = Recorder::new(&output_file, &timing_file)?;
     let mut cmd = Command::new("docker");
-    cmd.args(&["--version"]);
+    cmd.args(["--version"]);
 
     recorder.record_command(cmd, false)?;
 
@@ -392,7 +392,7 @@ fn test_record_log_tailing() -> Result<()> {
 
     let recorder = Recorder::new(&output_file, &timing_file)?;
     let mut cmd = Command::new("tail");
-    cmd.args(&["-5", &temp_log]);
+    cmd.args(["-5", &temp_log]);
 
     recorder.record_command(cmd, false)?;
 
@@ -184,7 +184,7 @@ fn test_concurrent_recording_sessions() -> Result<()> {
             let recorder = Recorder::new(&output_file, &timing_file)?;
             let mut cmd = Command::new("sh");
             cmd.arg("-c");
-            cmd.arg(&format!(
+            cmd.arg(format!(
                 "echo 'Session {}'; for j in 1 2 3; do echo \"Line $j\"; done",
                 i
             ));